@@ -0,0 +1,61 @@
+// benches/pixel_throughput.rs
+
+// Compares pixel update throughput with write-through and write-behind persistence on sled
+
+// For your knowledge
+// Run with: cargo bench --bench pixel_throughput
+// Each iteration applies one batch of pixel updates. Write-through commits (and fsyncs) every update,
+// write-behind only marks rows dirty and commits them all with a single flush, like one tick of the background task
+// The databases live in the system temp dir and are removed afterwards
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Duration;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use backend::server::cache::{CanvasCache, WritePolicy};
+use backend::server::store::SledStore;
+use protocol::{Rgb, CANVAS_HEIGHT, CANVAS_WIDTH};
+
+const UPDATES_PER_BATCH: u32 = 64;
+
+fn open_cache(name: &str, policy: WritePolicy) -> (CanvasCache, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(format!("rustycanvas_bench_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    let store = SledStore::open(path.to_str().unwrap()).expect("Failed to open the bench database");
+    (CanvasCache::load(Arc::new(store), policy).unwrap(), path)
+}
+
+fn apply_batch(cache: &CanvasCache, round: u32) {
+    for i in 0..UPDATES_PER_BATCH {
+        let n = round.wrapping_mul(UPDATES_PER_BATCH).wrapping_add(i);
+        let (x, y) = (n % CANVAS_WIDTH, (n / CANVAS_WIDTH) % CANVAS_HEIGHT);
+        cache.set_pixel(x, y, Rgb::new(n as u8, 0x80, 0xFF)).unwrap();
+    }
+}
+
+fn pixel_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pixel_updates");
+    group.throughput(Throughput::Elements(UPDATES_PER_BATCH as u64));
+
+    let policies = [
+        ("write_through", WritePolicy::WriteThrough),
+        ("write_behind", WritePolicy::WriteBehind { interval: Duration::from_millis(100) }),
+    ];
+    for (name, policy) in policies {
+        let (cache, path) = open_cache(name, policy);
+        let mut round = 0u32;
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                apply_batch(&cache, round);
+                round = round.wrapping_add(1);
+                black_box(cache.flush().unwrap());
+            })
+        });
+        drop(cache);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+    group.finish();
+}
+
+criterion_group!(benches, pixel_throughput);
+criterion_main!(benches);
@@ -1,20 +1,111 @@
 use axum::serve;
 use std::net::SocketAddr;
+use std::process::ExitCode;
 use tokio::net::TcpListener;
+use crate::server::discovery;
 use crate::server::state::init_app_state;
+use crate::server::cache::{self, WritePolicy};
+use crate::server::store::StoreConfig;
+use crate::server::shutdown::{self, ShutdownConfig};
+use crate::server::schedule;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
 
 mod server;
 
 #[tokio::main]
-async fn main() {
-    let app_state = init_app_state("data/canvas_db");
+async fn main() -> ExitCode {
+    // Logs go to stdout. RUST_LOG picks what is logged (default "info", "debug" adds every poll),
+    // LOG_FORMAT=json writes one JSON object per line for log collectors
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    if std::env::var("LOG_FORMAT").is_ok_and(|format| format == "json") {
+        tracing_subscriber::fmt().json().with_env_filter(filter).init();
+    } else {
+        tracing_subscriber::fmt().with_env_filter(filter).init();
+    }
 
-    let app = server::routes::create_router()
-        .with_state(app_state);
+    // Where the canvas is kept: CANVAS_STORE=sled|sqlite|memory and CANVAS_DB_PATH (see server/store/mod.rs)
+    let store_config = StoreConfig::from_env().unwrap_or_else(|e| panic!("{}", e));
+    let store = store_config.open().unwrap_or_else(|e| panic!("Failed to open {:?}: {}", store_config, e));
+    info!(store = ?store_config, "Using store");
+    // How often canvas updates are committed to the store: CANVAS_FLUSH_MS, 0 writes each one through (see server/cache.rs)
+    let policy = WritePolicy::from_env().unwrap_or_else(|e| panic!("{}", e));
+    info!(?policy, "Canvas write policy");
+    // How long clients are warned and requests may drain on Ctrl-C/SIGTERM (see server/shutdown.rs)
+    let shutdown_config = ShutdownConfig::from_env().unwrap_or_else(|e| panic!("{}", e));
+    let mut app_state = init_app_state(store, policy);
+    // An admin may have frozen the board before the restart, it stays frozen (see server/mode.rs)
+    let mode = server::mode::current_mode(&app_state);
+    if mode.mode != protocol::BoardMode::Open {
+        warn!(mode = ?mode.mode, "Board is frozen, pixels are refused until an admin reopens it");
+    }
+    let canvas = app_state.canvas.clone();
+    let shutdown_flag = app_state.shutdown.clone();
+    cache::spawn_write_behind(canvas.clone());
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+    // Protect admin routes when ADMIN_TOKEN is set in the environment
+    app_state.admin_token = std::env::var("ADMIN_TOKEN").ok().filter(|token| !token.is_empty());
+
+    // Opens, closes and clears the board for timed events (see server/schedule.rs)
+    schedule::spawn_scheduler(app_state.clone());
+
+    let app = server::routes::create_router(app_state);
+
+    // Only reachable from this machine by default. Set BIND_ADDR=0.0.0.0:8080 so LED boards on the LAN can connect
+    let addr: SocketAddr = std::env::var("BIND_ADDR")
+        .ok()
+        .and_then(|addr| addr.parse().ok())
+        .unwrap_or(SocketAddr::from(([127, 0, 0, 1], 8080)));
     let listener = TcpListener::bind(addr).await.unwrap();
-    println!("Listening on http://{}", addr);
+    info!("Listening on http://{}", addr);
+
+    // Let boards find us on the LAN (kept alive until the server exits). Pointless when only bound to loopback
+    let instance_name = std::env::var("MDNS_NAME").unwrap_or(discovery::DEFAULT_INSTANCE_NAME.to_string());
+    let _mdns = if addr.ip().is_loopback() {
+        None
+    } else {
+        match discovery::advertise(&instance_name, addr.port()) {
+            Ok(daemon) => {
+                info!(instance = %instance_name, service = protocol::MDNS_SERVICE_TYPE, "Advertising over mDNS");
+                Some(daemon)
+            }
+            Err(e) => {
+                warn!("mDNS advertisement failed, boards will need the server address: {}", e);
+                None
+            }
+        }
+    };
+
+    // Ctrl-C or SIGTERM: warn clients, stop accepting connections and let running requests finish,
+    // then write whatever the write-behind hasn't committed yet
+    let (signalled_tx, signalled_rx) = tokio::sync::oneshot::channel();
+    // Connect info gives the metrics the clients' addresses (see server/metrics.rs)
+    let server = serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown::notice_period(shutdown_flag, shutdown_config, signalled_tx));
+    let drained = tokio::select! {
+        biased;
+        result = server => result,
+        _ = async {
+            let _ = signalled_rx.await;
+            tokio::time::sleep(shutdown_config.drain).await;
+        } => {
+            warn!(drain = ?shutdown_config.drain, "Requests still running, closing them");
+            Ok(())
+        }
+    };
 
-    serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+    let mut status = ExitCode::SUCCESS;
+    if let Err(e) = drained {
+        error!("Server error: {}", e);
+        status = ExitCode::FAILURE;
+    }
+    match canvas.flush() {
+        Ok(rows) => info!(rows, "Saved the canvas"),
+        Err(e) => {
+            error!("Failed to save the canvas on shutdown: {}", e);
+            status = ExitCode::FAILURE;
+        }
+    }
+    info!("Server stopped{}", if status == ExitCode::SUCCESS { ", bye" } else { " with errors" });
+    status
+}
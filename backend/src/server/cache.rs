@@ -0,0 +1,181 @@
+// server/cache.rs
+
+// This module keeps the authoritative canvas in memory, in front of the CanvasStore
+
+// For your knowledge
+// Writing every pixel straight to disk means one fsync per POST /pixel, so the disk sets the request rate
+// Instead the canvas lives in memory (GET /canvas never touches the store) and each update only marks its row dirty
+// With WritePolicy::WriteBehind a background task commits every dirty row in one batch each interval (group commit),
+// so many updates share one fsync. A crash loses at most the last interval of updates. CanvasCache::flush()
+// commits whatever is left, the server calls it on shutdown
+// WritePolicy::WriteThrough writes each update before answering, like the server used to. It's what the tests use
+// Choose with CANVAS_FLUSH_MS: 0 for write-through, otherwise the interval in ms (default 100)
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use protocol::{Rgb, CANVAS_HEIGHT};
+use crate::server::store::{CanvasStore, PackedCanvas};
+use crate::server::error::AppError;
+
+const DEFAULT_FLUSH_MS: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePolicy {
+    WriteThrough,
+    WriteBehind { interval: Duration },
+}
+
+impl WritePolicy {
+    // Read from CANVAS_FLUSH_MS
+    pub fn from_env() -> Result<WritePolicy, String> {
+        WritePolicy::parse(std::env::var("CANVAS_FLUSH_MS").ok().as_deref())
+    }
+
+    pub fn parse(flush_ms: Option<&str>) -> Result<WritePolicy, String> {
+        let flush_ms = match flush_ms.map(str::trim).filter(|value| !value.is_empty()) {
+            Some(value) => value.parse().map_err(|_| format!("CANVAS_FLUSH_MS must be a number of ms, got '{}'", value))?,
+            None => DEFAULT_FLUSH_MS,
+        };
+        Ok(match flush_ms {
+            0 => WritePolicy::WriteThrough,
+            ms => WritePolicy::WriteBehind { interval: Duration::from_millis(ms) },
+        })
+    }
+}
+
+pub struct CanvasCache {
+    store: Arc<dyn CanvasStore>,
+    policy: WritePolicy,
+    canvas: RwLock<PackedCanvas>,
+    // Rows changed since they were last committed
+    dirty: Mutex<BTreeSet<u32>>,
+    // Held while committing or clearing, so a commit of old rows can't land after a clear
+    commit: Mutex<()>,
+}
+
+impl CanvasCache {
+    // Read the canvas from the store once, every read after this is served from memory
+    pub fn load(store: Arc<dyn CanvasStore>, policy: WritePolicy) -> Result<CanvasCache, AppError> {
+        let canvas = store.load_canvas()?;
+        Ok(CanvasCache {
+            store,
+            policy,
+            canvas: RwLock::new(canvas),
+            dirty: Mutex::new(BTreeSet::new()),
+            commit: Mutex::new(()),
+        })
+    }
+
+    pub fn policy(&self) -> WritePolicy {
+        self.policy
+    }
+
+    // A copy of the current canvas
+    pub fn snapshot(&self) -> PackedCanvas {
+        match self.canvas.read() {
+            Ok(canvas) => canvas.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    pub fn set_pixel(&self, x: u32, y: u32, color: Rgb) -> Result<(), AppError> {
+        match self.policy {
+            // The row goes to the store first, so if that fails the board is left as it was
+            WritePolicy::WriteThrough => {
+                let _commit = self.commit.lock().map_err(|_| AppError::DbWriteError)?;
+                let mut canvas = self.canvas.write().map_err(|_| AppError::DbWriteError)?;
+                let row = canvas.row_with(x, y, color).ok_or(AppError::OutOfBounds)?;
+                self.store.write_rows(&[(y, row.clone())])?;
+                canvas.set_row(y, &row);
+                Ok(())
+            }
+            WritePolicy::WriteBehind { .. } => {
+                let mut canvas = self.canvas.write().map_err(|_| AppError::DbWriteError)?;
+                if !canvas.set(x, y, color) {
+                    return Err(AppError::OutOfBounds);
+                }
+                self.dirty.lock().map_err(|_| AppError::DbWriteError)?.insert(y);
+                Ok(())
+            }
+        }
+    }
+
+    // Blank the canvas, in memory and in the store
+    pub fn clear(&self) -> Result<(), AppError> {
+        let _commit = self.commit.lock().map_err(|_| AppError::DbClearError)?;
+        let mut canvas = self.canvas.write().map_err(|_| AppError::DbClearError)?;
+        self.store.clear_pixels()?;
+        *canvas = PackedCanvas::default();
+        self.dirty.lock().map_err(|_| AppError::DbClearError)?.clear();
+        Ok(())
+    }
+
+    // Swap in a whole new board (restoring a snapshot). Every row goes to the store in one batch,
+    // so either the whole new board is stored or, if the store fails, none of it and the old one stays
+    pub fn replace(&self, new_canvas: PackedCanvas) -> Result<(), AppError> {
+        let _commit = self.commit.lock().map_err(|_| AppError::DbWriteError)?;
+        let mut canvas = self.canvas.write().map_err(|_| AppError::DbWriteError)?;
+        let rows: Vec<(u32, Vec<u8>)> = (0..CANVAS_HEIGHT)
+            .filter_map(|y| new_canvas.row(y).map(|row| (y, row.to_vec())))
+            .collect();
+        self.store.write_rows(&rows)?;
+        *canvas = new_canvas;
+        self.dirty.lock().map_err(|_| AppError::DbWriteError)?.clear();
+        Ok(())
+    }
+
+    // Number of rows waiting to be committed
+    pub fn pending_rows(&self) -> usize {
+        self.dirty.lock().map(|dirty| dirty.len()).unwrap_or(0)
+    }
+
+    // Commit every dirty row to the store in one batch. Returns how many rows were written
+    // If the store fails the rows stay dirty, and the next flush tries again
+    pub fn flush(&self) -> Result<usize, AppError> {
+        let _commit = self.commit.lock().map_err(|_| AppError::DbFlushError)?;
+        let rows: Vec<u32> = std::mem::take(&mut *self.dirty.lock().map_err(|_| AppError::DbFlushError)?).into_iter().collect();
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        let batch: Vec<(u32, Vec<u8>)> = {
+            let canvas = self.canvas.read().map_err(|_| AppError::DbFlushError)?;
+            rows.iter().filter_map(|&y| canvas.row(y).map(|row| (y, row.to_vec()))).collect()
+        };
+
+        if let Err(e) = self.store.write_rows(&batch) {
+            if let Ok(mut dirty) = self.dirty.lock() {
+                dirty.extend(rows);
+            }
+            return Err(e);
+        }
+        Ok(batch.len())
+    }
+}
+
+// Start the background commit task for WritePolicy::WriteBehind (nothing to do for write-through)
+// Must be called from inside the tokio runtime
+pub fn spawn_write_behind(cache: Arc<CanvasCache>) -> Option<tokio::task::JoinHandle<()>> {
+    let WritePolicy::WriteBehind { interval } = cache.policy() else {
+        return None;
+    };
+
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if cache.pending_rows() == 0 {
+                continue;
+            }
+            // Committing blocks on the disk, keep it off the async worker threads
+            let cache = cache.clone();
+            match tokio::task::spawn_blocking(move || cache.flush()).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("Canvas write-behind failed, will retry: {}", e),
+                Err(e) => tracing::error!("Canvas write-behind task failed: {}", e),
+            }
+        }
+    }))
+}
@@ -0,0 +1,43 @@
+// server/discovery.rs
+
+// Advertises the server on the local network with mDNS (DNS-SD), so LED boards can find it without being told an address
+
+// For your knowledge
+// A board set up with an empty server address asks the LAN for protocol::MDNS_SERVICE_TYPE ("_rustycanvas._tcp.local")
+// and connects to whatever answers with a host and port (see firmware-core/src/discovery.rs)
+// mdns-sd runs the responder on its own thread. The advertisement stays up for as long as the ServiceDaemon lives
+// It also answers for the host name, so "http://rustycanvas.local:8080" works from a browser on the same network
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use protocol::MDNS_SERVICE_TYPE;
+
+pub const DEFAULT_INSTANCE_NAME: &str = "RustyCanvas";
+
+// Describe this server: "<instance_name>._rustycanvas._tcp.local." on 'port' of every local address
+pub fn service_info(instance_name: &str, port: u16) -> Result<ServiceInfo, mdns_sd::Error> {
+    // mdns-sd wants fully qualified names, with the trailing dot
+    let service_type = format!("{}.", MDNS_SERVICE_TYPE);
+    let host_name = format!("{}.local.", host_label(instance_name));
+    let properties = [("version", env!("CARGO_PKG_VERSION"))];
+
+    // No addresses given, mdns-sd fills in the ones of each interface it answers on
+    let info = ServiceInfo::new(&service_type, instance_name, &host_name, (), port, &properties[..])?;
+    Ok(info.enable_addr_auto())
+}
+
+// Start answering mDNS queries for this server. Drop the daemon to stop
+pub fn advertise(instance_name: &str, port: u16) -> Result<ServiceDaemon, mdns_sd::Error> {
+    let daemon = ServiceDaemon::new()?;
+    daemon.register(service_info(instance_name, port)?)?;
+    Ok(daemon)
+}
+
+// Instance names can be anything ("Rohan's canvas"), host names only letters, digits and dashes
+fn host_label(instance_name: &str) -> String {
+    let label: String = instance_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let label = label.trim_matches('-');
+    if label.is_empty() { "rustycanvas".to_string() } else { label.to_string() }
+}
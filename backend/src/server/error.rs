@@ -0,0 +1,242 @@
+// server/error.rs
+
+// This module defines the one error type every part of the server returns, and how it is sent to clients
+
+// For your knowledge
+// Each variant has a stable code (e.g. "out_of_bounds") that clients match on, and an HTTP status
+// Handlers return Result<Json<T>, AppError>, and axum turns the error into the same JSON body on every route:
+//   { "success": false, "error": "out_of_bounds", "message": "pixel is off the board" }
+// (protocol::ErrorResponse). The code never changes once published, the message is only for people
+// Requests axum can't even read (bad JSON, bad query string, bad path) get that body too, through the
+// AppJson/AppQuery/AppPath extractors below, instead of axum's plaintext rejections
+// So do unknown routes and wrong methods (see routes.rs)
+// Every error is also counted by code on GET /metrics, and logged (5xx as errors, the rest at debug level)
+
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{FromRequest, FromRequestParts, Request};
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Json, Response};
+use protocol::ErrorResponse;
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    // The request can't be read. The text is axum's explanation
+    InvalidBody(String),
+    InvalidQuery(String),
+    InvalidPath(String),
+    UnsupportedMediaType,
+    PayloadTooLarge,
+    // The request was read, but something in it is wrong
+    OutOfBounds,
+    InvalidColor,
+    InvalidDeviceId,
+    InvalidFirmwareVersion,
+    InvalidSnapshotName,
+    InvalidDuration,
+    InvalidOpensAt,
+    InvalidArchive,
+    UnsupportedArchive,
+    Unauthorized,
+    UnknownRoute,
+    MethodNotAllowed,
+    UnknownDevice,
+    UnknownSnapshot,
+    SnapshotExists,
+    // The board isn't taking pixels right now (scheduled event, or an admin froze it)
+    CanvasClosed,
+    ReadOnly,
+    Maintenance,
+    // The store failed, nothing the client can fix
+    DbReadError,
+    DbWriteError,
+    DbFlushError,
+    DbClearError,
+}
+
+impl AppError {
+    // The code sent in the 'error' field
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::InvalidBody(_) => "invalid_body",
+            AppError::InvalidQuery(_) => "invalid_query",
+            AppError::InvalidPath(_) => "invalid_path",
+            AppError::UnsupportedMediaType => "unsupported_media_type",
+            AppError::PayloadTooLarge => "payload_too_large",
+            AppError::OutOfBounds => "out_of_bounds",
+            AppError::InvalidColor => "invalid_color",
+            AppError::InvalidDeviceId => "invalid_device_id",
+            AppError::InvalidFirmwareVersion => "invalid_firmware_version",
+            AppError::InvalidSnapshotName => "invalid_snapshot_name",
+            AppError::InvalidDuration => "invalid_duration",
+            AppError::InvalidOpensAt => "invalid_opens_at",
+            AppError::InvalidArchive => "invalid_archive",
+            AppError::UnsupportedArchive => "unsupported_archive",
+            AppError::Unauthorized => "unauthorized",
+            AppError::UnknownRoute => "unknown_route",
+            AppError::MethodNotAllowed => "method_not_allowed",
+            AppError::UnknownDevice => "unknown_device",
+            AppError::UnknownSnapshot => "unknown_snapshot",
+            AppError::SnapshotExists => "snapshot_exists",
+            AppError::CanvasClosed => "canvas_closed",
+            AppError::ReadOnly => "read_only",
+            AppError::Maintenance => "maintenance",
+            AppError::DbReadError => "db_read_error",
+            AppError::DbWriteError => "db_write_error",
+            AppError::DbFlushError => "db_flush_error",
+            AppError::DbClearError => "db_clear_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::InvalidBody(_)
+            | AppError::InvalidQuery(_)
+            | AppError::InvalidPath(_)
+            | AppError::OutOfBounds
+            | AppError::InvalidColor
+            | AppError::InvalidDeviceId
+            | AppError::InvalidFirmwareVersion
+            | AppError::InvalidSnapshotName
+            | AppError::InvalidDuration
+            | AppError::InvalidOpensAt
+            | AppError::InvalidArchive
+            | AppError::UnsupportedArchive => StatusCode::BAD_REQUEST,
+            AppError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::CanvasClosed | AppError::ReadOnly => StatusCode::FORBIDDEN,
+            AppError::UnknownRoute | AppError::UnknownDevice | AppError::UnknownSnapshot => StatusCode::NOT_FOUND,
+            AppError::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            AppError::SnapshotExists => StatusCode::CONFLICT,
+            AppError::Maintenance => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::DbReadError | AppError::DbWriteError | AppError::DbFlushError | AppError::DbClearError => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+// The 'message' field
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::InvalidBody(detail) | AppError::InvalidQuery(detail) | AppError::InvalidPath(detail) => f.write_str(detail),
+            AppError::UnsupportedMediaType => f.write_str("expected a JSON body with 'Content-Type: application/json'"),
+            AppError::PayloadTooLarge => f.write_str("request body is too large"),
+            AppError::OutOfBounds => f.write_str("pixel is off the board"),
+            AppError::InvalidColor => f.write_str("colour must look like #RRGGBB"),
+            AppError::InvalidDeviceId => f.write_str("device ID must be 1-64 letters, digits, '-' or '_'"),
+            AppError::InvalidFirmwareVersion => f.write_str("firmware version is too long"),
+            AppError::InvalidSnapshotName => f.write_str("snapshot name must be 1-64 letters, digits, '-' or '_'"),
+            AppError::InvalidDuration => f.write_str("an event must be open for between 1 minute and a week"),
+            AppError::InvalidOpensAt => f.write_str("the event would be over before it starts"),
+            AppError::InvalidArchive => f.write_str("archive contains a colour that isn't #RRGGBB"),
+            AppError::UnsupportedArchive => f.write_str("not an archive this server can read"),
+            AppError::Unauthorized => f.write_str("this route needs 'Authorization: Bearer <admin token>'"),
+            AppError::UnknownRoute => f.write_str("no such route"),
+            AppError::MethodNotAllowed => f.write_str("method not allowed on this route"),
+            AppError::UnknownDevice => f.write_str("device has not registered"),
+            AppError::UnknownSnapshot => f.write_str("no snapshot with that name"),
+            AppError::SnapshotExists => f.write_str("a snapshot with that name already exists"),
+            AppError::CanvasClosed => f.write_str("the board is closed outside the scheduled event"),
+            AppError::ReadOnly => f.write_str("the board is read-only"),
+            AppError::Maintenance => f.write_str("the board is down for maintenance, try again later"),
+            AppError::DbReadError => f.write_str("could not read from the store"),
+            AppError::DbWriteError => f.write_str("could not write to the store"),
+            AppError::DbFlushError => f.write_str("could not save to disk"),
+            AppError::DbClearError => f.write_str("could not clear the store"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse {
+            success: false,
+            error: self.code().to_string(),
+            message: self.to_string(),
+        };
+        let status = self.status();
+        // Inside the request's span, so the log line carries its request ID (see routes.rs)
+        if status.is_server_error() {
+            tracing::error!(error = self.code(), "{}", self);
+        } else {
+            tracing::debug!(error = self.code(), "{}", self);
+        }
+        let mut response = (status, Json(body)).into_response();
+        // So the metrics middleware can count it by code (see metrics.rs)
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection.status() {
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => AppError::UnsupportedMediaType,
+            StatusCode::PAYLOAD_TOO_LARGE => AppError::PayloadTooLarge,
+            _ => AppError::InvalidBody(rejection.body_text()),
+        }
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::InvalidQuery(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        AppError::InvalidPath(rejection.body_text())
+    }
+}
+
+// axum's Json, Query and Path extractors, with AppError as the rejection
+
+pub struct AppJson<T>(pub T);
+
+impl<S: Send + Sync, T: DeserializeOwned> FromRequest<S> for AppJson<T> {
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(request, state).await?;
+        Ok(AppJson(value))
+    }
+}
+
+pub struct AppQuery<T>(pub T);
+
+impl<S: Send + Sync, T: DeserializeOwned> FromRequestParts<S> for AppQuery<T> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) = axum::extract::Query::<T>::from_request_parts(parts, state).await?;
+        Ok(AppQuery(value))
+    }
+}
+
+pub struct AppPath<T>(pub T);
+
+impl<S: Send + Sync, T: DeserializeOwned + Send> FromRequestParts<S> for AppPath<T> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) = axum::extract::Path::<T>::from_request_parts(parts, state).await?;
+        Ok(AppPath(value))
+    }
+}
+
+// Fallbacks for requests no route matches (see routes.rs)
+pub async fn unknown_route() -> AppError {
+    AppError::UnknownRoute
+}
+
+pub async fn method_not_allowed() -> AppError {
+    AppError::MethodNotAllowed
+}
@@ -12,85 +12,71 @@
 
 // This file defines the handler functions for the Axum web server
 
-use axum::response::{IntoResponse, Json};
-use axum::http::StatusCode;
-use serde::{Deserialize, Serialize};
-use axum::extract::{State, Query};
+use axum::response::Json;
+use axum::http::{HeaderMap, HeaderName, StatusCode, header::{AUTHORIZATION, CONTENT_TYPE}};
+use axum::extract::State;
+use crate::server::error::{AppError, AppJson, AppPath, AppQuery};
+use crate::server::cache::CanvasCache;
+use crate::server::store::CanvasStore;
 use crate::server::state::{AppState, CANVAS_WIDTH, CANVAS_HEIGHT, DEFAULT_COLOR, PixelUpdate};
-use std::time::{SystemTime, UNIX_EPOCH};
-
-// Struct for JSON response for canvas state
-#[derive(serde::Serialize)]
-pub struct CanvasResponse {
-    pub width: u32,
-    pub height: u32,
-    pub pixels: Vec<Vec<String>>,
-}
-
-// Struct for JSON input for pixel update
-#[derive(Deserialize)]
-pub struct PixelUpdateInput {
-    pub x: u32,
-    pub y: u32,
-    pub color: String,
-}
-
-// Struct for JSON response for pixel update
-#[derive(Serialize)]
-pub struct PixelUpdateResponse {
-    pub success: bool,
-    pub error: Option<String>,
-}
-
-// Struct for clearing canvas response
-#[derive(Serialize)]
-pub struct ClearCanvasResponse {
-    pub success: bool,
-    pub message: String,
-}
-
-// Struct for getting updates since a timestamp
-#[derive(Deserialize)]
-pub struct GetUpdatesInput {
-    pub since: u64, // Client sends the timestamp since they last synced
-}
-
-// Struct for updates response
-#[derive(Serialize)]
-pub struct UpdatesResponse {
-    pub updates: Vec<PixelUpdate>,
-    pub reset_required: bool, // Tell client if they are too far behind
-}
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+// Request/response bodies live in the shared protocol crate so every client sees the same JSON shape
+pub use protocol::{
+    CanvasResponse,
+    PixelUpdateInput,
+    PixelUpdateResponse,
+    ClearCanvasResponse,
+    GetUpdatesInput,
+    UpdatesResponse,
+    DeviceReport,
+    DeviceReportResponse,
+    DeviceStatus,
+    DevicesResponse,
+    TestPattern,
+    TestPatternInput,
+    CanvasArchive,
+    SnapshotInput,
+    SnapshotInfo,
+    SnapshotsResponse,
+    SnapshotResponse,
+    EventScheduleInput,
+    EventStatus,
+    EventResponse,
+    ModeInput,
+    ModeStatus,
+    ModeResponse,
+    HealthResponse,
+    ReadinessResponse,
+    VersionResponse,
+};
+use protocol::{Rgb, ARCHIVE_FORMAT, ARCHIVE_VERSION, DEVICE_OFFLINE_AFTER_SECS};
+use crate::server::store::PackedCanvas;
+use crate::server::schedule::{cancel_event, check_canvas_open, event_status, schedule_event};
+use crate::server::mode::{check_mode_allows_pixels, current_mode, set_mode};
+use crate::server::health::{check_readiness, health, version_info};
+use std::sync::atomic::Ordering;
+
+const MAX_DEVICE_ID_LEN: usize = 64;
+const MAX_FIRMWARE_VERSION_LEN: usize = 32;
+const MAX_SNAPSHOT_NAME_LEN: usize = 64;
+// How many recent updates /updates can hand out before clients have to reload the board
+pub const HISTORY_LEN: usize = 50;
 
 // -------------------------------- LOGIC FUNCTIONS ----------------------------------
 // These functions contain the "Business Logic"
 
-// Helper to generate a standardized key for the DB, e.g., "5:10"
-fn make_key(x: u32, y: u32) -> String {
-    format!("{}:{}", x, y)
-}
-
-// Logic to reconstruct the full 2D array from the Key-Value store
-pub fn make_canvas_response(db: &sled::Db) -> CanvasResponse {
-    let mut pixels = Vec::new();
-
-    for y in 0..CANVAS_HEIGHT {
-        let mut row = Vec::new();
-        for x in 0..CANVAS_WIDTH {
-            let key = make_key(x, y);
-            
-            // Try to get the pixel from DB. If not found, use DEFAULT_COLOR.
-            let color = match db.get(&key) {
-                Ok(Some(ivec)) => {
-                    // Convert binary data back to String
-                    String::from_utf8(ivec.to_vec()).unwrap_or(DEFAULT_COLOR.to_string())
-                },
-                _ => DEFAULT_COLOR.to_string(), // Default if key missing or error
-            };
-            row.push(color);
-        }
-        pixels.push(row);
-    }
+// Logic to reconstruct the full 2D array from the in-memory canvas
+pub fn make_canvas_response(cache: &CanvasCache) -> CanvasResponse {
+    let canvas = cache.snapshot();
+
+    let pixels = (0..CANVAS_HEIGHT)
+        .map(|y| {
+            (0..CANVAS_WIDTH)
+                .map(|x| canvas.get(x, y).map(|color| color.to_string()).unwrap_or(DEFAULT_COLOR.to_string()))
+                .collect()
+        })
+        .collect();
 
     CanvasResponse {
         width: CANVAS_WIDTH,
@@ -99,46 +85,44 @@ pub fn make_canvas_response(db: &sled::Db) -> CanvasResponse {
     }
 }
 
-// Logic to update a single key-value pair in the DB
-pub fn apply_pixel_update(db: &sled::Db, input: &PixelUpdateInput) -> Result<(), &'static str> {
+// Logic to store a single pixel, returns the colour as it was stored
+pub fn apply_pixel_update(cache: &CanvasCache, input: &PixelUpdateInput) -> Result<Rgb, AppError> {
     if input.x >= CANVAS_WIDTH || input.y >= CANVAS_HEIGHT {
-        return Err("out_of_bounds");
+        return Err(AppError::OutOfBounds);
     }
 
-    let key = make_key(input.x, input.y);
-    
-    // Sled stores bytes, convert the hex string to bytes
-    db.insert(&key, input.color.as_bytes())
-        .map_err(|_| "db_write_error")?;
+    // Colours are stored as packed RGB, so only "#RRGGBB" can be kept
+    let color = Rgb::parse_hex(&input.color).map_err(|_| AppError::InvalidColor)?;
 
-    db.flush().map_err(|_| "db_flush_error")?;
+    cache.set_pixel(input.x, input.y, color)?;
+    Ok(color)
+}
 
-    Ok(())
+// Logic to reset the canvas (the device list is kept)
+pub fn reset_canvas_db(cache: &CanvasCache) -> Result<(), AppError> {
+    cache.clear()
 }
 
-// Logic to reset the canvas (clear the DB)
-pub fn reset_canvas_db(db: &sled::Db) -> Result<(), &'static str> {
-    // Sled's clear() removes all items from the Tree
-    db.clear().map_err(|_| "db_clear_error")?;
-    
-    // Ensure the change is written to disk
-    db.flush().map_err(|_| "db_flush_error")?;
-    
+// Logic to blank the whole board for everyone: the canvas, and the history that would redraw it
+// Pollers see reset_required on their next /updates and reload the blank board
+pub fn reset_board(state: &AppState, now: u64) -> Result<(), AppError> {
+    reset_canvas_db(&state.canvas)?;
+    // Under the history lock, so a pixel logged at the same time is either cleared or stamped after the reset
+    if let Ok(mut history) = state.history.write() {
+        history.clear();
+        state.replaced_at.store(now, Ordering::SeqCst);
+    }
     Ok(())
 }
 
-// Logic to log a pixel update into history
-pub fn log_pixel_update(state: &AppState, x: u32, y: u32, color: String) {
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
-
-    let update = PixelUpdate { x, y, color, timestamp };
-
+// Logic to log a pixel update into history, placed at 'now' (server time in ms)
+// Updates are always stamped after the last reset or restore, even within the same millisecond: clients that
+// reloaded then poll with since = replaced_at, and only get updates strictly newer than that
+pub fn log_pixel_update(state: &AppState, x: u32, y: u32, color: String, now: u64) {
     if let Ok(mut history) = state.history.write() {
-        history.push_back(update);
-        if history.len() > 50 {
+        let timestamp = now.max(state.replaced_at.load(Ordering::SeqCst) + 1);
+        history.push_back(PixelUpdate { x, y, color, timestamp });
+        if history.len() > HISTORY_LEN {
             history.pop_front();
         }
     }
@@ -146,13 +130,18 @@ pub fn log_pixel_update(state: &AppState, x: u32, y: u32, color: String) {
 
 // Logic to fetch updates since a given timestamp
 pub fn fetch_updates_since(state: &AppState, since: u64) -> (Vec<PixelUpdate>, bool) {
+    // The board was restored since the client last looked, the updates it missed don't describe it any more
+    if since < state.replaced_at.load(Ordering::SeqCst) {
+        return (Vec::new(), true);
+    }
+
     let history = state.history.read().unwrap();
     let mut updates = Vec::new();
     let mut reset_required = false;
 
     if let Some(first) = history.front() {
         // Only trigger reset if the buffer is full AND client is too old
-        let buffer_limit_reached = history.len() >= 50;
+        let buffer_limit_reached = history.len() >= HISTORY_LEN;
         
         if buffer_limit_reached && since < first.timestamp {
              reset_required = true;
@@ -167,6 +156,204 @@ pub fn fetch_updates_since(state: &AppState, since: u64) -> (Vec<PixelUpdate>, b
     
     (updates, reset_required)
 }
+
+// Logic to find the newest timestamp in history (0 if nothing has been placed yet)
+// A restore counts as the newest update, so clients that reload after it carry on from there
+pub fn latest_update_timestamp(state: &AppState) -> u64 {
+    let history = state.history.read().unwrap();
+    let latest = history.back().map(|update| update.timestamp).unwrap_or(0);
+    latest.max(state.replaced_at.load(Ordering::SeqCst))
+}
+
+// Current server time in ms since the Unix epoch (history timestamps and device last_seen)
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+// Logic to store a board's report
+// Registering adds (or replaces) the device. A heartbeat only updates a device that has registered,
+// so a board the server has forgotten (e.g. a wiped database) is told to register again
+// Returns the test pattern queued for the board, if any (it is handed over once)
+pub fn record_device_report(store: &dyn CanvasStore, report: &DeviceReport, register: bool, now: u64) -> Result<Option<TestPattern>, AppError> {
+    let id = report.device_id.as_str();
+    if id.is_empty()
+        || id.len() > MAX_DEVICE_ID_LEN
+        || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AppError::InvalidDeviceId);
+    }
+    if report.firmware_version.len() > MAX_FIRMWARE_VERSION_LEN {
+        return Err(AppError::InvalidFirmwareVersion);
+    }
+
+    let existing = store.get_device(id)?;
+    let registered_at = match (&existing, register) {
+        (_, true) => now,
+        (Some(existing), false) => existing.registered_at,
+        (None, false) => return Err(AppError::UnknownDevice),
+    };
+    let test_pattern = existing.and_then(|existing| existing.pending_test_pattern);
+
+    let status = DeviceStatus {
+        device_id: report.device_id.clone(),
+        firmware_version: report.firmware_version.clone(),
+        uptime_secs: report.uptime_secs,
+        rssi: report.rssi,
+        last_applied: report.last_applied,
+        registered_at,
+        last_seen: now,
+        // Worked out when the list is read
+        online: false,
+        pending_test_pattern: None,
+    };
+    store.put_device(&status)?;
+
+    Ok(test_pattern)
+}
+
+// Logic to queue a test pattern for a board, which it picks up with its next report
+pub fn queue_test_pattern(store: &dyn CanvasStore, device_id: &str, pattern: TestPattern) -> Result<(), AppError> {
+    let mut status = store.get_device(device_id)?.ok_or(AppError::UnknownDevice)?;
+    status.pending_test_pattern = Some(pattern);
+    store.put_device(&status)
+}
+
+// Logic to list every board that has registered, sorted by ID, with 'online' worked out for 'now'
+pub fn list_devices(store: &dyn CanvasStore, now: u64) -> Result<DevicesResponse, AppError> {
+    let mut devices = store.devices()?;
+    for status in &mut devices {
+        status.online = now.saturating_sub(status.last_seen) <= DEVICE_OFFLINE_AFTER_SECS * 1000;
+    }
+
+    Ok(DevicesResponse { devices })
+}
+
+// Logic to copy the board and the recent history into an archive (GET /backup and snapshots)
+pub fn make_archive(state: &AppState, now: u64) -> CanvasArchive {
+    let canvas = make_canvas_response(&state.canvas);
+    let history = state.history.read().map(|history| history.iter().cloned().collect()).unwrap_or_default();
+
+    CanvasArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at: now,
+        width: canvas.width,
+        height: canvas.height,
+        pixels: canvas.pixels,
+        history,
+    }
+}
+
+// Logic to turn an archive back into a board
+// Archives from a server with a different canvas size are cut or padded with DEFAULT_COLOR
+pub fn archive_to_canvas(archive: &CanvasArchive) -> Result<PackedCanvas, AppError> {
+    if archive.format != ARCHIVE_FORMAT || archive.version > ARCHIVE_VERSION {
+        return Err(AppError::UnsupportedArchive);
+    }
+
+    let mut canvas = PackedCanvas::default();
+    for (y, row) in archive.pixels.iter().enumerate() {
+        for (x, color) in row.iter().enumerate() {
+            let color = Rgb::parse_hex(color).map_err(|_| AppError::InvalidArchive)?;
+            // Anything off our board is dropped
+            canvas.set(x as u32, y as u32, color);
+        }
+    }
+    Ok(canvas)
+}
+
+// Logic to replace the board and history with an archive's, in one step
+// Pollers see reset_required on their next /updates and reload the whole board
+pub fn restore_archive(state: &AppState, archive: &CanvasArchive, now: u64) -> Result<(), AppError> {
+    let canvas = archive_to_canvas(archive)?;
+    state.canvas.replace(canvas)?;
+
+    if let Ok(mut history) = state.history.write() {
+        let skip = archive.history.len().saturating_sub(HISTORY_LEN);
+        *history = archive.history.iter().skip(skip).cloned().collect();
+        state.replaced_at.store(now, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+// Logic to describe a stored snapshot
+pub fn snapshot_info(name: &str, archive: &CanvasArchive) -> SnapshotInfo {
+    SnapshotInfo {
+        name: name.to_string(),
+        created_at: archive.created_at,
+        width: archive.width,
+        height: archive.height,
+        history_len: archive.history.len(),
+    }
+}
+
+// Snapshot names end up in URLs (/snapshots/{name}/restore), so they're kept to the same characters as device IDs
+pub fn validate_snapshot_name(name: &str) -> Result<(), AppError> {
+    if name.is_empty()
+        || name.len() > MAX_SNAPSHOT_NAME_LEN
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AppError::InvalidSnapshotName);
+    }
+    Ok(())
+}
+
+// Logic to save the current board as a named snapshot. Names are never overwritten, delete the old one first
+pub fn save_snapshot(state: &AppState, name: &str, now: u64) -> Result<SnapshotInfo, AppError> {
+    validate_snapshot_name(name)?;
+    if state.store.get_snapshot(name)?.is_some() {
+        return Err(AppError::SnapshotExists);
+    }
+
+    let archive = make_archive(state, now);
+    state.store.put_snapshot(name, &archive)?;
+    Ok(snapshot_info(name, &archive))
+}
+
+// Logic to list the stored snapshots, sorted by name
+pub fn list_snapshots(store: &dyn CanvasStore) -> Result<SnapshotsResponse, AppError> {
+    let snapshots = store.snapshots()?
+        .iter()
+        .map(|(name, archive)| snapshot_info(name, archive))
+        .collect();
+    Ok(SnapshotsResponse { snapshots })
+}
+
+// Logic to put a named snapshot back on the board
+pub fn restore_snapshot(state: &AppState, name: &str, now: u64) -> Result<SnapshotInfo, AppError> {
+    let archive = state.store.get_snapshot(name)?.ok_or(AppError::UnknownSnapshot)?;
+    restore_archive(state, &archive, now)?;
+    Ok(snapshot_info(name, &archive))
+}
+
+// Logic to forget a named snapshot
+pub fn delete_snapshot(store: &dyn CanvasStore, name: &str) -> Result<SnapshotInfo, AppError> {
+    let archive = store.get_snapshot(name)?.ok_or(AppError::UnknownSnapshot)?;
+    store.delete_snapshot(name)?;
+    Ok(snapshot_info(name, &archive))
+}
+
+// Logic to check the admin token on protected routes
+// With no token configured every request is allowed, so local setups keep working unchanged
+pub fn is_admin_authorized(state: &AppState, headers: &HeaderMap) -> bool {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return true;
+    };
+
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token == expected)
+}
+
+// The same check, as an error the admin handlers can return with '?'
+pub fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    if is_admin_authorized(state, headers) { Ok(()) } else { Err(AppError::Unauthorized) }
+}
 // -------------------------------- LOGIC FUNCTIONS ----------------------------------
 
 
@@ -176,66 +363,195 @@ pub fn fetch_updates_since(state: &AppState, since: u64) -> (Vec<PixelUpdate>, b
 // 2. Call Logic Functions
 // 3. Handle Side Effects (Saving)
 // 4. Return HTTP Response
+// Failures are returned as AppError, which sends the same JSON error body on every route (see error.rs)
 
 // GET /canvas
 pub async fn get_canvas_handler(State(app_state): State<AppState>) -> Json<CanvasResponse> {
     // Using logic function
-    let response = make_canvas_response(&app_state.db);
+    let response = make_canvas_response(&app_state.canvas);
 
     Json(response)
 }
 
 // POST /pixel
-pub async fn update_pixel_handler(State(app_state): State<AppState>, Json(payload): Json<PixelUpdateInput>) -> (StatusCode, Json<PixelUpdateResponse>) {
-    match apply_pixel_update(&app_state.db, &payload) {
-        Ok(_) => {
-            // Log the update in history
-            log_pixel_update(&app_state, payload.x, payload.y, payload.color);
-            
-            // Return Success Response
-            let response = PixelUpdateResponse {
-                success: true,
-                error: None,
-            };
-            (StatusCode::OK, Json(response))
-        },
-        Err(err_msg) => {
-            let response = PixelUpdateResponse {
-                success: false,
-                error: Some(err_msg.to_string()),
-            };
-            (StatusCode::BAD_REQUEST, Json(response))
-        }
-    }
+pub async fn update_pixel_handler(State(app_state): State<AppState>, AppJson(payload): AppJson<PixelUpdateInput>) -> Result<Json<PixelUpdateResponse>, AppError> {
+    // An admin may have frozen the board, and outside a scheduled event's open window it is read-only too
+    check_mode_allows_pixels(&app_state)?;
+    check_canvas_open(&app_state, now_millis())?;
+
+    let color = apply_pixel_update(&app_state.canvas, &payload)?;
+    // Log the update in history, in the same "#RRGGBB" form GET /canvas sends (e.g. "#abcdef" becomes "#ABCDEF")
+    log_pixel_update(&app_state, payload.x, payload.y, color.to_string(), now_millis());
+    app_state.metrics.pixel_placed();
+
+    Ok(Json(PixelUpdateResponse {
+        success: true,
+        error: None,
+    }))
 }
 
 // POST /reset
-pub async fn reset_canvas_handler(State(app_state): State<AppState>) -> (StatusCode, Json<ClearCanvasResponse>) {
-    match reset_canvas_db(&app_state.db) {
-        Ok(_) => {
-            let response = ClearCanvasResponse {
-                success: true,
-                message: "Canvas reset successfully".to_string(),
-            };
-            (StatusCode::OK, Json(response))
-        },
-        Err(err_msg) => {
-            let response = ClearCanvasResponse {
-                success: false,
-                message: err_msg.to_string(),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
-        }
-    }
+pub async fn reset_canvas_handler(State(app_state): State<AppState>, headers: HeaderMap) -> Result<Json<ClearCanvasResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    reset_board(&app_state, now_millis())?;
+    Ok(Json(ClearCanvasResponse {
+        success: true,
+        message: "Canvas reset successfully".to_string(),
+    }))
 }
 
 // GET /updates?since=123456789
-pub async fn get_updates_handler(State(app_state): State<AppState>, Query(params): Query<GetUpdatesInput>) -> Json<UpdatesResponse> {
+pub async fn get_updates_handler(State(app_state): State<AppState>, AppQuery(params): AppQuery<GetUpdatesInput>) -> Json<UpdatesResponse> {
     let (updates, reset_required) = fetch_updates_since(&app_state, params.since);
 
     Json(UpdatesResponse {
         updates,
         reset_required,
+        latest_timestamp: latest_update_timestamp(&app_state),
+        shutting_down: app_state.shutdown.is_set(),
+        mode: current_mode(&app_state).mode,
     })
 }
-// -------------------------------- HANDLER FUNCTIONS ----------------------------------
\ No newline at end of file
+
+// POST /devices/register
+pub async fn register_device_handler(State(app_state): State<AppState>, AppJson(payload): AppJson<DeviceReport>) -> Result<Json<DeviceReportResponse>, AppError> {
+    let test_pattern = record_device_report(app_state.store.as_ref(), &payload, true, now_millis())?;
+    Ok(Json(DeviceReportResponse { success: true, error: None, test_pattern }))
+}
+
+// POST /devices/heartbeat
+// A board the server doesn't know gets unknown_device (404), and registers again when it sees it
+pub async fn device_heartbeat_handler(State(app_state): State<AppState>, AppJson(payload): AppJson<DeviceReport>) -> Result<Json<DeviceReportResponse>, AppError> {
+    let test_pattern = record_device_report(app_state.store.as_ref(), &payload, false, now_millis())?;
+    Ok(Json(DeviceReportResponse { success: true, error: None, test_pattern }))
+}
+
+// POST /devices/{device_id}/test-pattern
+pub async fn queue_test_pattern_handler(State(app_state): State<AppState>, headers: HeaderMap, AppPath(device_id): AppPath<String>, AppJson(payload): AppJson<TestPatternInput>) -> Result<Json<DeviceReportResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    queue_test_pattern(app_state.store.as_ref(), &device_id, payload.pattern)?;
+    Ok(Json(DeviceReportResponse { success: true, error: None, test_pattern: None }))
+}
+
+// GET /devices
+pub async fn get_devices_handler(State(app_state): State<AppState>, headers: HeaderMap) -> Result<Json<DevicesResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    list_devices(app_state.store.as_ref(), now_millis()).map(Json)
+}
+
+// GET /backup
+pub async fn get_backup_handler(State(app_state): State<AppState>, headers: HeaderMap) -> Result<Json<CanvasArchive>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    Ok(Json(make_archive(&app_state, now_millis())))
+}
+
+// POST /restore
+pub async fn restore_backup_handler(State(app_state): State<AppState>, headers: HeaderMap, AppJson(payload): AppJson<CanvasArchive>) -> Result<Json<SnapshotResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    restore_archive(&app_state, &payload, now_millis())?;
+    Ok(snapshot_response(None))
+}
+
+// GET /snapshots
+pub async fn get_snapshots_handler(State(app_state): State<AppState>, headers: HeaderMap) -> Result<Json<SnapshotsResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    list_snapshots(app_state.store.as_ref()).map(Json)
+}
+
+// POST /snapshots
+pub async fn create_snapshot_handler(State(app_state): State<AppState>, headers: HeaderMap, AppJson(payload): AppJson<SnapshotInput>) -> Result<Json<SnapshotResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    let snapshot = save_snapshot(&app_state, &payload.name, now_millis())?;
+    Ok(snapshot_response(Some(snapshot)))
+}
+
+// POST /snapshots/{name}/restore
+pub async fn restore_snapshot_handler(State(app_state): State<AppState>, headers: HeaderMap, AppPath(name): AppPath<String>) -> Result<Json<SnapshotResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    let snapshot = restore_snapshot(&app_state, &name, now_millis())?;
+    Ok(snapshot_response(Some(snapshot)))
+}
+
+// DELETE /snapshots/{name}
+pub async fn delete_snapshot_handler(State(app_state): State<AppState>, headers: HeaderMap, AppPath(name): AppPath<String>) -> Result<Json<SnapshotResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    let snapshot = delete_snapshot(app_state.store.as_ref(), &name)?;
+    Ok(snapshot_response(Some(snapshot)))
+}
+
+// Shared success response for the snapshot routes
+fn snapshot_response(snapshot: Option<SnapshotInfo>) -> Json<SnapshotResponse> {
+    Json(SnapshotResponse { success: true, error: None, snapshot })
+}
+
+// GET /event
+pub async fn get_event_handler(State(app_state): State<AppState>) -> Json<EventStatus> {
+    Json(event_status(&app_state, now_millis()))
+}
+
+// POST /event
+pub async fn schedule_event_handler(State(app_state): State<AppState>, headers: HeaderMap, AppJson(payload): AppJson<EventScheduleInput>) -> Result<Json<EventResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    let event = schedule_event(&app_state, &payload, now_millis())?;
+    Ok(event_response(event))
+}
+
+// DELETE /event
+pub async fn cancel_event_handler(State(app_state): State<AppState>, headers: HeaderMap) -> Result<Json<EventResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    let event = cancel_event(&app_state, now_millis())?;
+    Ok(event_response(event))
+}
+
+// Shared success response for the event routes
+fn event_response(event: EventStatus) -> Json<EventResponse> {
+    Json(EventResponse { success: true, error: None, event: Some(event) })
+}
+
+// GET /mode
+pub async fn get_mode_handler(State(app_state): State<AppState>) -> Json<ModeStatus> {
+    Json(current_mode(&app_state))
+}
+
+// POST /mode
+pub async fn set_mode_handler(State(app_state): State<AppState>, headers: HeaderMap, AppJson(payload): AppJson<ModeInput>) -> Result<Json<ModeResponse>, AppError> {
+    require_admin(&app_state, &headers)?;
+
+    let mode = set_mode(&app_state, payload.mode, now_millis())?;
+    Ok(Json(ModeResponse { success: true, error: None, mode: Some(mode) }))
+}
+
+// GET /metrics (Prometheus text format)
+pub async fn get_metrics_handler(State(app_state): State<AppState>) -> ([(HeaderName, &'static str); 1], String) {
+    let history_size = app_state.history.read().map(|history| history.len()).unwrap_or(0);
+    let body = app_state.metrics.render(history_size, Instant::now());
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+// GET /healthz
+pub async fn get_health_handler(State(app_state): State<AppState>) -> Json<HealthResponse> {
+    Json(health(&app_state, Instant::now()))
+}
+
+// GET /readyz (503 until every check passes)
+pub async fn get_readiness_handler(State(app_state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let readiness = check_readiness(&app_state, now_millis());
+    let status = if readiness.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(readiness))
+}
+
+// GET /version
+pub async fn get_version_handler(State(app_state): State<AppState>) -> Json<VersionResponse> {
+    Json(version_info(&app_state))
+}
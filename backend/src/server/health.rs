@@ -0,0 +1,110 @@
+// server/health.rs
+
+// This module answers the questions a supervisor (systemd, docker, kubernetes) asks about the server
+
+// For your knowledge
+// - GET /healthz: is the process alive? Answered without touching the store, so a slow disk can't get us restarted
+// - GET /readyz: can it do its job? Each check below is run on every call and listed with what it found:
+//    - store_read: a setting can be read from the store
+//    - store_write: a setting can be written to the store (the probe's own key). On sled or SQLite that is a real
+//      disk write, and supervisors probe every few seconds, so after a successful write the next one waits for
+//      WRITE_CHECK_INTERVAL_MS. Until then the check reports the last write. A failed write is retried on every probe
+//    - history: the recent updates GET /updates hands out can be read
+//    - accepting: the server hasn't been asked to stop (see shutdown.rs), so traffic should go elsewhere
+//   Any failed check makes it answer 503, the body is the same either way
+// - GET /version: what was built and how the board is set up, for bug reports and dashboards
+
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use protocol::{
+    CanvasConfig, HealthResponse, ReadinessCheck, ReadinessResponse, VersionResponse,
+    ARCHIVE_VERSION, CANVAS_HEIGHT, CANVAS_WIDTH, DEFAULT_COLOR, PALETTE,
+};
+use crate::server::cache::WritePolicy;
+use crate::server::error::AppError;
+use crate::server::handlers::HISTORY_LEN;
+use crate::server::state::AppState;
+
+const PROBE_SETTING: &str = "ready_probe";
+pub const WRITE_CHECK_INTERVAL_MS: u64 = 60_000;
+
+// Logic to say we're alive
+pub fn health(state: &AppState, now: Instant) -> HealthResponse {
+    HealthResponse {
+        status: "ok".to_string(),
+        uptime_secs: now.saturating_duration_since(state.started_at).as_secs(),
+    }
+}
+
+// Logic to run every readiness check
+pub fn check_readiness(state: &AppState, now: u64) -> ReadinessResponse {
+    let store_read = state.store.get_setting(PROBE_SETTING).map(|_| "store answered".to_string());
+    let store_write = check_store_write(state, now);
+    let history = state.history.read().map(|history| history.len());
+
+    let checks = vec![
+        store_check("store_read", store_read),
+        store_check("store_write", store_write),
+        match history {
+            Ok(len) => check("history", true, format!("{} recent updates", len)),
+            Err(_) => check("history", false, "history is unreadable after a panic".to_string()),
+        },
+        if state.shutdown.is_set() {
+            check("accepting", false, "shutting down".to_string())
+        } else {
+            check("accepting", true, "taking requests".to_string())
+        },
+    ];
+
+    ReadinessResponse {
+        ready: checks.iter().all(|check| check.ok),
+        checks,
+    }
+}
+
+// Logic to write the probe setting, unless the last probe wrote it less than WRITE_CHECK_INTERVAL_MS ago
+fn check_store_write(state: &AppState, now: u64) -> Result<String, AppError> {
+    let last_write = state.ready_write_at.load(Ordering::SeqCst);
+    if last_write != 0 && now.saturating_sub(last_write) < WRITE_CHECK_INTERVAL_MS {
+        return Ok(format!("store took a write {}s ago", now.saturating_sub(last_write) / 1000));
+    }
+
+    state.store.put_setting(PROBE_SETTING, &now.to_string())?;
+    state.ready_write_at.store(now, Ordering::SeqCst);
+    Ok("store took a write".to_string())
+}
+
+fn store_check(name: &str, result: Result<String, AppError>) -> ReadinessCheck {
+    match result {
+        Ok(detail) => check(name, true, detail),
+        Err(e) => check(name, false, e.to_string()),
+    }
+}
+
+fn check(name: &str, ok: bool, detail: String) -> ReadinessCheck {
+    ReadinessCheck { name: name.to_string(), ok, detail }
+}
+
+// Logic to describe this build and the board it serves
+pub fn version_info(state: &AppState) -> VersionResponse {
+    let flush_ms = match state.canvas.policy() {
+        WritePolicy::WriteThrough => 0,
+        WritePolicy::WriteBehind { interval } => interval.as_millis() as u64,
+    };
+
+    VersionResponse {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        commit: option_env!("GIT_COMMIT").filter(|commit| !commit.is_empty()).map(str::to_string),
+        profile: if cfg!(debug_assertions) { "debug" } else { "release" }.to_string(),
+        canvas: CanvasConfig {
+            width: CANVAS_WIDTH,
+            height: CANVAS_HEIGHT,
+            default_color: DEFAULT_COLOR.to_string(),
+            palette: PALETTE.iter().map(|color| color.to_string()).collect(),
+            history_len: HISTORY_LEN,
+            flush_ms,
+            archive_version: ARCHIVE_VERSION,
+        },
+    }
+}
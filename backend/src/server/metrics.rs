@@ -0,0 +1,251 @@
+// server/metrics.rs
+
+// This module counts what the server is doing and serves it on GET /metrics, in Prometheus' text format
+
+// For your knowledge
+// Prometheus (or anything that speaks its format) scrapes GET /metrics every so often and keeps the history itself,
+// so all we hold are running totals and current values:
+//  - rustycanvas_pixels_placed_total: pixels accepted by POST /pixel
+//  - rustycanvas_rejections_total{reason}: failed requests, by error code (see error.rs)
+//  - rustycanvas_active_clients: addresses that polled GET /updates in the last ACTIVE_CLIENT_WINDOW
+//    (clients behind the same NAT count once)
+//  - rustycanvas_history_size: updates currently kept for /updates
+//  - rustycanvas_db_seconds{op}: how long store calls take, as a histogram (see TimedStore below)
+// Counters only go up and start from 0 when the server starts, Prometheus copes with the reset
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use protocol::{CanvasArchive, DeviceStatus};
+use crate::server::error::AppError;
+use crate::server::state::AppState;
+use crate::server::store::{CanvasStore, PackedCanvas};
+
+pub const ACTIVE_CLIENT_WINDOW: Duration = Duration::from_secs(30);
+// Upper bounds of the latency buckets, in seconds. Local stores answer in well under a millisecond,
+// a slow disk shows up in the top few
+const DB_BUCKETS: [f64; 10] = [0.0001, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5];
+
+#[derive(Default)]
+pub struct Metrics {
+    pixels_placed: AtomicU64,
+    rejections: Mutex<BTreeMap<&'static str, u64>>,
+    pollers: Mutex<Pollers>,
+    db: Mutex<BTreeMap<&'static str, Histogram>>,
+}
+
+// When each address last polled /updates. Addresses that went quiet are dropped at most one
+// ACTIVE_CLIENT_WINDOW after they stop, whether or not anyone scrapes /metrics
+#[derive(Default)]
+struct Pollers {
+    last_seen: HashMap<IpAddr, Instant>,
+    pruned_at: Option<Instant>,
+}
+
+impl Pollers {
+    fn prune(&mut self, now: Instant) {
+        self.last_seen.retain(|_, last_seen| now.saturating_duration_since(*last_seen) <= ACTIVE_CLIENT_WINDOW);
+        self.pruned_at = Some(now);
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    // Not cumulative, each count is for its own bucket (the last one is everything above DB_BUCKETS)
+    buckets: [u64; DB_BUCKETS.len() + 1],
+    count: u64,
+    sum: f64,
+}
+
+impl Metrics {
+    pub fn pixel_placed(&self) {
+        self.pixels_placed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rejected(&self, reason: &'static str) {
+        if let Ok(mut rejections) = self.rejections.lock() {
+            *rejections.entry(reason).or_default() += 1;
+        }
+    }
+
+    pub fn client_polled(&self, ip: IpAddr, now: Instant) {
+        if let Ok(mut pollers) = self.pollers.lock() {
+            pollers.last_seen.insert(ip, now);
+            // Once per window rather than on every poll, the map is scanned in full
+            if pollers.pruned_at.is_none_or(|pruned_at| now.saturating_duration_since(pruned_at) >= ACTIVE_CLIENT_WINDOW) {
+                pollers.prune(now);
+            }
+        }
+    }
+
+    // Clients that polled within ACTIVE_CLIENT_WINDOW of 'now'. Older ones are forgotten
+    pub fn active_clients(&self, now: Instant) -> usize {
+        let Ok(mut pollers) = self.pollers.lock() else {
+            return 0;
+        };
+        pollers.prune(now);
+        pollers.last_seen.len()
+    }
+
+    pub fn db_call(&self, op: &'static str, took: Duration) {
+        let seconds = took.as_secs_f64();
+        if let Ok(mut db) = self.db.lock() {
+            let histogram = db.entry(op).or_default();
+            let bucket = DB_BUCKETS.iter().position(|bound| seconds <= *bound).unwrap_or(DB_BUCKETS.len());
+            histogram.buckets[bucket] += 1;
+            histogram.count += 1;
+            histogram.sum += seconds;
+        }
+    }
+
+    // Everything in Prometheus' text exposition format
+    pub fn render(&self, history_size: usize, now: Instant) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP rustycanvas_pixels_placed_total Pixels accepted by POST /pixel.");
+        let _ = writeln!(out, "# TYPE rustycanvas_pixels_placed_total counter");
+        let _ = writeln!(out, "rustycanvas_pixels_placed_total {}", self.pixels_placed.load(Ordering::Relaxed));
+
+        let _ = writeln!(out, "# HELP rustycanvas_rejections_total Failed requests, by error code.");
+        let _ = writeln!(out, "# TYPE rustycanvas_rejections_total counter");
+        if let Ok(rejections) = self.rejections.lock() {
+            for (reason, count) in rejections.iter() {
+                let _ = writeln!(out, "rustycanvas_rejections_total{{reason=\"{}\"}} {}", reason, count);
+            }
+        }
+
+        let _ = writeln!(out, "# HELP rustycanvas_active_clients Client addresses that polled GET /updates in the last {}s.", ACTIVE_CLIENT_WINDOW.as_secs());
+        let _ = writeln!(out, "# TYPE rustycanvas_active_clients gauge");
+        let _ = writeln!(out, "rustycanvas_active_clients {}", self.active_clients(now));
+
+        let _ = writeln!(out, "# HELP rustycanvas_history_size Updates currently kept for GET /updates.");
+        let _ = writeln!(out, "# TYPE rustycanvas_history_size gauge");
+        let _ = writeln!(out, "rustycanvas_history_size {}", history_size);
+
+        let _ = writeln!(out, "# HELP rustycanvas_db_seconds Time spent in store calls, by operation.");
+        let _ = writeln!(out, "# TYPE rustycanvas_db_seconds histogram");
+        if let Ok(db) = self.db.lock() {
+            for (op, histogram) in db.iter() {
+                let mut cumulative = 0;
+                for (bound, count) in DB_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                    cumulative += count;
+                    let _ = writeln!(out, "rustycanvas_db_seconds_bucket{{op=\"{}\",le=\"{}\"}} {}", op, bound, cumulative);
+                }
+                let _ = writeln!(out, "rustycanvas_db_seconds_bucket{{op=\"{}\",le=\"+Inf\"}} {}", op, histogram.count);
+                let _ = writeln!(out, "rustycanvas_db_seconds_sum{{op=\"{}\"}} {}", op, histogram.sum);
+                let _ = writeln!(out, "rustycanvas_db_seconds_count{{op=\"{}\"}} {}", op, histogram.count);
+            }
+        }
+
+        out
+    }
+}
+
+// Middleware (see routes.rs): counts failed requests by their error code and who is polling /updates, and logs
+// every request once it's answered. Successful reads are logged at debug level, or every poll would fill the log
+// The client's address is only known when the server runs with connect info (main.rs), not in the tests' oneshot calls
+pub async fn track_requests(State(app_state): State<AppState>, request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let is_read = request.method() == Method::GET;
+    let poller = (request.method() == Method::GET && request.uri().path() == "/updates")
+        .then(|| request.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip()))
+        .flatten();
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16();
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+    if is_read && response.status().is_success() {
+        tracing::debug!(status, latency_ms, "request done");
+    } else {
+        tracing::info!(status, latency_ms, "request done");
+    }
+
+    // AppError leaves itself on the response it becomes (see error.rs)
+    if let Some(err) = response.extensions().get::<AppError>() {
+        app_state.metrics.rejected(err.code());
+    } else if let Some(ip) = poller {
+        app_state.metrics.client_polled(ip, Instant::now());
+    }
+    response
+}
+
+// A CanvasStore that times every call into the Metrics, then hands it to the real store
+pub struct TimedStore {
+    inner: Arc<dyn CanvasStore>,
+    metrics: Arc<Metrics>,
+}
+
+impl TimedStore {
+    pub fn new(inner: Arc<dyn CanvasStore>, metrics: Arc<Metrics>) -> TimedStore {
+        TimedStore { inner, metrics }
+    }
+
+    fn timed<T>(&self, op: &'static str, call: impl FnOnce(&dyn CanvasStore) -> T) -> T {
+        let start = Instant::now();
+        let result = call(self.inner.as_ref());
+        self.metrics.db_call(op, start.elapsed());
+        result
+    }
+}
+
+impl CanvasStore for TimedStore {
+    fn load_canvas(&self) -> Result<PackedCanvas, AppError> {
+        self.timed("load_canvas", |store| store.load_canvas())
+    }
+
+    fn write_rows(&self, rows: &[(u32, Vec<u8>)]) -> Result<(), AppError> {
+        self.timed("write_rows", |store| store.write_rows(rows))
+    }
+
+    fn clear_pixels(&self) -> Result<(), AppError> {
+        self.timed("clear_pixels", |store| store.clear_pixels())
+    }
+
+    fn get_device(&self, device_id: &str) -> Result<Option<DeviceStatus>, AppError> {
+        self.timed("get_device", |store| store.get_device(device_id))
+    }
+
+    fn put_device(&self, status: &DeviceStatus) -> Result<(), AppError> {
+        self.timed("put_device", |store| store.put_device(status))
+    }
+
+    fn devices(&self) -> Result<Vec<DeviceStatus>, AppError> {
+        self.timed("devices", |store| store.devices())
+    }
+
+    fn get_snapshot(&self, name: &str) -> Result<Option<CanvasArchive>, AppError> {
+        self.timed("get_snapshot", |store| store.get_snapshot(name))
+    }
+
+    fn put_snapshot(&self, name: &str, archive: &CanvasArchive) -> Result<(), AppError> {
+        self.timed("put_snapshot", |store| store.put_snapshot(name, archive))
+    }
+
+    fn delete_snapshot(&self, name: &str) -> Result<bool, AppError> {
+        self.timed("delete_snapshot", |store| store.delete_snapshot(name))
+    }
+
+    fn snapshots(&self) -> Result<Vec<(String, CanvasArchive)>, AppError> {
+        self.timed("snapshots", |store| store.snapshots())
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<String>, AppError> {
+        self.timed("get_setting", |store| store.get_setting(key))
+    }
+
+    fn put_setting(&self, key: &str, value: &str) -> Result<(), AppError> {
+        self.timed("put_setting", |store| store.put_setting(key, value))
+    }
+
+    fn delete_setting(&self, key: &str) -> Result<(), AppError> {
+        self.timed("delete_setting", |store| store.delete_setting(key))
+    }
+}
@@ -11,4 +11,13 @@
 
 pub mod routes;
 pub mod handlers;
-pub mod state;
\ No newline at end of file
+pub mod error;
+pub mod metrics;
+pub mod state;
+pub mod discovery;
+pub mod store;
+pub mod cache;
+pub mod shutdown;
+pub mod schedule;
+pub mod mode;
+pub mod health;
\ No newline at end of file
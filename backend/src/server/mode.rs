@@ -0,0 +1,66 @@
+// server/mode.rs
+
+// This module holds the board mode: an admin switch that freezes the board without stopping the server
+
+// For your knowledge
+// - open: pixels can be placed as usual (a scheduled event can still close the board, see schedule.rs)
+// - read_only: POST /pixel is refused with "read_only" (403), e.g. while cleaning up after vandalism
+// - maintenance: POST /pixel is refused with "maintenance" (503), clients should try again later
+// GET /canvas and GET /updates keep working in every mode, and every /updates answer carries the mode,
+// so clients find out about a change on their next poll
+// The mode is saved in the store's settings, so a board frozen in a hurry stays frozen across a restart
+// When in doubt the board stays shut: a saved mode that can't be read comes back read-only, and a lock poisoned
+// by a panic still gives the last mode that was set
+
+use protocol::{BoardMode, ModeStatus};
+use crate::server::error::AppError;
+use crate::server::state::AppState;
+use crate::server::store::CanvasStore;
+
+const MODE_SETTING: &str = "mode";
+
+// Logic to read the mode saved by a previous run: open if none was saved, read-only if it can't be parsed
+// Fails if the store can't be read
+pub fn load_mode(store: &dyn CanvasStore) -> Result<ModeStatus, AppError> {
+    let Some(value) = store.get_setting(MODE_SETTING)? else {
+        return Ok(ModeStatus { mode: BoardMode::Open, changed_at: 0 });
+    };
+    match serde_json::from_str(&value) {
+        Ok(saved) => Ok(saved),
+        Err(e) => {
+            tracing::warn!(saved = %value, "Could not read the saved board mode ({}), starting read-only", e);
+            Ok(ModeStatus { mode: BoardMode::ReadOnly, changed_at: 0 })
+        }
+    }
+}
+
+// Logic to read the current mode
+pub fn current_mode(state: &AppState) -> ModeStatus {
+    match state.mode.read() {
+        Ok(mode) => mode.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+// Logic to switch modes. Setting the mode it's already in keeps the old changed_at
+pub fn set_mode(state: &AppState, mode: BoardMode, now: u64) -> Result<ModeStatus, AppError> {
+    let mut current = state.mode.write().map_err(|_| AppError::DbWriteError)?;
+    if current.mode == mode {
+        return Ok(current.clone());
+    }
+
+    let status = ModeStatus { mode, changed_at: now };
+    let value = serde_json::to_string(&status).map_err(|_| AppError::DbWriteError)?;
+    state.store.put_setting(MODE_SETTING, &value)?;
+    *current = status.clone();
+    Ok(status)
+}
+
+// Logic to check whether the mode lets pixels through
+pub fn check_mode_allows_pixels(state: &AppState) -> Result<(), AppError> {
+    match current_mode(state).mode {
+        BoardMode::Open => Ok(()),
+        BoardMode::ReadOnly => Err(AppError::ReadOnly),
+        BoardMode::Maintenance => Err(AppError::Maintenance),
+    }
+}
@@ -4,21 +4,86 @@
 
 // For your knowledge
 // A route maps the HTTP request and a URL path to a specific handler function
+// Layers wrap every route. Each request gets an ID (the client's X-Request-Id, or a new UUID) which is sent back
+// in the response and put on a tracing span, so every log line written while handling it carries the ID
 
-use axum::{Router, routing::{get, post}};
+use axum::{Router, middleware, routing::{delete, get, post}};
+use axum::body::Body;
+use axum::http::Request;
+use tower::ServiceBuilder;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing::Span;
 use crate::server::state::AppState;
+use crate::server::metrics::track_requests;
+use crate::server::error::{method_not_allowed, unknown_route};
 use crate::server::handlers::{
     get_canvas_handler,
     update_pixel_handler,
     reset_canvas_handler,
     get_updates_handler,
+    register_device_handler,
+    device_heartbeat_handler,
+    get_devices_handler,
+    queue_test_pattern_handler,
+    get_backup_handler,
+    restore_backup_handler,
+    get_snapshots_handler,
+    create_snapshot_handler,
+    restore_snapshot_handler,
+    delete_snapshot_handler,
+    get_event_handler,
+    schedule_event_handler,
+    cancel_event_handler,
+    get_mode_handler,
+    set_mode_handler,
+    get_metrics_handler,
+    get_health_handler,
+    get_readiness_handler,
+    get_version_handler,
 };
 
 // Function to create and return the router with all defined routes
-pub fn create_router() -> Router<AppState> {
+pub fn create_router(app_state: AppState) -> Router {
     Router::new()
         .route("/canvas", get(get_canvas_handler))
         .route("/pixel", post(update_pixel_handler))
         .route("/reset", post(reset_canvas_handler))
         .route("/updates", get(get_updates_handler))
+        .route("/devices", get(get_devices_handler))
+        .route("/devices/register", post(register_device_handler))
+        .route("/devices/heartbeat", post(device_heartbeat_handler))
+        .route("/devices/{device_id}/test-pattern", post(queue_test_pattern_handler))
+        .route("/backup", get(get_backup_handler))
+        .route("/restore", post(restore_backup_handler))
+        .route("/snapshots", get(get_snapshots_handler).post(create_snapshot_handler))
+        .route("/snapshots/{name}", delete(delete_snapshot_handler))
+        .route("/snapshots/{name}/restore", post(restore_snapshot_handler))
+        .route("/event", get(get_event_handler).post(schedule_event_handler).delete(cancel_event_handler))
+        .route("/mode", get(get_mode_handler).post(set_mode_handler))
+        .route("/metrics", get(get_metrics_handler))
+        .route("/healthz", get(get_health_handler))
+        .route("/readyz", get(get_readiness_handler))
+        .route("/version", get(get_version_handler))
+        // Anything else still gets a JSON error body
+        .fallback(unknown_route)
+        .method_not_allowed_fallback(method_not_allowed)
+        // Counts and logs each request, inside its span (see metrics.rs)
+        .layer(middleware::from_fn_with_state(app_state.clone(), track_requests))
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                // Only opens the span, track_requests and AppError write the log lines
+                .layer(TraceLayer::new_for_http().make_span_with(request_span).on_request(()).on_response(()).on_failure(()))
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        )
+        .with_state(app_state)
+}
+
+fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request.headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    tracing::info_span!("request", id = %request_id, method = %request.method(), path = %request.uri().path())
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+// server/schedule.rs
+
+// This module runs timed events: the board opens at a set time, goes read-only after a while,
+// and can be saved as a snapshot and cleared when it closes (e.g. a 5-minute class demo)
+
+// For your knowledge
+// There is at most one event. Its phase (scheduled, open, closed) is worked out from the clock on every request,
+// so opening and closing happen exactly on time without anything running in the background
+// The snapshot and reset at closing time do need to run by themselves, so a task checks once a second (spawn_scheduler)
+// The event is saved in the store's settings, so a restart in the middle of a demo picks it up again
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use protocol::{EventPhase, EventSchedule, EventScheduleInput, EventStatus};
+use crate::server::handlers::{now_millis, reset_board, save_snapshot, validate_snapshot_name};
+use crate::server::error::AppError;
+use crate::server::state::AppState;
+use crate::server::store::CanvasStore;
+
+const EVENT_SETTING: &str = "event";
+// A week is plenty for a demo, and keeps closes_at far from overflowing
+const MAX_OPEN_MINUTES: u64 = 7 * 24 * 60;
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// What the store keeps: the schedule, and how far its closing actions got
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledEvent {
+    pub schedule: EventSchedule,
+    // So a reset that failed and is retried doesn't try to save the snapshot again
+    pub snapshot_saved: bool,
+    pub close_handled: bool,
+}
+
+// Logic to place 'now' in the schedule
+pub fn event_phase(schedule: Option<&EventSchedule>, now: u64) -> EventPhase {
+    match schedule {
+        None => EventPhase::Idle,
+        Some(schedule) if now < schedule.opens_at => EventPhase::Scheduled,
+        Some(schedule) if now < schedule.closes_at => EventPhase::Open,
+        Some(_) => EventPhase::Closed,
+    }
+}
+
+// Logic to read the event saved by a previous run (a value we can't read is dropped, like devices)
+pub fn load_event(store: &dyn CanvasStore) -> Result<Option<ScheduledEvent>, AppError> {
+    let value = store.get_setting(EVENT_SETTING)?;
+    Ok(value.and_then(|value| serde_json::from_str(&value).ok()))
+}
+
+fn save_event(store: &dyn CanvasStore, event: Option<&ScheduledEvent>) -> Result<(), AppError> {
+    match event {
+        Some(event) => {
+            let value = serde_json::to_string(event).map_err(|_| AppError::DbWriteError)?;
+            store.put_setting(EVENT_SETTING, &value)
+        }
+        None => store.delete_setting(EVENT_SETTING),
+    }
+}
+
+// Logic to describe the current event for GET /event
+pub fn event_status(state: &AppState, now: u64) -> EventStatus {
+    let schedule = state.event.read().ok().and_then(|event| event.as_ref().map(|event| event.schedule.clone()));
+    EventStatus {
+        phase: event_phase(schedule.as_ref(), now),
+        schedule,
+        server_time: now,
+    }
+}
+
+// Logic to check whether pixels may be placed right now
+pub fn check_canvas_open(state: &AppState, now: u64) -> Result<(), AppError> {
+    let event = state.event.read().map_err(|_| AppError::DbReadError)?;
+    match event_phase(event.as_ref().map(|event| &event.schedule), now) {
+        EventPhase::Idle | EventPhase::Open => Ok(()),
+        EventPhase::Scheduled | EventPhase::Closed => Err(AppError::CanvasClosed),
+    }
+}
+
+// Logic to set up an event, replacing any previous one
+pub fn schedule_event(state: &AppState, input: &EventScheduleInput, now: u64) -> Result<EventStatus, AppError> {
+    if input.open_minutes == 0 || input.open_minutes > MAX_OPEN_MINUTES {
+        return Err(AppError::InvalidDuration);
+    }
+    if let Some(name) = &input.snapshot_on_close {
+        validate_snapshot_name(name)?;
+        if state.store.get_snapshot(name)?.is_some() {
+            return Err(AppError::SnapshotExists);
+        }
+    }
+
+    let opens_at = input.opens_at.unwrap_or(now);
+    let closes_at = opens_at.checked_add(input.open_minutes * 60 * 1000).ok_or(AppError::InvalidOpensAt)?;
+    // It would be over before it started
+    if closes_at <= now {
+        return Err(AppError::InvalidOpensAt);
+    }
+
+    let event = ScheduledEvent {
+        schedule: EventSchedule {
+            opens_at,
+            closes_at,
+            snapshot_on_close: input.snapshot_on_close.clone(),
+            reset_on_close: input.reset_on_close,
+        },
+        snapshot_saved: false,
+        close_handled: false,
+    };
+    let mut current = state.event.write().map_err(|_| AppError::DbWriteError)?;
+    save_event(state.store.as_ref(), Some(&event))?;
+    *current = Some(event);
+    drop(current);
+
+    Ok(event_status(state, now))
+}
+
+// Logic to drop the event, the board is open as usual again
+pub fn cancel_event(state: &AppState, now: u64) -> Result<EventStatus, AppError> {
+    let mut current = state.event.write().map_err(|_| AppError::DbWriteError)?;
+    save_event(state.store.as_ref(), None)?;
+    *current = None;
+    drop(current);
+
+    Ok(event_status(state, now))
+}
+
+// Logic to run the closing actions once the event has closed. Returns true if it did anything
+// If the snapshot can't be saved the board is not cleared, so a finished drawing is never lost
+// Store errors are retried on the next check. A snapshot name taken since the event was scheduled is not,
+// the board is just left as it is
+pub fn run_close_actions(state: &AppState, now: u64) -> Result<bool, AppError> {
+    let mut current = state.event.write().map_err(|_| AppError::DbWriteError)?;
+    let Some(event) = current.as_mut() else {
+        return Ok(false);
+    };
+    if event.close_handled || event_phase(Some(&event.schedule), now) != EventPhase::Closed {
+        return Ok(false);
+    }
+
+    if let Some(name) = event.schedule.snapshot_on_close.as_ref().filter(|_| !event.snapshot_saved) {
+        match save_snapshot(state, name, now) {
+            Ok(_) => {
+                event.snapshot_saved = true;
+                save_event(state.store.as_ref(), Some(event))?;
+            }
+            Err(AppError::SnapshotExists) => {
+                event.close_handled = true;
+                save_event(state.store.as_ref(), Some(event))?;
+                return Err(AppError::SnapshotExists);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if event.schedule.reset_on_close {
+        reset_board(state, now)?;
+    }
+
+    event.close_handled = true;
+    save_event(state.store.as_ref(), Some(event))?;
+    Ok(true)
+}
+
+// Start the task that runs the closing actions on time
+// Must be called from inside the tokio runtime
+pub fn spawn_scheduler(state: AppState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let state = state.clone();
+            // Snapshots and resets write to the store, keep them off the async worker threads
+            let result = tokio::task::spawn_blocking(move || run_close_actions(&state, now_millis())).await;
+            match result {
+                Ok(Ok(true)) => tracing::info!("Event closed, closing actions done"),
+                Ok(Ok(false)) => {}
+                Ok(Err(e)) => tracing::warn!("Event closing actions failed: {}", e),
+                Err(e) => tracing::error!("Event scheduler task failed: {}", e),
+            }
+        }
+    })
+}
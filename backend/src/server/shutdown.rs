@@ -0,0 +1,100 @@
+// server/shutdown.rs
+
+// This module handles stopping the server cleanly on Ctrl-C (SIGINT) or SIGTERM
+
+// For your knowledge
+// Stopping happens in three steps:
+//  1. Notice: the server keeps serving for a moment, but every GET /updates answers shutting_down: true,
+//     so polling clients (web page, TUI, client stream) find out before their requests start failing
+//  2. Drain: no new connections are accepted, requests already running are allowed to finish
+//     If they take longer than the drain timeout we stop waiting for them
+//  3. Flush: main.rs writes the canvas rows the write-behind hasn't committed yet (see cache.rs)
+// SHUTDOWN_NOTICE_MS and SHUTDOWN_DRAIN_SECS change how long steps 1 and 2 may take
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+const DEFAULT_NOTICE_MS: u64 = 1500;
+const DEFAULT_DRAIN_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownConfig {
+    // How long clients get to see shutting_down before we stop accepting connections
+    pub notice: Duration,
+    // How long in-flight requests get to finish
+    pub drain: Duration,
+}
+
+impl ShutdownConfig {
+    // Read from SHUTDOWN_NOTICE_MS and SHUTDOWN_DRAIN_SECS
+    pub fn from_env() -> Result<ShutdownConfig, String> {
+        ShutdownConfig::parse(
+            std::env::var("SHUTDOWN_NOTICE_MS").ok().as_deref(),
+            std::env::var("SHUTDOWN_DRAIN_SECS").ok().as_deref(),
+        )
+    }
+
+    pub fn parse(notice_ms: Option<&str>, drain_secs: Option<&str>) -> Result<ShutdownConfig, String> {
+        Ok(ShutdownConfig {
+            notice: Duration::from_millis(parse_number("SHUTDOWN_NOTICE_MS", notice_ms, DEFAULT_NOTICE_MS)?),
+            drain: Duration::from_secs(parse_number("SHUTDOWN_DRAIN_SECS", drain_secs, DEFAULT_DRAIN_SECS)?),
+        })
+    }
+}
+
+fn parse_number(name: &str, value: Option<&str>, default: u64) -> Result<u64, String> {
+    match value.map(str::trim).filter(|value| !value.is_empty()) {
+        Some(value) => value.parse().map_err(|_| format!("{} must be a whole number, got '{}'", name, value)),
+        None => Ok(default),
+    }
+}
+
+// Shared between the handlers and main.rs, set once a stop signal arrives
+#[derive(Clone, Default)]
+pub struct ShutdownFlag(Arc<AtomicBool>);
+
+impl ShutdownFlag {
+    pub fn trigger(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// Wait for Ctrl-C or SIGTERM (what systemd and docker send), returns the signal's name for the log
+pub async fn wait_for_signal() -> &'static str {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            // Can't listen for SIGTERM, Ctrl-C still works
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => "SIGINT",
+        _ = terminate => "SIGTERM",
+    }
+}
+
+// Passed to axum's with_graceful_shutdown: wait for a signal, announce it, give clients the notice period,
+// then let axum stop accepting connections. 'signalled' fires at that point so main.rs can start the drain timeout
+pub async fn notice_period(flag: ShutdownFlag, config: ShutdownConfig, signalled: tokio::sync::oneshot::Sender<()>) {
+    let signal = wait_for_signal().await;
+    tracing::info!(signal, notice = ?config.notice, drain = ?config.drain, "Shutting down");
+    flag.trigger();
+    tokio::time::sleep(config.notice).await;
+    let _ = signalled.send(());
+}
@@ -1,38 +1,69 @@
 // server/state.rs
 
 // This module manages the global canvas state. It supports:
-//  - Storing the canvas state persistently using Sled key-value store
+//  - Storing the canvas state persistently through a CanvasStore (sled, SQLite or memory, see store/mod.rs)
+//  - Serving the canvas from memory, with writes committed to the store in batches (see cache.rs)
 
-use sled::Db;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::AtomicU64;
 use std::collections::VecDeque;
-use serde::Serialize;
+use std::time::Instant;
 
-pub const CANVAS_WIDTH: u32 = 32;
-pub const CANVAS_HEIGHT: u32 = 16;
-pub const DEFAULT_COLOR: &str = "#000000";
-
-#[derive(Clone, Serialize, Debug)]
-pub struct PixelUpdate {
-    pub x: u32,
-    pub y: u32,
-    pub color: String,
-    pub timestamp: u64,
-}
+// Canvas dimensions and the history entry type are shared with the frontend and firmware
+pub use protocol::{CANVAS_WIDTH, CANVAS_HEIGHT, DEFAULT_COLOR, PixelUpdate};
+use crate::server::cache::{CanvasCache, WritePolicy};
+use crate::server::store::CanvasStore;
+use crate::server::shutdown::ShutdownFlag;
+use crate::server::schedule::{self, ScheduledEvent};
+use crate::server::mode;
+use crate::server::metrics::{Metrics, TimedStore};
+use protocol::ModeStatus;
 
 #[derive(Clone)] 
 pub struct AppState {
-    pub db: Db,
+    // Devices are read and written straight through the store, pixels through 'canvas'
+    pub store: Arc<dyn CanvasStore>,
+    pub canvas: Arc<CanvasCache>,
     pub history: Arc<RwLock<VecDeque<PixelUpdate>>>,
+    // Server time (ms) the whole board was last replaced by a restore, 0 if never
+    // Clients polling from before then are told to reload (reset_required)
+    pub replaced_at: Arc<AtomicU64>,
+    // When set, admin routes (e.g. POST /reset) require "Authorization: Bearer <token>"
+    pub admin_token: Option<String>,
+    // Set once the server has been asked to stop, clients are told through GET /updates (see shutdown.rs)
+    pub shutdown: ShutdownFlag,
+    // The timed event, if one is set up (see schedule.rs)
+    pub event: Arc<RwLock<Option<ScheduledEvent>>>,
+    // Open, read-only or maintenance, switched by an admin (see mode.rs)
+    pub mode: Arc<RwLock<ModeStatus>>,
+    // Served on GET /metrics (see metrics.rs)
+    pub metrics: Arc<Metrics>,
+    // When the state was built, for the uptime on GET /healthz
+    pub started_at: Instant,
+    // Server time (ms) GET /readyz last wrote to the store, 0 if never (see health.rs)
+    pub ready_write_at: Arc<AtomicU64>,
 }
 
 
-pub fn init_app_state(path: &str) -> AppState {
-    // sled::open creates the database directory if it doesn't exist and recovers previous state if it does
-    let db = sled::open(path).expect("Failed to open Sled database");
+pub fn init_app_state(store: Arc<dyn CanvasStore>, policy: WritePolicy) -> AppState {
+    // Every store call is timed for GET /metrics
+    let metrics = Arc::new(Metrics::default());
+    let store: Arc<dyn CanvasStore> = Arc::new(TimedStore::new(store, metrics.clone()));
+    let canvas = CanvasCache::load(store.clone(), policy).expect("Failed to load the canvas");
+    let event = schedule::load_event(store.as_ref()).expect("Failed to load the scheduled event");
+    let mode = mode::load_mode(store.as_ref()).expect("Failed to load the board mode");
 
     AppState {
-        db,
+        store,
+        canvas: Arc::new(canvas),
         history: Arc::new(RwLock::new(VecDeque::new())),
+        replaced_at: Arc::new(AtomicU64::new(0)),
+        admin_token: None,
+        shutdown: ShutdownFlag::default(),
+        event: Arc::new(RwLock::new(event)),
+        mode: Arc::new(RwLock::new(mode)),
+        metrics,
+        started_at: Instant::now(),
+        ready_write_at: Arc::new(AtomicU64::new(0)),
     }
 }
\ No newline at end of file
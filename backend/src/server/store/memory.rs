@@ -0,0 +1,98 @@
+// server/store/memory.rs
+
+// A CanvasStore that only lives in memory, everything is gone when it is dropped
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use protocol::{CanvasArchive, DeviceStatus};
+use super::{CanvasStore, PackedCanvas};
+use crate::server::error::AppError;
+
+#[derive(Default)]
+pub struct MemoryStore {
+    canvas: RwLock<PackedCanvas>,
+    // BTreeMap keeps the devices sorted by ID
+    devices: RwLock<BTreeMap<String, DeviceStatus>>,
+    snapshots: RwLock<BTreeMap<String, CanvasArchive>>,
+    settings: RwLock<BTreeMap<String, String>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CanvasStore for MemoryStore {
+    fn load_canvas(&self) -> Result<PackedCanvas, AppError> {
+        let canvas = self.canvas.read().map_err(|_| AppError::DbReadError)?;
+        Ok(canvas.clone())
+    }
+
+    fn write_rows(&self, rows: &[(u32, Vec<u8>)]) -> Result<(), AppError> {
+        let mut canvas = self.canvas.write().map_err(|_| AppError::DbWriteError)?;
+        for (y, row) in rows {
+            canvas.set_row(*y, row);
+        }
+        Ok(())
+    }
+
+    fn clear_pixels(&self) -> Result<(), AppError> {
+        *self.canvas.write().map_err(|_| AppError::DbClearError)? = PackedCanvas::default();
+        Ok(())
+    }
+
+    fn get_device(&self, device_id: &str) -> Result<Option<DeviceStatus>, AppError> {
+        let devices = self.devices.read().map_err(|_| AppError::DbReadError)?;
+        Ok(devices.get(device_id).cloned())
+    }
+
+    fn put_device(&self, status: &DeviceStatus) -> Result<(), AppError> {
+        let mut devices = self.devices.write().map_err(|_| AppError::DbWriteError)?;
+        devices.insert(status.device_id.clone(), status.clone());
+        Ok(())
+    }
+
+    fn devices(&self) -> Result<Vec<DeviceStatus>, AppError> {
+        let devices = self.devices.read().map_err(|_| AppError::DbReadError)?;
+        Ok(devices.values().cloned().collect())
+    }
+
+    fn get_snapshot(&self, name: &str) -> Result<Option<CanvasArchive>, AppError> {
+        let snapshots = self.snapshots.read().map_err(|_| AppError::DbReadError)?;
+        Ok(snapshots.get(name).cloned())
+    }
+
+    fn put_snapshot(&self, name: &str, archive: &CanvasArchive) -> Result<(), AppError> {
+        let mut snapshots = self.snapshots.write().map_err(|_| AppError::DbWriteError)?;
+        snapshots.insert(name.to_string(), archive.clone());
+        Ok(())
+    }
+
+    fn delete_snapshot(&self, name: &str) -> Result<bool, AppError> {
+        let mut snapshots = self.snapshots.write().map_err(|_| AppError::DbWriteError)?;
+        Ok(snapshots.remove(name).is_some())
+    }
+
+    fn snapshots(&self) -> Result<Vec<(String, CanvasArchive)>, AppError> {
+        let snapshots = self.snapshots.read().map_err(|_| AppError::DbReadError)?;
+        Ok(snapshots.iter().map(|(name, archive)| (name.clone(), archive.clone())).collect())
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<String>, AppError> {
+        let settings = self.settings.read().map_err(|_| AppError::DbReadError)?;
+        Ok(settings.get(key).cloned())
+    }
+
+    fn put_setting(&self, key: &str, value: &str) -> Result<(), AppError> {
+        let mut settings = self.settings.write().map_err(|_| AppError::DbWriteError)?;
+        settings.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete_setting(&self, key: &str) -> Result<(), AppError> {
+        let mut settings = self.settings.write().map_err(|_| AppError::DbWriteError)?;
+        settings.remove(key);
+        Ok(())
+    }
+}
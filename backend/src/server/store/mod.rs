@@ -0,0 +1,184 @@
+// server/store/mod.rs
+
+// This module defines where the canvas, the device list, the canvas snapshots and the server settings are kept
+
+// For your knowledge
+// Handler logic only talks to the 'CanvasStore' trait, never to a database directly
+// There are three implementations, picked when the server starts (see StoreConfig::from_env):
+//  - SledStore: the embedded key-value store the server has always used (data/canvas_db)
+//  - SqliteStore: a single SQLite file, for when sled's on-disk format is a worry
+//  - MemoryStore: nothing on disk, used by the tests and for throwaway servers
+// Errors are AppError::Db*Error, which the handlers pass on in their JSON responses (see error.rs)
+//
+// The canvas is kept as packed RGB rows (3 bytes per pixel, one row per key), so reading the whole board is
+// one scan of CANVAS_HEIGHT values instead of a lookup per pixel. Rows are written whole, in batches (see cache.rs). Rows stored at a different width (after the canvas size changes) are cut or padded when read
+// Older databases kept one "x:y" key per pixel; the sled and SQLite stores convert them when they're opened
+
+pub mod memory;
+pub mod sled_store;
+pub mod sqlite;
+
+use std::sync::Arc;
+use protocol::{CanvasArchive, DeviceStatus, Rgb, CANVAS_HEIGHT, CANVAS_WIDTH, DEFAULT_COLOR};
+use crate::server::error::AppError;
+
+pub use memory::MemoryStore;
+pub use sled_store::SledStore;
+pub use sqlite::SqliteStore;
+
+pub trait CanvasStore: Send + Sync {
+    // The whole board. Pixels never drawn are DEFAULT_COLOR
+    fn load_canvas(&self) -> Result<PackedCanvas, AppError>;
+
+    // Replace whole rows as (y, packed RGB), all of them made durable together (one flush or transaction)
+    // This is what the write-behind cache uses to commit a batch of updates (see server/cache.rs)
+    fn write_rows(&self, rows: &[(u32, Vec<u8>)]) -> Result<(), AppError>;
+
+    // Forget every pixel (the device list is kept)
+    fn clear_pixels(&self) -> Result<(), AppError>;
+
+    fn get_device(&self, device_id: &str) -> Result<Option<DeviceStatus>, AppError>;
+
+    // Add or replace the device with status.device_id
+    fn put_device(&self, status: &DeviceStatus) -> Result<(), AppError>;
+
+    // Every device, sorted by ID
+    fn devices(&self) -> Result<Vec<DeviceStatus>, AppError>;
+
+    // Named copies of the board (see POST /snapshots). Like devices, clearing the canvas keeps them
+    fn get_snapshot(&self, name: &str) -> Result<Option<CanvasArchive>, AppError>;
+
+    // Add or replace the snapshot called 'name'
+    fn put_snapshot(&self, name: &str, archive: &CanvasArchive) -> Result<(), AppError>;
+
+    // Returns false if there was no such snapshot
+    fn delete_snapshot(&self, name: &str) -> Result<bool, AppError>;
+
+    // Every snapshot, sorted by name
+    fn snapshots(&self) -> Result<Vec<(String, CanvasArchive)>, AppError>;
+
+    // Small named values (JSON) that must survive a restart, e.g. the scheduled event (see schedule.rs)
+    fn get_setting(&self, key: &str) -> Result<Option<String>, AppError>;
+
+    fn put_setting(&self, key: &str, value: &str) -> Result<(), AppError>;
+
+    fn delete_setting(&self, key: &str) -> Result<(), AppError>;
+}
+
+// Which store to open, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreConfig {
+    Sled(String),
+    Sqlite(String),
+    Memory,
+}
+
+impl StoreConfig {
+    // Read from CANVAS_STORE ("sled", "sqlite" or "memory", default "sled") and CANVAS_DB_PATH
+    pub fn from_env() -> Result<StoreConfig, String> {
+        let kind = std::env::var("CANVAS_STORE").unwrap_or_else(|_| "sled".to_string());
+        let path = std::env::var("CANVAS_DB_PATH").ok().filter(|path| !path.is_empty());
+        StoreConfig::parse(&kind, path)
+    }
+
+    // 'path' defaults to data/canvas_db for sled and data/canvas.sqlite3 for SQLite
+    pub fn parse(kind: &str, path: Option<String>) -> Result<StoreConfig, String> {
+        match kind.to_ascii_lowercase().as_str() {
+            "sled" => Ok(StoreConfig::Sled(path.unwrap_or_else(|| "data/canvas_db".to_string()))),
+            "sqlite" => Ok(StoreConfig::Sqlite(path.unwrap_or_else(|| "data/canvas.sqlite3".to_string()))),
+            "memory" => Ok(StoreConfig::Memory),
+            other => Err(format!("unknown store '{}', expected sled, sqlite or memory", other)),
+        }
+    }
+
+    pub fn open(&self) -> Result<Arc<dyn CanvasStore>, String> {
+        Ok(match self {
+            StoreConfig::Sled(path) => Arc::new(SledStore::open(path).map_err(|e| e.to_string())?),
+            StoreConfig::Sqlite(path) => Arc::new(SqliteStore::open(path).map_err(|e| e.to_string())?),
+            StoreConfig::Memory => Arc::new(MemoryStore::new()),
+        })
+    }
+}
+
+// The canvas as CANVAS_HEIGHT rows of packed RGB bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedCanvas {
+    rows: Vec<Vec<u8>>,
+}
+
+impl Default for PackedCanvas {
+    fn default() -> Self {
+        PackedCanvas { rows: vec![blank_row(); CANVAS_HEIGHT as usize] }
+    }
+}
+
+impl PackedCanvas {
+    // Colour at (x, y), None if it's off the canvas
+    pub fn get(&self, x: u32, y: u32) -> Option<Rgb> {
+        let row = self.rows.get(y as usize)?;
+        let at = x as usize * 3;
+        let rgb = row.get(at..at + 3)?;
+        Some(Rgb::new(rgb[0], rgb[1], rgb[2]))
+    }
+
+    // Returns false (and changes nothing) if (x, y) is off the canvas
+    pub fn set(&mut self, x: u32, y: u32, color: Rgb) -> bool {
+        match self.rows.get_mut(y as usize) {
+            Some(row) => set_in_row(row, x, color),
+            None => false,
+        }
+    }
+
+    // Row 'y' as it would be with (x, y) set to 'color', None if (x, y) is off the canvas
+    pub fn row_with(&self, x: u32, y: u32, color: Rgb) -> Option<Vec<u8>> {
+        let mut row = self.rows.get(y as usize)?.clone();
+        set_in_row(&mut row, x, color).then_some(row)
+    }
+
+    pub fn row(&self, y: u32) -> Option<&[u8]> {
+        self.rows.get(y as usize).map(Vec::as_slice)
+    }
+
+    // Replace row 'y' with a stored row (cut or padded to the canvas width)
+    pub fn set_row(&mut self, y: u32, stored: &[u8]) {
+        if let Some(row) = self.rows.get_mut(y as usize) {
+            *row = fit_row(stored);
+        }
+    }
+}
+
+// A row with every pixel DEFAULT_COLOR
+pub fn blank_row() -> Vec<u8> {
+    let color = Rgb::parse_hex(DEFAULT_COLOR).unwrap_or(Rgb::BLACK);
+    [color.r, color.g, color.b].repeat(CANVAS_WIDTH as usize)
+}
+
+// A stored row at the current canvas width
+fn fit_row(stored: &[u8]) -> Vec<u8> {
+    let mut row = blank_row();
+    let len = row.len().min(stored.len() / 3 * 3);
+    row[..len].copy_from_slice(&stored[..len]);
+    row
+}
+
+// Set pixel 'x' of a packed row. Returns false if it's off the row
+fn set_in_row(row: &mut [u8], x: u32, color: Rgb) -> bool {
+    let at = x as usize * 3;
+    match row.get_mut(at..at + 3) {
+        Some(rgb) => {
+            rgb.copy_from_slice(&[color.r, color.g, color.b]);
+            true
+        }
+        None => false,
+    }
+}
+
+// Fold pixels from the old one-key-per-pixel layout into 'canvas', for the sled store's migration
+// Pixels off the canvas or with unreadable colours are dropped
+pub fn merge_legacy_pixels(canvas: &mut PackedCanvas, pixels: impl IntoIterator<Item = (u32, u32, String)>) {
+    for (x, y, color) in pixels {
+        if let Ok(color) = Rgb::parse_hex(&color) {
+            canvas.set(x, y, color);
+        }
+    }
+}
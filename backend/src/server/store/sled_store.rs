@@ -0,0 +1,201 @@
+// server/store/sled_store.rs
+
+// A CanvasStore kept in a sled database directory
+
+// For your knowledge
+// Canvas rows are in sled's default tree under "row:" followed by the row number (big-endian, so they sort in order),
+// with the packed RGB bytes as the value. Devices, snapshots and settings are JSON values in their own "devices",
+// "snapshots" and "settings" trees, so clearing the canvas (which clears the default tree) doesn't forget them
+// Databases from before packed rows have "x:y" keys with hex colour strings. open() folds them into rows and
+// deletes them in one atomic batch, so an interrupted migration just runs again next time
+// Every write is flushed before returning, so an update that was acknowledged survives a crash
+
+use protocol::{CanvasArchive, DeviceStatus, CANVAS_HEIGHT};
+use super::{merge_legacy_pixels, CanvasStore, PackedCanvas};
+use crate::server::error::AppError;
+
+const DEVICES_TREE: &str = "devices";
+const SNAPSHOTS_TREE: &str = "snapshots";
+const SETTINGS_TREE: &str = "settings";
+const ROW_PREFIX: &[u8] = b"row:";
+
+pub struct SledStore {
+    db: sled::Db,
+    devices: sled::Tree,
+    snapshots: sled::Tree,
+    settings: sled::Tree,
+}
+
+impl SledStore {
+    // sled::open creates the database directory if it doesn't exist and recovers previous state if it does
+    pub fn open(path: &str) -> sled::Result<SledStore> {
+        let db = sled::open(path)?;
+        let devices = db.open_tree(DEVICES_TREE)?;
+        let snapshots = db.open_tree(SNAPSHOTS_TREE)?;
+        let settings = db.open_tree(SETTINGS_TREE)?;
+        let store = SledStore { db, devices, snapshots, settings };
+        store.migrate_legacy_pixels()?;
+        Ok(store)
+    }
+
+    // Convert "x:y" pixel keys into packed rows
+    fn migrate_legacy_pixels(&self) -> sled::Result<()> {
+        let mut legacy_keys = Vec::new();
+        let mut legacy_pixels = Vec::new();
+        for entry in self.db.iter() {
+            let (key, value) = entry?;
+            if let Some((x, y)) = parse_legacy_key(&key) {
+                legacy_pixels.push((x, y, String::from_utf8_lossy(&value).into_owned()));
+                legacy_keys.push(key);
+            }
+        }
+        if legacy_keys.is_empty() {
+            return Ok(());
+        }
+
+        let mut canvas = self.read_rows()?;
+        merge_legacy_pixels(&mut canvas, legacy_pixels);
+
+        let mut batch = sled::Batch::default();
+        for y in 0..CANVAS_HEIGHT {
+            if let Some(row) = canvas.row(y) {
+                batch.insert(row_key(y).to_vec(), row);
+            }
+        }
+        for key in &legacy_keys {
+            batch.remove(key);
+        }
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        tracing::info!(count = legacy_keys.len(), "Converted stored pixels to packed rows");
+        Ok(())
+    }
+
+    fn read_rows(&self) -> sled::Result<PackedCanvas> {
+        let mut canvas = PackedCanvas::default();
+        for entry in self.db.scan_prefix(ROW_PREFIX) {
+            let (key, row) = entry?;
+            if let Some(y) = parse_row_key(&key) {
+                canvas.set_row(y, &row);
+            }
+        }
+        Ok(canvas)
+    }
+}
+
+// Key of row 'y', e.g. "row:" followed by 0x00000005
+fn row_key(y: u32) -> [u8; 8] {
+    let mut key = [0u8; 8];
+    key[..4].copy_from_slice(ROW_PREFIX);
+    key[4..].copy_from_slice(&y.to_be_bytes());
+    key
+}
+
+fn parse_row_key(key: &[u8]) -> Option<u32> {
+    let y = key.strip_prefix(ROW_PREFIX)?;
+    Some(u32::from_be_bytes(y.try_into().ok()?))
+}
+
+// The old per-pixel key, e.g. "5:10"
+fn parse_legacy_key(key: &[u8]) -> Option<(u32, u32)> {
+    let (x, y) = std::str::from_utf8(key).ok()?.split_once(':')?;
+    Some((x.parse().ok()?, y.parse().ok()?))
+}
+
+impl CanvasStore for SledStore {
+    fn load_canvas(&self) -> Result<PackedCanvas, AppError> {
+        self.read_rows().map_err(|_| AppError::DbReadError)
+    }
+
+    fn write_rows(&self, rows: &[(u32, Vec<u8>)]) -> Result<(), AppError> {
+        let mut batch = sled::Batch::default();
+        for (y, row) in rows {
+            batch.insert(row_key(*y).to_vec(), row.as_slice());
+        }
+        self.db.apply_batch(batch).map_err(|_| AppError::DbWriteError)?;
+        self.db.flush().map_err(|_| AppError::DbFlushError)?;
+        Ok(())
+    }
+
+    fn clear_pixels(&self) -> Result<(), AppError> {
+        // Sled's clear() removes all items from the Tree
+        self.db.clear().map_err(|_| AppError::DbClearError)?;
+        self.db.flush().map_err(|_| AppError::DbFlushError)?;
+        Ok(())
+    }
+
+    fn get_device(&self, device_id: &str) -> Result<Option<DeviceStatus>, AppError> {
+        let value = self.devices.get(device_id).map_err(|_| AppError::DbReadError)?;
+        // A value we can't read is treated as missing, and gets overwritten
+        Ok(value.and_then(|value| serde_json::from_slice(&value).ok()))
+    }
+
+    fn put_device(&self, status: &DeviceStatus) -> Result<(), AppError> {
+        let value = serde_json::to_vec(status).map_err(|_| AppError::DbWriteError)?;
+        self.devices.insert(status.device_id.as_str(), value).map_err(|_| AppError::DbWriteError)?;
+        self.devices.flush().map_err(|_| AppError::DbFlushError)?;
+        Ok(())
+    }
+
+    fn devices(&self) -> Result<Vec<DeviceStatus>, AppError> {
+        let mut devices = Vec::new();
+        // Sled iterates in key order
+        for entry in self.devices.iter() {
+            let (_, value) = entry.map_err(|_| AppError::DbReadError)?;
+            if let Ok(status) = serde_json::from_slice(&value) {
+                devices.push(status);
+            }
+        }
+        Ok(devices)
+    }
+
+    fn get_snapshot(&self, name: &str) -> Result<Option<CanvasArchive>, AppError> {
+        let value = self.snapshots.get(name).map_err(|_| AppError::DbReadError)?;
+        match value {
+            Some(value) => serde_json::from_slice(&value).map(Some).map_err(|_| AppError::DbReadError),
+            None => Ok(None),
+        }
+    }
+
+    fn put_snapshot(&self, name: &str, archive: &CanvasArchive) -> Result<(), AppError> {
+        let value = serde_json::to_vec(archive).map_err(|_| AppError::DbWriteError)?;
+        self.snapshots.insert(name, value).map_err(|_| AppError::DbWriteError)?;
+        self.snapshots.flush().map_err(|_| AppError::DbFlushError)?;
+        Ok(())
+    }
+
+    fn delete_snapshot(&self, name: &str) -> Result<bool, AppError> {
+        let removed = self.snapshots.remove(name).map_err(|_| AppError::DbWriteError)?;
+        self.snapshots.flush().map_err(|_| AppError::DbFlushError)?;
+        Ok(removed.is_some())
+    }
+
+    fn snapshots(&self) -> Result<Vec<(String, CanvasArchive)>, AppError> {
+        let mut snapshots = Vec::new();
+        for entry in self.snapshots.iter() {
+            let (name, value) = entry.map_err(|_| AppError::DbReadError)?;
+            // Skip anything we can't read rather than hiding every other snapshot
+            if let Ok(archive) = serde_json::from_slice(&value) {
+                snapshots.push((String::from_utf8_lossy(&name).into_owned(), archive));
+            }
+        }
+        Ok(snapshots)
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<String>, AppError> {
+        let value = self.settings.get(key).map_err(|_| AppError::DbReadError)?;
+        Ok(value.map(|value| String::from_utf8_lossy(&value).into_owned()))
+    }
+
+    fn put_setting(&self, key: &str, value: &str) -> Result<(), AppError> {
+        self.settings.insert(key, value.as_bytes()).map_err(|_| AppError::DbWriteError)?;
+        self.settings.flush().map_err(|_| AppError::DbFlushError)?;
+        Ok(())
+    }
+
+    fn delete_setting(&self, key: &str) -> Result<(), AppError> {
+        self.settings.remove(key).map_err(|_| AppError::DbWriteError)?;
+        self.settings.flush().map_err(|_| AppError::DbFlushError)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+// server/store/sqlite.rs
+
+// A CanvasStore kept in a single SQLite file
+
+// For your knowledge
+// rusqlite's Connection can't be shared between threads, so it sits behind a Mutex. Every call is one short query
+// Devices, snapshots and settings are stored as the same JSON the sled store uses, keyed by device_id, name and key
+// The tables are created on first open, so pointing CANVAS_DB_PATH at a new file is all the setup there is
+// Canvas rows are BLOBs of packed RGB keyed by row number
+
+use std::path::Path;
+use std::sync::Mutex;
+use protocol::{CanvasArchive, DeviceStatus};
+use rusqlite::{params, Connection, OptionalExtension};
+use super::{CanvasStore, PackedCanvas};
+use crate::server::error::AppError;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS canvas_rows (
+        y INTEGER PRIMARY KEY NOT NULL,
+        rgb BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS devices (
+        device_id TEXT PRIMARY KEY NOT NULL,
+        status TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS snapshots (
+        name TEXT PRIMARY KEY NOT NULL,
+        archive TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    );
+";
+
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    // Creates the file (and its directory) if needed
+    pub fn open(path: &str) -> Result<SqliteStore, Box<dyn std::error::Error>> {
+        if let Some(parent) = Path::new(path).parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { conn: Mutex::new(conn) })
+    }
+}
+
+fn read_rows(conn: &Connection) -> rusqlite::Result<PackedCanvas> {
+    let mut canvas = PackedCanvas::default();
+    let mut statement = conn.prepare_cached("SELECT y, rgb FROM canvas_rows")?;
+    let rows = statement.query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, Vec<u8>>(1)?)))?;
+    for row in rows {
+        let (y, rgb) = row?;
+        canvas.set_row(y, &rgb);
+    }
+    Ok(canvas)
+}
+
+fn write_row(conn: &Connection, y: u32, rgb: &[u8]) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO canvas_rows (y, rgb) VALUES (?1, ?2) ON CONFLICT (y) DO UPDATE SET rgb = excluded.rgb",
+        params![y, rgb],
+    )?;
+    Ok(())
+}
+
+impl CanvasStore for SqliteStore {
+    fn load_canvas(&self) -> Result<PackedCanvas, AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbReadError)?;
+        read_rows(&conn).map_err(|_| AppError::DbReadError)
+    }
+
+    fn write_rows(&self, rows: &[(u32, Vec<u8>)]) -> Result<(), AppError> {
+        let mut conn = self.conn.lock().map_err(|_| AppError::DbWriteError)?;
+        let transaction = conn.transaction().map_err(|_| AppError::DbWriteError)?;
+        for (y, row) in rows {
+            write_row(&transaction, *y, row).map_err(|_| AppError::DbWriteError)?;
+        }
+        transaction.commit().map_err(|_| AppError::DbWriteError)
+    }
+
+    fn clear_pixels(&self) -> Result<(), AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbClearError)?;
+        conn.execute("DELETE FROM canvas_rows", []).map_err(|_| AppError::DbClearError)?;
+        Ok(())
+    }
+
+    fn get_device(&self, device_id: &str) -> Result<Option<DeviceStatus>, AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbReadError)?;
+        let status: Option<String> = conn
+            .query_row("SELECT status FROM devices WHERE device_id = ?1", params![device_id], |row| row.get(0))
+            .optional()
+            .map_err(|_| AppError::DbReadError)?;
+        // A value we can't read is treated as missing, and gets overwritten
+        Ok(status.and_then(|status| serde_json::from_str(&status).ok()))
+    }
+
+    fn put_device(&self, status: &DeviceStatus) -> Result<(), AppError> {
+        let value = serde_json::to_string(status).map_err(|_| AppError::DbWriteError)?;
+        let conn = self.conn.lock().map_err(|_| AppError::DbWriteError)?;
+        conn.execute(
+            "INSERT INTO devices (device_id, status) VALUES (?1, ?2) ON CONFLICT (device_id) DO UPDATE SET status = excluded.status",
+            params![status.device_id, value],
+        )
+        .map_err(|_| AppError::DbWriteError)?;
+        Ok(())
+    }
+
+    fn devices(&self) -> Result<Vec<DeviceStatus>, AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbReadError)?;
+        let mut statement = conn.prepare_cached("SELECT status FROM devices ORDER BY device_id").map_err(|_| AppError::DbReadError)?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0)).map_err(|_| AppError::DbReadError)?;
+        let mut devices = Vec::new();
+        for status in rows {
+            let status = status.map_err(|_| AppError::DbReadError)?;
+            if let Ok(status) = serde_json::from_str(&status) {
+                devices.push(status);
+            }
+        }
+        Ok(devices)
+    }
+
+    fn get_snapshot(&self, name: &str) -> Result<Option<CanvasArchive>, AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbReadError)?;
+        let archive: Option<String> = conn
+            .query_row("SELECT archive FROM snapshots WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()
+            .map_err(|_| AppError::DbReadError)?;
+        match archive {
+            Some(archive) => serde_json::from_str(&archive).map(Some).map_err(|_| AppError::DbReadError),
+            None => Ok(None),
+        }
+    }
+
+    fn put_snapshot(&self, name: &str, archive: &CanvasArchive) -> Result<(), AppError> {
+        let value = serde_json::to_string(archive).map_err(|_| AppError::DbWriteError)?;
+        let conn = self.conn.lock().map_err(|_| AppError::DbWriteError)?;
+        conn.execute(
+            "INSERT INTO snapshots (name, archive) VALUES (?1, ?2) ON CONFLICT (name) DO UPDATE SET archive = excluded.archive",
+            params![name, value],
+        )
+        .map_err(|_| AppError::DbWriteError)?;
+        Ok(())
+    }
+
+    fn delete_snapshot(&self, name: &str) -> Result<bool, AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbWriteError)?;
+        let removed = conn.execute("DELETE FROM snapshots WHERE name = ?1", params![name]).map_err(|_| AppError::DbWriteError)?;
+        Ok(removed > 0)
+    }
+
+    fn snapshots(&self) -> Result<Vec<(String, CanvasArchive)>, AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbReadError)?;
+        let mut statement = conn.prepare_cached("SELECT name, archive FROM snapshots ORDER BY name").map_err(|_| AppError::DbReadError)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|_| AppError::DbReadError)?;
+        let mut snapshots = Vec::new();
+        for row in rows {
+            let (name, archive) = row.map_err(|_| AppError::DbReadError)?;
+            // Skip anything we can't read rather than hiding every other snapshot
+            if let Ok(archive) = serde_json::from_str(&archive) {
+                snapshots.push((name, archive));
+            }
+        }
+        Ok(snapshots)
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<String>, AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbReadError)?;
+        conn.query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(|_| AppError::DbReadError)
+    }
+
+    fn put_setting(&self, key: &str, value: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbWriteError)?;
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2) ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )
+        .map_err(|_| AppError::DbWriteError)?;
+        Ok(())
+    }
+
+    fn delete_setting(&self, key: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock().map_err(|_| AppError::DbWriteError)?;
+        conn.execute("DELETE FROM settings WHERE key = ?1", params![key]).map_err(|_| AppError::DbWriteError)?;
+        Ok(())
+    }
+}
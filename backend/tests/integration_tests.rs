@@ -2,28 +2,28 @@
 
 // Integration tests: testing the actual HTTP endpoints
 
-use std::fs;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use axum::{
     body::{Body, to_bytes},
+    extract::ConnectInfo,
     http::{Request, StatusCode},
 };
 use tower::util::ServiceExt; // for .oneshot()
 use serde_json::json;
 use backend::server::routes::create_router;
 use backend::server::state::init_app_state;
+use backend::server::store::{CanvasStore, MemoryStore, PackedCanvas};
+use backend::server::error::AppError;
+use protocol::{CanvasArchive, DeviceStatus};
+use backend::server::cache::WritePolicy;
 
 // Test for GET /canvas endpoint
 // Verifies that the full canvas is returned correctly
 #[tokio::test]
 async fn test_canvas_endpoint_returns_full_canvas() {
-    // Sled creates a folder
-    let test_db_path = "test_db_canvas_endpoint";
-    
-    // Clean up beforehand
-    let _ = fs::remove_dir_all(test_db_path);
-
-    let app_state = init_app_state(test_db_path);
-    let app = create_router().with_state(app_state);
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
 
     let response = app
         .oneshot(
@@ -45,18 +45,14 @@ async fn test_canvas_endpoint_returns_full_canvas() {
     assert_eq!(json_body["width"], 32);
     assert_eq!(json_body["height"], 16);
 
-    let _ = fs::remove_dir_all(test_db_path);
 }
 
 // Test for POST /pixel endpoint
 // Updates a pixel and verifies the update via GET /canvas
 #[tokio::test]
 async fn test_post_pixel_updates_canvas() {
-    let test_db_path = "test_db_pixel_update";
-    let _ = fs::remove_dir_all(test_db_path);
-
-    let app_state = init_app_state(test_db_path);
-    let app = create_router().with_state(app_state);
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
 
     let payload = json!({
         "x": 0,
@@ -96,17 +92,13 @@ async fn test_post_pixel_updates_canvas() {
 
     assert_eq!(json_canvas["pixels"][0][0], "#FF0000");
 
-    let _ = fs::remove_dir_all(test_db_path);
 }
 
 // Test for POST /pixel with out-of-bounds coordinates
 #[tokio::test]
 async fn test_post_pixel_out_of_bounds() {
-    let test_db_path = "test_db_pixel_oob";
-    let _ = fs::remove_dir_all(test_db_path);
-
-    let app_state = init_app_state(test_db_path);
-    let app = create_router().with_state(app_state);
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
 
     let payload = json!({ "x": 999, "y": 999, "color": "#123456" });
 
@@ -123,17 +115,13 @@ async fn test_post_pixel_out_of_bounds() {
         .unwrap();
 
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
-    let _ = fs::remove_dir_all(test_db_path);
 }
 
 // Test for POST /reset endpoint
 #[tokio::test]
 async fn test_reset_endpoint() {
-    let test_db_path = "test_db_reset_endpoint";
-    let _ = fs::remove_dir_all(test_db_path);
-
-    let app_state = init_app_state(test_db_path);
-    let app = create_router().with_state(app_state);
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
 
     // Paint a pixel (Red)
     let pixel_payload = json!({
@@ -178,17 +166,13 @@ async fn test_reset_endpoint() {
     // Pixel [5][5] should be default color (usually #000000), NOT #FF0000
     assert_ne!(json_canvas["pixels"][5][5], "#FF0000"); 
 
-    let _ = fs::remove_dir_all(test_db_path);
 }
 
 // Test for GET /updates endpoint
 #[tokio::test]
 async fn test_updates_endpoint() {
-    let test_db_path = "test_db_updates";
-    let _ = fs::remove_dir_all(test_db_path);
-
-    let app_state = init_app_state(test_db_path);
-    let app = create_router().with_state(app_state);
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
 
     // Get time slightly before now (1 sec ago) (simulating a client that just synced 1 sec ago)
     let start_time = std::time::SystemTime::now()
@@ -226,6 +210,686 @@ async fn test_updates_endpoint() {
     assert_eq!(json_body["updates"].as_array().unwrap().len(), 1);
     assert_eq!(json_body["updates"][0]["color"], "#ABCDEF");
     assert_eq!(json_body["reset_required"], false);
+    // Newest timestamp is the update we just made
+    assert_eq!(json_body["latest_timestamp"], json_body["updates"][0]["timestamp"]);
+    assert_eq!(json_body["shutting_down"], false);
+
+}
+
+// Test for GET /updates once the server has been asked to stop
+// Pollers are told, and requests are still served while the notice lasts
+#[tokio::test]
+async fn test_updates_announce_shutdown() {
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.shutdown.trigger();
+    let app = create_router(app_state);
+
+    let payload = json!({ "x": 1, "y": 1, "color": "#ABCDEF" });
+    let response = app.clone().oneshot(
+        Request::builder()
+            .uri("/pixel")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(payload.to_string()))
+            .unwrap(),
+    ).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.oneshot(
+        Request::builder()
+            .uri("/updates?since=0")
+            .method("GET")
+            .body(Body::empty())
+            .unwrap(),
+    ).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+    let json_body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(json_body["shutting_down"], true);
+    assert_eq!(json_body["updates"].as_array().unwrap().len(), 1);
+}
+// Test for POST /reset when an admin token is configured
+#[tokio::test]
+async fn test_reset_endpoint_requires_admin_token() {
+    let mut app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.admin_token = Some("secret".to_string());
+    let app = create_router(app_state);
+
+    // No token -> rejected
+    let response = app.clone().oneshot(
+        Request::builder()
+            .uri("/reset")
+            .method("POST")
+            .body(Body::empty())
+            .unwrap(),
+    ).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Wrong token -> rejected
+    let response = app.clone().oneshot(
+        Request::builder()
+            .uri("/reset")
+            .method("POST")
+            .header("Authorization", "Bearer wrong")
+            .body(Body::empty())
+            .unwrap(),
+    ).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Correct token -> accepted
+    let response = app.oneshot(
+        Request::builder()
+            .uri("/reset")
+            .method("POST")
+            .header("Authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap(),
+    ).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+}
 
-    let _ = fs::remove_dir_all(test_db_path);
-}
\ No newline at end of file
+// Test for colours: GET /canvas and GET /updates describe a pixel the same way, whatever case it was sent in
+#[tokio::test]
+async fn test_pixel_color_is_normalised_everywhere() {
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
+
+    let payload = json!({ "x": 4, "y": 5, "color": "#abcdef" });
+    let response = app.clone().oneshot(
+        Request::builder()
+            .uri("/pixel")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(payload.to_string()))
+            .unwrap(),
+    ).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.clone().oneshot(Request::builder().uri("/canvas").body(Body::empty()).unwrap()).await.unwrap();
+    let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+    let canvas: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+
+    let response = app.oneshot(Request::builder().uri("/updates?since=0").body(Body::empty()).unwrap()).await.unwrap();
+    let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+    let updates: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+
+    assert_eq!(canvas["pixels"][5][4], "#ABCDEF");
+    assert_eq!(updates["updates"][0]["color"], canvas["pixels"][5][4]);
+}
+
+// Test for POST /reset reaching boards that follow /updates
+// Pollers must reload the blank board, and nothing from before the reset may be replayed
+#[tokio::test]
+async fn test_reset_makes_pollers_reload() {
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
+
+    let poll = |since: u64| Request::builder().uri(format!("/updates?since={}", since)).method("GET").body(Body::empty()).unwrap();
+    let read_json = |response: axum::response::Response| async move {
+        let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body_bytes).unwrap()
+    };
+
+    let payload = json!({ "x": 2, "y": 3, "color": "#FF0000" });
+    let response = app.clone().oneshot(
+        Request::builder()
+            .uri("/pixel")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(payload.to_string()))
+            .unwrap(),
+    ).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let before = read_json(app.clone().oneshot(poll(0)).await.unwrap()).await;
+    let seen = before["latest_timestamp"].as_u64().unwrap();
+
+    // The reset has to land on a later millisecond than the pixel
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let response = app.clone().oneshot(Request::builder().uri("/reset").method("POST").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // A board that was following along reloads
+    let after = read_json(app.clone().oneshot(poll(seen)).await.unwrap()).await;
+    assert_eq!(after["reset_required"], true);
+    assert!(after["latest_timestamp"].as_u64().unwrap() > seen);
+
+    // and one that starts from the latest timestamp gets nothing to replay
+    let latest = after["latest_timestamp"].as_u64().unwrap();
+    let fresh = read_json(app.oneshot(poll(latest)).await.unwrap()).await;
+    assert_eq!(fresh["reset_required"], false);
+    assert!(fresh["updates"].as_array().unwrap().is_empty());
+}
+
+// Test for the device routes: register, heartbeat, then GET /devices (an admin route)
+#[tokio::test]
+async fn test_device_registration_and_listing() {
+    let mut app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.admin_token = Some("secret".to_string());
+    let app = create_router(app_state);
+
+    let report = json!({
+        "device_id": "rustycanvas-a1b2c3d4e5f6",
+        "firmware_version": "0.1.0",
+        "uptime_secs": 12,
+        "rssi": -58,
+        "last_applied": 0
+    });
+    let post = |uri: &str, body: String| {
+        Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    // Not registered yet -> 404, which tells the board to register
+    let response = app.clone().oneshot(post("/devices/heartbeat", report.to_string())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.clone().oneshot(post("/devices/register", report.to_string())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut heartbeat = report.clone();
+    heartbeat["uptime_secs"] = json!(42);
+    heartbeat["last_applied"] = json!(1_700_000_000_000u64);
+    let response = app.clone().oneshot(post("/devices/heartbeat", heartbeat.to_string())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut bad = report.clone();
+    bad["device_id"] = json!("not an id");
+    let response = app.clone().oneshot(post("/devices/register", bad.to_string())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Listing the boards needs the admin token
+    let response = app.clone().oneshot(
+        Request::builder()
+            .uri("/devices")
+            .method("GET")
+            .body(Body::empty())
+            .unwrap(),
+    ).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.oneshot(
+        Request::builder()
+            .uri("/devices")
+            .method("GET")
+            .header("Authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap(),
+    ).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+    let json_body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+    let devices = json_body["devices"].as_array().unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0]["device_id"], "rustycanvas-a1b2c3d4e5f6");
+    assert_eq!(devices[0]["uptime_secs"], 42);
+    assert_eq!(devices[0]["last_applied"], 1_700_000_000_000u64);
+    assert_eq!(devices[0]["online"], true);
+
+}
+
+// Test for POST /devices/{device_id}/test-pattern: queued by an admin, handed to the board with its next heartbeat
+#[tokio::test]
+async fn test_test_pattern_reaches_board() {
+    let mut app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.admin_token = Some("secret".to_string());
+    let app = create_router(app_state);
+
+    let report = json!({
+        "device_id": "board-1",
+        "firmware_version": "0.1.0",
+        "uptime_secs": 12,
+        "rssi": null,
+        "last_applied": 0
+    });
+    let post = |uri: &str, token: Option<&str>, body: String| {
+        let mut request = Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("Content-Type", "application/json");
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.body(Body::from(body)).unwrap()
+    };
+    let pattern = json!({ "pattern": "color_bars" }).to_string();
+
+    // Unknown board
+    let response = app.clone().oneshot(post("/devices/board-1/test-pattern", Some("secret"), pattern.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.clone().oneshot(post("/devices/register", None, report.to_string())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Admin only, and only known patterns
+    let response = app.clone().oneshot(post("/devices/board-1/test-pattern", None, pattern.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let bad = json!({ "pattern": "rainbow" }).to_string();
+    let response = app.clone().oneshot(post("/devices/board-1/test-pattern", Some("secret"), bad)).await.unwrap();
+    assert!(response.status().is_client_error());
+
+    let response = app.clone().oneshot(post("/devices/board-1/test-pattern", Some("secret"), pattern)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The next heartbeat carries it, the one after doesn't
+    for expected in [json!("color_bars"), json!(null)] {
+        let response = app.clone().oneshot(post("/devices/heartbeat", None, report.to_string())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+        let json_body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(json_body["test_pattern"], expected);
+    }
+
+}
+
+// Test for the backup and snapshot routes (all admin routes)
+// Export a board, wipe it, then bring it back from the archive and from a named snapshot
+#[tokio::test]
+async fn test_backup_snapshot_and_restore_endpoints() {
+    let mut app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.admin_token = Some("secret".to_string());
+    let app = create_router(app_state);
+
+    let request = |method: &str, uri: &str, token: Option<&str>, body: Option<String>| {
+        let mut request = Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Content-Type", "application/json");
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.body(body.map(Body::from).unwrap_or_else(Body::empty)).unwrap()
+    };
+    let read_json = |response: axum::response::Response| async move {
+        let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body_bytes).unwrap()
+    };
+    let pixel_at = |app: axum::Router, x: usize, y: usize| async move {
+        let response = app.oneshot(request("GET", "/canvas", None, None)).await.unwrap();
+        let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+        let json_body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        json_body["pixels"][y][x].clone()
+    };
+
+    let pixel = json!({ "x": 4, "y": 5, "color": "#ABCDEF" }).to_string();
+    let response = app.clone().oneshot(request("POST", "/pixel", None, Some(pixel))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Admin only
+    for (method, uri) in [("GET", "/backup"), ("GET", "/snapshots"), ("POST", "/snapshots/any/restore"), ("DELETE", "/snapshots/any")] {
+        let response = app.clone().oneshot(request(method, uri, None, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+    }
+
+    // Export the board, and save it as a snapshot
+    let response = app.clone().oneshot(request("GET", "/backup", Some("secret"), None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let archive = read_json(response).await;
+    assert_eq!(archive["format"], "rustycanvas-archive");
+    assert_eq!(archive["pixels"][5][4], "#ABCDEF");
+    assert_eq!(archive["history"][0]["color"], "#ABCDEF");
+
+    let name = json!({ "name": "before-reset" }).to_string();
+    let response = app.clone().oneshot(request("POST", "/snapshots", Some("secret"), Some(name.clone()))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(request("POST", "/snapshots", Some("secret"), Some(name))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app.clone().oneshot(request("GET", "/snapshots", Some("secret"), None)).await.unwrap();
+    let listed = read_json(response).await;
+    assert_eq!(listed["snapshots"][0]["name"], "before-reset");
+    assert_eq!(listed["snapshots"][0]["history_len"], 1);
+
+    // Wipe, restore the exported file
+    let response = app.clone().oneshot(request("POST", "/reset", Some("secret"), None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(pixel_at(app.clone(), 4, 5).await, "#000000");
+
+    let response = app.clone().oneshot(request("POST", "/restore", Some("secret"), Some(archive.to_string()))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(pixel_at(app.clone(), 4, 5).await, "#ABCDEF");
+
+    // Pollers are told to reload
+    let response = app.clone().oneshot(request("GET", "/updates?since=1", None, None)).await.unwrap();
+    let updates = read_json(response).await;
+    assert_eq!(updates["reset_required"], true);
+
+    // Archives from a newer server are refused
+    let mut newer = archive.clone();
+    newer["version"] = json!(99);
+    let response = app.clone().oneshot(request("POST", "/restore", Some("secret"), Some(newer.to_string()))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(read_json(response).await["error"], "unsupported_archive");
+
+    // Wipe again, restore the named snapshot
+    let response = app.clone().oneshot(request("POST", "/reset", Some("secret"), None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(request("POST", "/snapshots/before-reset/restore", Some("secret"), None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(read_json(response).await["snapshot"]["name"], "before-reset");
+    assert_eq!(pixel_at(app.clone(), 4, 5).await, "#ABCDEF");
+
+    let response = app.clone().oneshot(request("POST", "/snapshots/missing/restore", Some("secret"), None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.clone().oneshot(request("DELETE", "/snapshots/before-reset", Some("secret"), None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.oneshot(request("DELETE", "/snapshots/before-reset", Some("secret"), None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+// Test for the event routes: a scheduled event keeps the board read-only until it opens
+#[tokio::test]
+async fn test_scheduled_event_blocks_pixels_until_open() {
+    let mut app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.admin_token = Some("secret".to_string());
+    let app = create_router(app_state);
+
+    let request = |method: &str, uri: &str, token: Option<&str>, body: Option<String>| {
+        let mut request = Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Content-Type", "application/json");
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.body(body.map(Body::from).unwrap_or_else(Body::empty)).unwrap()
+    };
+    let read_json = |response: axum::response::Response| async move {
+        let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body_bytes).unwrap()
+    };
+    let pixel = json!({ "x": 1, "y": 1, "color": "#FF0000" }).to_string();
+
+    let response = app.clone().oneshot(request("GET", "/event", None, None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(read_json(response).await["phase"], "idle");
+
+    // Opens in an hour
+    let opens_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64 + 3_600_000;
+    let event = json!({ "opens_at": opens_at, "open_minutes": 5 }).to_string();
+    let response = app.clone().oneshot(request("POST", "/event", None, Some(event.clone()))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app.clone().oneshot(request("POST", "/event", Some("secret"), Some(event))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = read_json(response).await;
+    assert_eq!(body["event"]["phase"], "scheduled");
+    assert_eq!(body["event"]["schedule"]["closes_at"], opens_at + 300_000);
+
+    let bad = json!({ "open_minutes": 0 }).to_string();
+    let response = app.clone().oneshot(request("POST", "/event", Some("secret"), Some(bad))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app.clone().oneshot(request("POST", "/pixel", None, Some(pixel.clone()))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(read_json(response).await["error"], "canvas_closed");
+
+    let response = app.clone().oneshot(request("GET", "/event", None, None)).await.unwrap();
+    assert_eq!(read_json(response).await["phase"], "scheduled");
+
+    // Cancelled: open as usual
+    let response = app.clone().oneshot(request("DELETE", "/event", Some("secret"), None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.oneshot(request("POST", "/pixel", None, Some(pixel))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+// Test for the mode routes: freezing the board refuses pixels, while reads and the updates feed keep working
+#[tokio::test]
+async fn test_board_mode_freezes_pixels() {
+    let mut app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.admin_token = Some("secret".to_string());
+    let app = create_router(app_state);
+
+    let request = |method: &str, uri: &str, token: Option<&str>, body: Option<String>| {
+        let mut request = Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Content-Type", "application/json");
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.body(body.map(Body::from).unwrap_or_else(Body::empty)).unwrap()
+    };
+    let read_json = |response: axum::response::Response| async move {
+        let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body_bytes).unwrap()
+    };
+    let pixel = json!({ "x": 1, "y": 1, "color": "#FF0000" }).to_string();
+
+    let response = app.clone().oneshot(request("GET", "/mode", None, None)).await.unwrap();
+    assert_eq!(read_json(response).await["mode"], "open");
+    let response = app.clone().oneshot(request("GET", "/updates?since=0", None, None)).await.unwrap();
+    assert_eq!(read_json(response).await["mode"], "open");
+
+    let read_only = json!({ "mode": "read_only" }).to_string();
+    let response = app.clone().oneshot(request("POST", "/mode", None, Some(read_only.clone()))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app.clone().oneshot(request("POST", "/mode", Some("secret"), Some(read_only))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(read_json(response).await["mode"]["mode"], "read_only");
+
+    let response = app.clone().oneshot(request("POST", "/pixel", None, Some(pixel.clone()))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(read_json(response).await["error"], "read_only");
+
+    // Reading still works, and pollers are told about the change
+    let response = app.clone().oneshot(request("GET", "/canvas", None, None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(request("GET", "/updates?since=0", None, None)).await.unwrap();
+    assert_eq!(read_json(response).await["mode"], "read_only");
+
+    let maintenance = json!({ "mode": "maintenance" }).to_string();
+    app.clone().oneshot(request("POST", "/mode", Some("secret"), Some(maintenance))).await.unwrap();
+    let response = app.clone().oneshot(request("POST", "/pixel", None, Some(pixel.clone()))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(read_json(response).await["error"], "maintenance");
+
+    let open = json!({ "mode": "open" }).to_string();
+    app.clone().oneshot(request("POST", "/mode", Some("secret"), Some(open))).await.unwrap();
+    let response = app.oneshot(request("POST", "/pixel", None, Some(pixel))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+// Test for the error body: every failure, including requests axum can't read, answers the same JSON shape
+#[tokio::test]
+async fn test_errors_share_one_json_body() {
+    let mut app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.admin_token = Some("secret".to_string());
+    let app = create_router(app_state);
+
+    let request = |method: &str, uri: &str, content_type: Option<&str>, body: &str| {
+        let mut request = Request::builder().uri(uri).method(method);
+        if let Some(content_type) = content_type {
+            request = request.header("Content-Type", content_type);
+        }
+        request.body(Body::from(body.to_string())).unwrap()
+    };
+    let json = Some("application/json");
+
+    let cases = [
+        // Extractor rejections
+        (request("GET", "/updates?since=yesterday", None, ""), StatusCode::BAD_REQUEST, "invalid_query"),
+        (request("GET", "/updates", None, ""), StatusCode::BAD_REQUEST, "invalid_query"),
+        (request("POST", "/pixel", json, "{ not json"), StatusCode::BAD_REQUEST, "invalid_body"),
+        (request("POST", "/pixel", json, r#"{ "x": 1 }"#), StatusCode::BAD_REQUEST, "invalid_body"),
+        (request("POST", "/pixel", None, r##"{ "x": 1, "y": 1, "color": "#FFFFFF" }"##), StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type"),
+        // Routing
+        (request("GET", "/nowhere", None, ""), StatusCode::NOT_FOUND, "unknown_route"),
+        (request("DELETE", "/canvas", None, ""), StatusCode::METHOD_NOT_ALLOWED, "method_not_allowed"),
+        // Handler errors, on routes that used to answer different shapes
+        (request("POST", "/pixel", json, r##"{ "x": 99, "y": 1, "color": "#FFFFFF" }"##), StatusCode::BAD_REQUEST, "out_of_bounds"),
+        (request("POST", "/reset", None, ""), StatusCode::UNAUTHORIZED, "unauthorized"),
+        (request("GET", "/devices", None, ""), StatusCode::UNAUTHORIZED, "unauthorized"),
+        (request("POST", "/devices/heartbeat", json, r#"{ "device_id": "board-a", "firmware_version": "0.1.0", "uptime_secs": 1, "last_applied": 0 }"#), StatusCode::NOT_FOUND, "unknown_device"),
+    ];
+
+    for (request, status, code) in cases {
+        let uri = request.uri().to_string();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), status, "{}", uri);
+        assert_eq!(response.headers()["content-type"], "application/json", "{}", uri);
+
+        let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(body["success"], false, "{}", uri);
+        assert_eq!(body["error"], code, "{}", uri);
+        assert!(body["message"].as_str().is_some_and(|message| !message.is_empty()), "{}", uri);
+    }
+}
+
+// Test for GET /metrics and the request ID every response carries
+#[tokio::test]
+async fn test_metrics_and_request_ids() {
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
+
+    let pixel = |x: u32| {
+        Request::builder()
+            .uri("/pixel")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(json!({ "x": x, "y": 1, "color": "#FF0000" }).to_string()))
+            .unwrap()
+    };
+    let response = app.clone().oneshot(pixel(1)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // A new ID when the client didn't send one
+    assert!(!response.headers()["x-request-id"].is_empty());
+    let response = app.clone().oneshot(pixel(99)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // The client's own ID is kept. Its address counts as an active client (the server adds it from the connection)
+    let mut poll = Request::builder()
+        .uri("/updates?since=0")
+        .header("X-Request-Id", "poll-1")
+        .body(Body::empty())
+        .unwrap();
+    poll.extensions_mut().insert(ConnectInfo(SocketAddr::from(([192, 168, 1, 20], 50000))));
+    let response = app.clone().oneshot(poll).await.unwrap();
+    assert_eq!(response.headers()["x-request-id"], "poll-1");
+
+    let response = app.clone().oneshot(Request::builder().uri("/nowhere").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+    let body = String::from_utf8(body_bytes.to_vec()).unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+
+    for expected in [
+        "rustycanvas_pixels_placed_total 1",
+        "rustycanvas_rejections_total{reason=\"out_of_bounds\"} 1",
+        "rustycanvas_rejections_total{reason=\"unknown_route\"} 1",
+        "rustycanvas_active_clients 1",
+        "rustycanvas_history_size 1",
+        "rustycanvas_db_seconds_count{op=\"write_rows\"} 1",
+    ] {
+        assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, body);
+    }
+}
+
+// Tests for GET /healthz, GET /readyz and GET /version
+#[tokio::test]
+async fn test_health_readiness_and_version() {
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let app = create_router(app_state.clone());
+
+    let get = |uri: &str| Request::builder().uri(uri).method("GET").body(Body::empty()).unwrap();
+    let read_json = |response: axum::response::Response| async move {
+        let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body_bytes).unwrap()
+    };
+
+    let response = app.clone().oneshot(get("/healthz")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let health = read_json(response).await;
+    assert_eq!(health["status"], "ok");
+    assert!(health["uptime_secs"].is_u64());
+
+    let response = app.clone().oneshot(get("/readyz")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let readiness = read_json(response).await;
+    assert_eq!(readiness["ready"], true);
+    let names: Vec<&str> = readiness["checks"].as_array().unwrap().iter().map(|check| check["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["store_read", "store_write", "history", "accepting"]);
+
+    let response = app.clone().oneshot(get("/version")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let version = read_json(response).await;
+    assert_eq!(version["name"], "backend");
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(version["canvas"]["width"], 32);
+    assert_eq!(version["canvas"]["height"], 16);
+    assert_eq!(version["canvas"]["default_color"], "#000000");
+    assert_eq!(version["canvas"]["flush_ms"], 0);
+    assert_eq!(version["canvas"]["history_len"], 50);
+
+    // Once asked to stop it's still alive, but no longer ready
+    app_state.shutdown.trigger();
+    let response = app.clone().oneshot(get("/healthz")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.oneshot(get("/readyz")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let readiness = read_json(response).await;
+    assert_eq!(readiness["ready"], false);
+    let accepting = readiness["checks"].as_array().unwrap().iter().find(|check| check["name"] == "accepting").unwrap();
+    assert_eq!(accepting["ok"], false);
+}
+
+// A store whose writes fail, like a full or read-only disk
+struct FullDiskStore(MemoryStore);
+
+impl CanvasStore for FullDiskStore {
+    fn load_canvas(&self) -> Result<PackedCanvas, AppError> { self.0.load_canvas() }
+    fn write_rows(&self, _rows: &[(u32, Vec<u8>)]) -> Result<(), AppError> { Err(AppError::DbWriteError) }
+    fn clear_pixels(&self) -> Result<(), AppError> { Err(AppError::DbClearError) }
+    fn get_device(&self, device_id: &str) -> Result<Option<DeviceStatus>, AppError> { self.0.get_device(device_id) }
+    fn put_device(&self, _status: &DeviceStatus) -> Result<(), AppError> { Err(AppError::DbWriteError) }
+    fn devices(&self) -> Result<Vec<DeviceStatus>, AppError> { self.0.devices() }
+    fn get_snapshot(&self, name: &str) -> Result<Option<CanvasArchive>, AppError> { self.0.get_snapshot(name) }
+    fn put_snapshot(&self, _name: &str, _archive: &CanvasArchive) -> Result<(), AppError> { Err(AppError::DbWriteError) }
+    fn delete_snapshot(&self, _name: &str) -> Result<bool, AppError> { Err(AppError::DbWriteError) }
+    fn snapshots(&self) -> Result<Vec<(String, CanvasArchive)>, AppError> { self.0.snapshots() }
+    fn get_setting(&self, key: &str) -> Result<Option<String>, AppError> { self.0.get_setting(key) }
+    fn put_setting(&self, _key: &str, _value: &str) -> Result<(), AppError> { Err(AppError::DbWriteError) }
+    fn delete_setting(&self, _key: &str) -> Result<(), AppError> { Err(AppError::DbWriteError) }
+}
+
+// Test for GET /readyz when the store can't be written to
+#[tokio::test]
+async fn test_readiness_reports_failing_store() {
+    let app_state = init_app_state(Arc::new(FullDiskStore(MemoryStore::new())), WritePolicy::WriteThrough);
+    let app = create_router(app_state);
+
+    let response = app.clone().oneshot(Request::builder().uri("/readyz").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body_bytes = to_bytes(response.into_body(), 1_048_576).await.unwrap();
+    let readiness: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(readiness["ready"], false);
+    for check in readiness["checks"].as_array().unwrap() {
+        let failed = check["name"] == "store_write";
+        assert_eq!(check["ok"], !failed, "{}", check);
+        if failed {
+            assert_eq!(check["detail"], "could not write to the store");
+        }
+    }
+
+    // Liveness doesn't depend on the store
+    let response = app.oneshot(Request::builder().uri("/healthz").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -3,14 +3,43 @@
 // Unit tests: directly testing handler function logic (not the HTTP endpoints)
 
 use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use backend::server::handlers::{
     make_canvas_response,
     PixelUpdateInput,
     apply_pixel_update,
     reset_canvas_db,
+    reset_board,
+    latest_update_timestamp,
     log_pixel_update,
-    fetch_updates_since
+    fetch_updates_since,
+    record_device_report,
+    list_devices,
+    queue_test_pattern,
+    make_archive,
+    archive_to_canvas,
+    save_snapshot,
+    list_snapshots,
+    restore_snapshot,
+    delete_snapshot,
+    now_millis,
+    DeviceReport,
 };
+use protocol::TestPattern;
+use backend::server::cache::{CanvasCache, WritePolicy};
+use backend::server::discovery;
+use backend::server::error::AppError;
+use backend::server::metrics::{Metrics, ACTIVE_CLIENT_WINDOW};
+use backend::server::shutdown::ShutdownConfig;
+use backend::server::schedule::{cancel_event, check_canvas_open, event_phase, event_status, run_close_actions, schedule_event};
+use protocol::{EventPhase, EventSchedule, EventScheduleInput};
+use backend::server::mode::{check_mode_allows_pixels, current_mode, load_mode, set_mode};
+use backend::server::health::{check_readiness, WRITE_CHECK_INTERVAL_MS};
+use protocol::BoardMode;
+use backend::server::store::{CanvasStore, MemoryStore, PackedCanvas, SledStore, SqliteStore, StoreConfig};
+use protocol::{CanvasArchive, DeviceStatus, Rgb};
 use backend::server::state::{
     init_app_state,
     CANVAS_WIDTH,
@@ -19,19 +48,27 @@ use backend::server::state::{
     PixelUpdate
 };
 
-// Test helper to create a db
-fn setup_test_db(path: &str) -> sled::Db {
-    let _ = fs::remove_dir_all(path);
-    sled::open(path).expect("Failed to open test db")
+// Test helper to create a store (in memory, so there's nothing to clean up)
+fn setup_test_store() -> MemoryStore {
+    MemoryStore::new()
+}
+
+// Test helper to put a write-through canvas cache in front of 'store'
+fn cache_over(store: Arc<dyn CanvasStore>) -> CanvasCache {
+    CanvasCache::load(store, WritePolicy::WriteThrough).unwrap()
+}
+
+// Test helper to create a canvas cache over a fresh in-memory store
+fn setup_test_cache() -> CanvasCache {
+    cache_over(Arc::new(setup_test_store()))
 }
 
 // Tests for GET /canvas endpoint dependencies
 #[test]
 fn test_default_canvas_values() {
-    let path = "unit_test_default_canvas";
-    let db = setup_test_db(path);
+    let canvas = setup_test_cache();
 
-    let response = make_canvas_response(&db);
+    let response = make_canvas_response(&canvas);
 
     assert_eq!(response.width, CANVAS_WIDTH);
     assert_eq!(response.height, CANVAS_HEIGHT);
@@ -41,38 +78,12 @@ fn test_default_canvas_values() {
     // Check that default is black
     assert_eq!(response.pixels[0][0], DEFAULT_COLOR);
 
-    let _ = fs::remove_dir_all(path);
-}
-
-// Tests for GET /canvas endpoint dependencies
-#[test]
-fn test_persistence_across_restarts() {
-    let path = "unit_test_persistence";
-    let _ = fs::remove_dir_all(path);
-
-    // Open DB, Write Data, Drop DB
-    {
-        let db = sled::open(path).unwrap();
-        let input = PixelUpdateInput { x: 5, y: 5, color: "#ABCDEF".to_string() };
-        apply_pixel_update(&db, &input).unwrap();
-        // db is dropped here (simulating server shutdown)
-    }
-
-    // Reopen DB (simulating server restart)
-    let db_reopened = sled::open(path).unwrap();
-    
-    // Verify data is still there
-    let response = make_canvas_response(&db_reopened);
-    assert_eq!(response.pixels[5][5], "#ABCDEF");
-
-    let _ = fs::remove_dir_all(path);
 }
 
 // Tests for POST /pixel endpoint dependencies
 #[test]
 fn test_apply_pixel_update_valid() {
-    let path = "unit_test_apply_valid";
-    let db = setup_test_db(path);
+    let canvas = setup_test_cache();
 
     let input = PixelUpdateInput {
         x: 1,
@@ -80,22 +91,20 @@ fn test_apply_pixel_update_valid() {
         color: "#FF00FF".to_string(),
     };
 
-    let result = apply_pixel_update(&db, &input);
+    let result = apply_pixel_update(&canvas, &input);
 
     assert!(result.is_ok());
 
     // Verify via response generator
-    let response = make_canvas_response(&db);
+    let response = make_canvas_response(&canvas);
     assert_eq!(response.pixels[2][1], "#FF00FF");
 
-    let _ = fs::remove_dir_all(path);
 }
 
 // Tests for POST /pixel endpoint dependencies
 #[test]
 fn test_apply_pixel_update_out_of_bounds() {
-    let path = "unit_test_apply_oob";
-    let db = setup_test_db(path);
+    let canvas = setup_test_cache();
 
     let input = PixelUpdateInput {
         x: 100, // invalid
@@ -103,17 +112,65 @@ fn test_apply_pixel_update_out_of_bounds() {
         color: "#FFFFFF".to_string(),
     };
 
-    let result = apply_pixel_update(&db, &input);
+    let result = apply_pixel_update(&canvas, &input);
 
     assert!(result.is_err());
-    let _ = fs::remove_dir_all(path);
+
+    // Only "#RRGGBB" colours can be stored
+    let input = PixelUpdateInput { x: 0, y: 0, color: "red".to_string() };
+    assert_eq!(apply_pixel_update(&canvas, &input), Err(AppError::InvalidColor));
+}
+
+// A memory store whose canvas writes fail while 'failing' is set, like a full disk
+#[derive(Default)]
+struct FlakyStore {
+    inner: MemoryStore,
+    failing: AtomicBool,
+}
+
+impl CanvasStore for FlakyStore {
+    fn load_canvas(&self) -> Result<PackedCanvas, AppError> { self.inner.load_canvas() }
+    fn write_rows(&self, rows: &[(u32, Vec<u8>)]) -> Result<(), AppError> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(AppError::DbWriteError);
+        }
+        self.inner.write_rows(rows)
+    }
+    fn clear_pixels(&self) -> Result<(), AppError> { self.inner.clear_pixels() }
+    fn get_device(&self, device_id: &str) -> Result<Option<DeviceStatus>, AppError> { self.inner.get_device(device_id) }
+    fn put_device(&self, status: &DeviceStatus) -> Result<(), AppError> { self.inner.put_device(status) }
+    fn devices(&self) -> Result<Vec<DeviceStatus>, AppError> { self.inner.devices() }
+    fn get_snapshot(&self, name: &str) -> Result<Option<CanvasArchive>, AppError> { self.inner.get_snapshot(name) }
+    fn put_snapshot(&self, name: &str, archive: &CanvasArchive) -> Result<(), AppError> { self.inner.put_snapshot(name, archive) }
+    fn delete_snapshot(&self, name: &str) -> Result<bool, AppError> { self.inner.delete_snapshot(name) }
+    fn snapshots(&self) -> Result<Vec<(String, CanvasArchive)>, AppError> { self.inner.snapshots() }
+    fn get_setting(&self, key: &str) -> Result<Option<String>, AppError> { self.inner.get_setting(key) }
+    fn put_setting(&self, key: &str, value: &str) -> Result<(), AppError> { self.inner.put_setting(key, value) }
+    fn delete_setting(&self, key: &str) -> Result<(), AppError> { self.inner.delete_setting(key) }
+}
+
+// A write-through pixel the store refused must not show up on the board
+#[test]
+fn test_failed_write_through_leaves_canvas_unchanged() {
+    let store = Arc::new(FlakyStore::default());
+    let canvas = cache_over(store.clone());
+    let input = PixelUpdateInput { x: 3, y: 4, color: "#FF0000".to_string() };
+
+    store.failing.store(true, Ordering::SeqCst);
+    assert_eq!(apply_pixel_update(&canvas, &input), Err(AppError::DbWriteError));
+    assert_eq!(make_canvas_response(&canvas).pixels[4][3], DEFAULT_COLOR);
+    assert_eq!(canvas.pending_rows(), 0);
+
+    store.failing.store(false, Ordering::SeqCst);
+    apply_pixel_update(&canvas, &input).unwrap();
+    assert_eq!(make_canvas_response(&canvas).pixels[4][3], "#FF0000");
+    assert_eq!(store.load_canvas().unwrap().get(3, 4), Some(Rgb::new(255, 0, 0)));
 }
 
 // Tests for POST /reset endpoint dependencies
 #[test]
 fn test_reset_canvas_logic() {
-    let path = "unit_test_reset_logic";
-    let db = setup_test_db(path);
+    let canvas = setup_test_cache();
 
     // Paint a pixel manually
     let input = PixelUpdateInput {
@@ -121,51 +178,64 @@ fn test_reset_canvas_logic() {
         y: 10,
         color: "#FFFFFF".to_string(),
     };
-    apply_pixel_update(&db, &input).unwrap();
+    apply_pixel_update(&canvas, &input).unwrap();
 
     // Verify it's painted
-    let response_before = make_canvas_response(&db);
+    let response_before = make_canvas_response(&canvas);
     assert_eq!(response_before.pixels[10][10], "#FFFFFF");
 
     // Call Reset
-    let result = reset_canvas_db(&db);
+    let result = reset_canvas_db(&canvas);
     assert!(result.is_ok());
 
     // Verify it's back to default (Black)
-    let response_after = make_canvas_response(&db);
+    let response_after = make_canvas_response(&canvas);
     assert_eq!(response_after.pixels[10][10], DEFAULT_COLOR);
 
-    let _ = fs::remove_dir_all(path);
 }
 
 // Tests for GET /updates endpoint dependencies
 #[test]
 fn test_log_pixel_update_adds_to_history() {
-    let path = "unit_test_log_update";
-    let _ = fs::remove_dir_all(path);
-    let app_state = init_app_state(path);
+    let app_state = init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough);
 
-    log_pixel_update(&app_state, 10, 10, "#FFFFFF".to_string());
+    log_pixel_update(&app_state, 10, 10, "#FFFFFF".to_string(), now_millis());
 
     let history = app_state.history.read().unwrap();
     assert_eq!(history.len(), 1);
     assert_eq!(history[0].color, "#FFFFFF");
 
-    let _ = fs::remove_dir_all(path);
+}
+
+// A pixel placed in the same millisecond as a reset must still reach clients that reloaded after the reset
+#[test]
+fn test_pixel_in_the_reset_millisecond_is_not_lost() {
+    let app_state = init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough);
+    let now = 1_700_000_000_000;
+    log_pixel_update(&app_state, 1, 1, "#FF0000".to_string(), now - 10);
+
+    reset_board(&app_state, now).unwrap();
+    log_pixel_update(&app_state, 2, 2, "#00FF00".to_string(), now);
+
+    // A client that was following along reloads, then continues from the latest timestamp it was given
+    assert_eq!(fetch_updates_since(&app_state, now - 10), (vec![], true));
+    let (updates, reset_required) = fetch_updates_since(&app_state, now);
+    assert!(!reset_required);
+    assert_eq!(updates.len(), 1);
+    assert_eq!((updates[0].x, updates[0].y, updates[0].timestamp), (2, 2, now + 1));
+    assert_eq!(latest_update_timestamp(&app_state), now + 1);
 }
 
 // Tests for GET /updates endpoint dependencies
 #[test]
 fn test_history_pruning_limit() {
-    let path = "unit_test_pruning";
-    let _ = fs::remove_dir_all(path);
-    let app_state = init_app_state(path);
+    let app_state = init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough);
 
     let color = "#000000".to_string();
 
     // Fill exactly to limit
     for _ in 0..50 {
-        log_pixel_update(&app_state, 0, 0, color.clone());
+        log_pixel_update(&app_state, 0, 0, color.clone(), now_millis());
     }
 
     // Read lock to verify length
@@ -175,7 +245,7 @@ fn test_history_pruning_limit() {
     }
 
     // Add one more to trigger prune
-    log_pixel_update(&app_state, 99, 99, "#UNIQUE".to_string());
+    log_pixel_update(&app_state, 99, 99, "#UNIQUE".to_string(), now_millis());
 
     let history = app_state.history.read().unwrap();
     
@@ -185,15 +255,12 @@ fn test_history_pruning_limit() {
     // The LAST item should be our new color
     assert_eq!(history.back().unwrap().color, "#UNIQUE");
 
-    let _ = fs::remove_dir_all(path);
 }
 
 // Tests for GET /updates endpoint dependencies
 #[test]
 fn test_reset_required_logic() {
-    let path = "unit_test_reset_req_logic";
-    let _ = fs::remove_dir_all(path);
-    let app_state = init_app_state(path);
+    let app_state = init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough);
 
     // Scenario 1: Buffer is NOT full. Client asks for very old time.
     // Should return updates, NO reset.
@@ -203,14 +270,14 @@ fn test_reset_required_logic() {
     }
     
     let (_, reset) = fetch_updates_since(&app_state, 1000);
-    assert_eq!(reset, false, "Should not reset if buffer is not full");
+    assert!(!reset, "Should not reset if buffer is not full");
 
     // Scenario 2: Buffer IS full. Client asks for time older than oldest record.
     // Should trigger RESET.
     {
         let mut history = app_state.history.write().unwrap();
         history.clear();
-        // Simulate full buffer [3000, 3001, ... 4999]
+        // Simulate full buffer [3000, 3001, ... 3049]
         for i in 0..50 {
             history.push_back(PixelUpdate { 
                 x:0, y:0, color:"#A".to_string(), 
@@ -228,12 +295,558 @@ fn test_reset_required_logic() {
 
     let (_, reset) = fetch_updates_since(&app_state, 1000); // Client asks for T=1000
     // Oldest record is 3000. Buffer is full (50). Client (1000) is older than 3000.
-    assert_eq!(reset, true, "Should reset if buffer is full and client is old");
+    assert!(reset, "Should reset if buffer is full and client is old");
+
+    // Scenario 3: Buffer IS full. Client asks for recent time (inside the buffered range).
+    let (updates, reset) = fetch_updates_since(&app_state, 3040);
+    assert!(!reset, "Should not reset if client is recent");
+    assert!(!updates.is_empty());
+
+}
+// Tests for the mDNS advertisement boards use to find the server
+#[test]
+fn test_mdns_service_info() {
+    let info = discovery::service_info("Rohan's Canvas", 8080).unwrap();
+    assert_eq!(info.get_type(), "_rustycanvas._tcp.local.");
+    assert_eq!(info.get_fullname(), "Rohan's Canvas._rustycanvas._tcp.local.");
+    assert_eq!(info.get_port(), 8080);
+    // Host names can only have letters, digits and dashes
+    assert_eq!(info.get_hostname(), "rohan-s-canvas.local.");
+    assert_eq!(info.get_property_val_str("version"), Some(env!("CARGO_PKG_VERSION")));
+
+    let info = discovery::service_info("!!!", 80).unwrap();
+    assert_eq!(info.get_hostname(), "rustycanvas.local.");
+}
 
-    // Scenario 3: Buffer IS full. Client asks for recent time.
-    let (updates, reset) = fetch_updates_since(&app_state, 4990);
-    assert_eq!(reset, false, "Should not reset if client is recent");
-    assert!(updates.len() > 0);
+// Test helper to build a board's report
+fn device_report(id: &str, uptime_secs: u64) -> DeviceReport {
+    DeviceReport {
+        device_id: id.to_string(),
+        firmware_version: "0.1.0".to_string(),
+        uptime_secs,
+        rssi: Some(-60),
+        last_applied: 42,
+    }
+}
+
+// Tests for POST /devices/register, POST /devices/heartbeat and GET /devices dependencies
+#[test]
+fn test_device_register_and_heartbeat() {
+    let store: Arc<dyn CanvasStore> = Arc::new(setup_test_store());
+
+    // A heartbeat from a board that never registered is refused
+    assert_eq!(record_device_report(store.as_ref(), &device_report("board-b", 5), false, 1_000), Err(AppError::UnknownDevice));
+
+    record_device_report(store.as_ref(), &device_report("board-b", 5), true, 1_000).unwrap();
+    record_device_report(store.as_ref(), &device_report("board-a", 5), true, 1_000).unwrap();
+    record_device_report(store.as_ref(), &device_report("board-b", 35), false, 31_000).unwrap();
+
+    // Listed in ID order, the heartbeat keeps the registration time
+    let devices = list_devices(store.as_ref(), 31_000).unwrap().devices;
+    assert_eq!(devices.len(), 2);
+    assert_eq!(devices[0].device_id, "board-a");
+    assert_eq!(devices[1].device_id, "board-b");
+    assert_eq!(devices[1].uptime_secs, 35);
+    assert_eq!(devices[1].registered_at, 1_000);
+    assert_eq!(devices[1].last_seen, 31_000);
+    assert!(devices.iter().all(|device| device.online));
+
+    // board-a has been quiet too long
+    let devices = list_devices(store.as_ref(), 120_000).unwrap().devices;
+    assert!(!devices[0].online);
+    assert!(devices[1].online);
+
+    // Resetting the canvas doesn't forget the boards
+    reset_canvas_db(&cache_over(store.clone())).unwrap();
+    assert_eq!(list_devices(store.as_ref(), 120_000).unwrap().devices.len(), 2);
+
+}
+
+#[test]
+fn test_device_report_validation() {
+    let store = setup_test_store();
+
+    for bad in ["", "has space", "slash/id", &"x".repeat(65)] {
+        assert_eq!(record_device_report(&store, &device_report(bad, 1), true, 0), Err(AppError::InvalidDeviceId), "{:?}", bad);
+    }
+    let mut report = device_report("board-a", 1);
+    report.firmware_version = "v".repeat(33);
+    assert_eq!(record_device_report(&store, &report, true, 0), Err(AppError::InvalidFirmwareVersion));
+    assert!(list_devices(&store, 0).unwrap().devices.is_empty());
+
+}
+
+#[test]
+fn test_queued_test_pattern_is_handed_over_once() {
+    let store = setup_test_store();
+
+    assert_eq!(queue_test_pattern(&store, "board-a", TestPattern::ColorBars), Err(AppError::UnknownDevice));
+
+    record_device_report(&store, &device_report("board-a", 1), true, 0).unwrap();
+    queue_test_pattern(&store, "board-a", TestPattern::ColorBars).unwrap();
+    assert_eq!(list_devices(&store, 0).unwrap().devices[0].pending_test_pattern, Some(TestPattern::ColorBars));
+
+    assert_eq!(record_device_report(&store, &device_report("board-a", 31), false, 30_000), Ok(Some(TestPattern::ColorBars)));
+    assert_eq!(record_device_report(&store, &device_report("board-a", 61), false, 60_000), Ok(None));
+    assert_eq!(list_devices(&store, 60_000).unwrap().devices[0].pending_test_pattern, None);
+
+    // A board that rebooted gets it when it registers
+    queue_test_pattern(&store, "board-a", TestPattern::PixelWalk).unwrap();
+    assert_eq!(record_device_report(&store, &device_report("board-a", 1), true, 90_000), Ok(Some(TestPattern::PixelWalk)));
+
+}
+
+// Test helper to reopen a store that was just dropped
+// sled's background threads can hold the database lock for a moment after the last handle goes away
+fn reopen(config: &StoreConfig) -> Arc<dyn CanvasStore> {
+    for _ in 0..50 {
+        if let Ok(store) = config.open() {
+            return store;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    config.open().unwrap()
+}
+
+// Tests for the stores behind every route: sled and SQLite keep everything across a restart
+#[test]
+fn test_persistence_across_restarts() {
+    let sled_path = "unit_test_persistence_sled";
+    let sqlite_path = "unit_test_persistence.sqlite3";
+    let _ = fs::remove_dir_all(sled_path);
+    let _ = fs::remove_file(sqlite_path);
+
+    for config in [StoreConfig::Sled(sled_path.to_string()), StoreConfig::Sqlite(sqlite_path.to_string())] {
+        // Open store, Write Data, Drop store (simulating server shutdown)
+        {
+            let store = config.open().unwrap();
+            let input = PixelUpdateInput { x: 5, y: 5, color: "#ABCDEF".to_string() };
+            apply_pixel_update(&cache_over(store.clone()), &input).unwrap();
+            record_device_report(store.as_ref(), &device_report("board-a", 1), true, 1_000).unwrap();
+        }
+
+        // Reopen (simulating server restart) and verify data is still there
+        let store = reopen(&config);
+        let response = make_canvas_response(&cache_over(store.clone()));
+        assert_eq!(response.pixels[5][5], "#ABCDEF", "{:?}", config);
+        assert_eq!(list_devices(store.as_ref(), 1_000).unwrap().devices.len(), 1, "{:?}", config);
+    }
+
+    let _ = fs::remove_dir_all(sled_path);
+    let _ = fs::remove_file(sqlite_path);
+}
 
-    let _ = fs::remove_dir_all(path);
-}
\ No newline at end of file
+// Every store must behave the same way
+#[test]
+fn test_stores_behave_alike() {
+    let sled_path = "unit_test_store_sled";
+    let sqlite_path = "unit_test_store.sqlite3";
+    let _ = fs::remove_dir_all(sled_path);
+    let _ = fs::remove_file(sqlite_path);
+
+    let stores: [(&str, Box<dyn CanvasStore>); 3] = [
+        ("memory", Box::new(MemoryStore::new())),
+        ("sled", Box::new(SledStore::open(sled_path).unwrap())),
+        ("sqlite", Box::new(SqliteStore::open(sqlite_path).unwrap())),
+    ];
+    for (name, store) in stores {
+        let store = store.as_ref();
+        assert_eq!(store.load_canvas().unwrap(), PackedCanvas::default(), "{}", name);
+
+        let mut canvas = PackedCanvas::default();
+        canvas.set(3, 1, Rgb::new(255, 0, 0));
+        canvas.set(0, 2, Rgb::new(0, 255, 0));
+        let row = |canvas: &PackedCanvas, y: u32| (y, canvas.row(y).unwrap().to_vec());
+        store.write_rows(&[row(&canvas, 1), row(&canvas, 2)]).unwrap();
+        // Overwrite
+        canvas.set(3, 1, Rgb::new(0, 0, 255));
+        store.write_rows(&[row(&canvas, 1)]).unwrap();
+
+        let stored = store.load_canvas().unwrap();
+        assert_eq!(stored, canvas, "{}", name);
+        assert_eq!(stored.get(3, 1), Some(Rgb::new(0, 0, 255)), "{}", name);
+        assert_eq!(stored.get(1, 1), Some(Rgb::BLACK), "{}", name);
+
+        record_device_report(store, &device_report("board-b", 1), true, 0).unwrap();
+        record_device_report(store, &device_report("board-a", 1), true, 0).unwrap();
+        assert_eq!(store.get_device("board-b").unwrap().unwrap().device_id, "board-b", "{}", name);
+        assert_eq!(store.get_device("board-c").unwrap(), None, "{}", name);
+
+        // Clearing the canvas keeps the devices, which come back sorted by ID
+        store.clear_pixels().unwrap();
+        assert_eq!(store.load_canvas().unwrap(), PackedCanvas::default(), "{}", name);
+        let ids: Vec<String> = store.devices().unwrap().into_iter().map(|device| device.device_id).collect();
+        assert_eq!(ids, ["board-a", "board-b"], "{}", name);
+
+        // Snapshots too, sorted by name
+        let archive = make_archive(&init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough), 7);
+        store.put_snapshot("second", &archive).unwrap();
+        store.put_snapshot("first", &archive).unwrap();
+        assert_eq!(store.get_snapshot("first").unwrap(), Some(archive.clone()), "{}", name);
+        assert_eq!(store.get_snapshot("third").unwrap(), None, "{}", name);
+        let names: Vec<String> = store.snapshots().unwrap().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["first", "second"], "{}", name);
+        assert_eq!(store.delete_snapshot("first"), Ok(true), "{}", name);
+        assert_eq!(store.delete_snapshot("first"), Ok(false), "{}", name);
+
+        // Settings
+        assert_eq!(store.get_setting("event"), Ok(None), "{}", name);
+        store.put_setting("event", "{}").unwrap();
+        store.put_setting("event", "{\"a\":1}").unwrap();
+        assert_eq!(store.get_setting("event"), Ok(Some("{\"a\":1}".to_string())), "{}", name);
+        store.delete_setting("event").unwrap();
+        assert_eq!(store.get_setting("event"), Ok(None), "{}", name);
+    }
+
+    let _ = fs::remove_dir_all(sled_path);
+    let _ = fs::remove_file(sqlite_path);
+}
+
+// Databases from before packed rows are converted when they're opened
+#[test]
+fn test_legacy_pixels_are_migrated() {
+    let sled_path = "unit_test_migrate_sled";
+    let _ = fs::remove_dir_all(sled_path);
+
+    // The old layout: one "x:y" key per pixel
+    {
+        let db = sled::open(sled_path).unwrap();
+        db.insert("5:5", "#ABCDEF").unwrap();
+        db.insert("31:15", "#FF0000").unwrap();
+        // Off the canvas, or not a colour: dropped
+        db.insert("99:0", "#FFFFFF").unwrap();
+        db.insert("0:0", "#UNIQUE").unwrap();
+        db.flush().unwrap();
+    }
+
+    let config = StoreConfig::Sled(sled_path.to_string());
+    let store = reopen(&config);
+    let response = make_canvas_response(&cache_over(store.clone()));
+    assert_eq!(response.pixels[5][5], "#ABCDEF");
+    assert_eq!(response.pixels[15][31], "#FF0000");
+    assert_eq!(response.pixels[0][0], DEFAULT_COLOR);
+    drop(store);
+
+    // Only converted once: a pixel drawn after the migration survives reopening
+    let store = reopen(&config);
+    cache_over(store.clone()).set_pixel(5, 5, Rgb::new(1, 2, 3)).unwrap();
+    drop(store);
+    let store = reopen(&config);
+    assert_eq!(make_canvas_response(&cache_over(store.clone())).pixels[5][5], "#010203");
+
+    let _ = fs::remove_dir_all(sled_path);
+}
+
+#[test]
+fn test_store_config() {
+    assert_eq!(StoreConfig::parse("sled", None), Ok(StoreConfig::Sled("data/canvas_db".to_string())));
+    assert_eq!(StoreConfig::parse("SQLite", Some("/tmp/canvas.db".to_string())), Ok(StoreConfig::Sqlite("/tmp/canvas.db".to_string())));
+    assert_eq!(StoreConfig::parse("memory", None), Ok(StoreConfig::Memory));
+    assert!(StoreConfig::parse("postgres", None).is_err());
+}
+
+// Tests for the in-memory canvas and its write-behind commits
+#[test]
+fn test_write_behind_commits_in_batches() {
+    let store: Arc<dyn CanvasStore> = Arc::new(setup_test_store());
+    let policy = WritePolicy::WriteBehind { interval: Duration::from_secs(60) };
+    let cache = CanvasCache::load(store.clone(), policy).unwrap();
+
+    for x in 0..10 {
+        apply_pixel_update(&cache, &PixelUpdateInput { x, y: 3, color: "#FF0000".to_string() }).unwrap();
+    }
+    apply_pixel_update(&cache, &PixelUpdateInput { x: 0, y: 7, color: "#00FF00".to_string() }).unwrap();
+
+    // Served from memory straight away, but not in the store yet
+    assert_eq!(make_canvas_response(&cache).pixels[3][9], "#FF0000");
+    assert_eq!(store.load_canvas().unwrap(), PackedCanvas::default());
+    assert_eq!(cache.pending_rows(), 2);
+
+    // Eleven updates, two rows, one commit
+    assert_eq!(cache.flush(), Ok(2));
+    assert_eq!(cache.pending_rows(), 0);
+    assert_eq!(store.load_canvas().unwrap(), cache.snapshot());
+    assert_eq!(cache.flush(), Ok(0));
+
+    // A reset drops pending rows, they must not come back with a later commit
+    apply_pixel_update(&cache, &PixelUpdateInput { x: 1, y: 1, color: "#0000FF".to_string() }).unwrap();
+    reset_canvas_db(&cache).unwrap();
+    assert_eq!(cache.flush(), Ok(0));
+    assert_eq!(store.load_canvas().unwrap(), PackedCanvas::default());
+    assert_eq!(make_canvas_response(&cache).pixels[3][9], DEFAULT_COLOR);
+}
+
+#[test]
+fn test_write_policy_from_config() {
+    assert_eq!(WritePolicy::parse(None), Ok(WritePolicy::WriteBehind { interval: Duration::from_millis(100) }));
+    assert_eq!(WritePolicy::parse(Some("250")), Ok(WritePolicy::WriteBehind { interval: Duration::from_millis(250) }));
+    assert_eq!(WritePolicy::parse(Some("0")), Ok(WritePolicy::WriteThrough));
+    assert!(WritePolicy::parse(Some("soon")).is_err());
+}
+
+#[test]
+fn test_shutdown_config() {
+    let defaults = ShutdownConfig::parse(None, None).unwrap();
+    assert_eq!(defaults, ShutdownConfig { notice: Duration::from_millis(1500), drain: Duration::from_secs(10) });
+    let config = ShutdownConfig::parse(Some("0"), Some(" 30 ")).unwrap();
+    assert_eq!(config, ShutdownConfig { notice: Duration::ZERO, drain: Duration::from_secs(30) });
+    assert!(ShutdownConfig::parse(Some("-1"), None).is_err());
+    assert!(ShutdownConfig::parse(None, Some("1.5")).is_err());
+}
+
+// Tests for backups and named snapshots
+#[test]
+fn test_snapshot_save_and_restore() {
+    let app_state = init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough);
+    apply_pixel_update(&app_state.canvas, &PixelUpdateInput { x: 2, y: 3, color: "#ABCDEF".to_string() }).unwrap();
+    log_pixel_update(&app_state, 2, 3, "#ABCDEF".to_string(), now_millis());
+
+    let saved = save_snapshot(&app_state, "before-reset", 1000).unwrap();
+    assert_eq!((saved.created_at, saved.width, saved.height, saved.history_len), (1000, CANVAS_WIDTH, CANVAS_HEIGHT, 1));
+    assert_eq!(save_snapshot(&app_state, "before-reset", 1001), Err(AppError::SnapshotExists));
+    assert_eq!(save_snapshot(&app_state, "no spaces", 1001), Err(AppError::InvalidSnapshotName));
+    assert_eq!(save_snapshot(&app_state, "", 1001), Err(AppError::InvalidSnapshotName));
+
+    reset_canvas_db(&app_state.canvas).unwrap();
+    app_state.history.write().unwrap().clear();
+    assert_eq!(make_canvas_response(&app_state.canvas).pixels[3][2], DEFAULT_COLOR);
+
+    let now = now_millis();
+    assert_eq!(restore_snapshot(&app_state, "before-reset", now).unwrap(), saved);
+    assert_eq!(make_canvas_response(&app_state.canvas).pixels[3][2], "#ABCDEF");
+    assert_eq!(app_state.history.read().unwrap().len(), 1);
+    // And in the store, not just in memory
+    assert_eq!(app_state.store.load_canvas().unwrap().get(2, 3), Some(Rgb::new(0xAB, 0xCD, 0xEF)));
+
+    // Anyone polling from before the restore has to reload, anyone after it carries on
+    let (updates, reset) = fetch_updates_since(&app_state, now - 1);
+    assert!(reset && updates.is_empty());
+    let (_, reset) = fetch_updates_since(&app_state, now);
+    assert!(!reset);
+
+    assert_eq!(restore_snapshot(&app_state, "missing", now), Err(AppError::UnknownSnapshot));
+    assert_eq!(list_snapshots(app_state.store.as_ref()).unwrap().snapshots, vec![saved.clone()]);
+    assert_eq!(delete_snapshot(app_state.store.as_ref(), "before-reset"), Ok(saved));
+    assert_eq!(delete_snapshot(app_state.store.as_ref(), "before-reset"), Err(AppError::UnknownSnapshot));
+    assert!(list_snapshots(app_state.store.as_ref()).unwrap().snapshots.is_empty());
+}
+
+#[test]
+fn test_archive_checks() {
+    let app_state = init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough);
+    let archive = make_archive(&app_state, 0);
+    assert_eq!(archive_to_canvas(&archive), Ok(PackedCanvas::default()));
+
+    let mut newer = archive.clone();
+    newer.version += 1;
+    assert_eq!(archive_to_canvas(&newer), Err(AppError::UnsupportedArchive));
+
+    let mut other = archive.clone();
+    other.format = "something-else".to_string();
+    assert_eq!(archive_to_canvas(&other), Err(AppError::UnsupportedArchive));
+
+    let mut bad_color = archive.clone();
+    bad_color.pixels[0][0] = "red".to_string();
+    assert_eq!(archive_to_canvas(&bad_color), Err(AppError::InvalidArchive));
+
+    // From a bigger board: what fits is kept
+    let mut bigger = archive;
+    bigger.pixels.iter_mut().for_each(|row| row.push("#FFFFFF".to_string()));
+    bigger.pixels[0][0] = "#FF0000".to_string();
+    let canvas = archive_to_canvas(&bigger).unwrap();
+    assert_eq!(canvas.get(0, 0), Some(Rgb::new(255, 0, 0)));
+    assert_eq!(canvas.get(CANVAS_WIDTH - 1, 0), Some(Rgb::BLACK));
+}
+
+// Tests for scheduled events
+#[test]
+fn test_event_phases() {
+    let schedule = EventSchedule { opens_at: 1000, closes_at: 2000, snapshot_on_close: None, reset_on_close: false };
+    assert_eq!(event_phase(None, 1500), EventPhase::Idle);
+    assert_eq!(event_phase(Some(&schedule), 999), EventPhase::Scheduled);
+    assert_eq!(event_phase(Some(&schedule), 1000), EventPhase::Open);
+    assert_eq!(event_phase(Some(&schedule), 1999), EventPhase::Open);
+    assert_eq!(event_phase(Some(&schedule), 2000), EventPhase::Closed);
+}
+
+#[test]
+fn test_schedule_event_validation() {
+    let app_state = init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough);
+    let input = |opens_at: Option<u64>, open_minutes: u64, snapshot: Option<&str>| EventScheduleInput {
+        opens_at,
+        open_minutes,
+        snapshot_on_close: snapshot.map(str::to_string),
+        reset_on_close: false,
+    };
+
+    assert_eq!(schedule_event(&app_state, &input(None, 0, None), 1000), Err(AppError::InvalidDuration));
+    assert_eq!(schedule_event(&app_state, &input(None, 100_000, None), 1000), Err(AppError::InvalidDuration));
+    // Already over
+    assert_eq!(schedule_event(&app_state, &input(Some(0), 1, None), 60_000), Err(AppError::InvalidOpensAt));
+    assert_eq!(schedule_event(&app_state, &input(Some(u64::MAX), 1, None), 1000), Err(AppError::InvalidOpensAt));
+    assert_eq!(schedule_event(&app_state, &input(None, 5, Some("no spaces")), 1000), Err(AppError::InvalidSnapshotName));
+    save_snapshot(&app_state, "taken", 0).unwrap();
+    assert_eq!(schedule_event(&app_state, &input(None, 5, Some("taken")), 1000), Err(AppError::SnapshotExists));
+    assert_eq!(event_status(&app_state, 1000).phase, EventPhase::Idle);
+
+    // Opens now when opens_at is left out
+    let status = schedule_event(&app_state, &input(None, 5, None), 1000).unwrap();
+    assert_eq!(status.phase, EventPhase::Open);
+    assert_eq!(status.schedule.map(|schedule| (schedule.opens_at, schedule.closes_at)), Some((1000, 301_000)));
+}
+
+#[test]
+fn test_event_opens_closes_and_resets() {
+    let store: Arc<dyn CanvasStore> = Arc::new(setup_test_store());
+    let app_state = init_app_state(store.clone(), WritePolicy::WriteThrough);
+    let input = EventScheduleInput {
+        opens_at: Some(10_000),
+        open_minutes: 5,
+        snapshot_on_close: Some("demo".to_string()),
+        reset_on_close: true,
+    };
+    let closes_at = 10_000 + 5 * 60 * 1000;
+    schedule_event(&app_state, &input, 5_000).unwrap();
+
+    // Read-only until it opens, and again once it closes
+    assert_eq!(check_canvas_open(&app_state, 9_999), Err(AppError::CanvasClosed));
+    assert_eq!(check_canvas_open(&app_state, 10_000), Ok(()));
+    apply_pixel_update(&app_state.canvas, &PixelUpdateInput { x: 1, y: 1, color: "#FF0000".to_string() }).unwrap();
+    assert_eq!(run_close_actions(&app_state, closes_at - 1), Ok(false));
+    assert_eq!(check_canvas_open(&app_state, closes_at), Err(AppError::CanvasClosed));
+
+    // At closing time the drawing is saved, then cleared, once
+    assert_eq!(run_close_actions(&app_state, closes_at), Ok(true));
+    assert_eq!(run_close_actions(&app_state, closes_at + 1000), Ok(false));
+    let saved = store.get_snapshot("demo").unwrap().unwrap();
+    assert_eq!(saved.pixels[1][1], "#FF0000");
+    assert_eq!(make_canvas_response(&app_state.canvas).pixels[1][1], DEFAULT_COLOR);
+    let (_, reset) = fetch_updates_since(&app_state, closes_at - 1);
+    assert!(reset, "pollers reload the cleared board");
+
+    // A restart keeps the event, and doesn't run the closing actions again
+    let restarted = init_app_state(store.clone(), WritePolicy::WriteThrough);
+    assert_eq!(event_status(&restarted, closes_at + 5000).phase, EventPhase::Closed);
+    assert_eq!(run_close_actions(&restarted, closes_at + 5000), Ok(false));
+
+    // Cancelling opens the board again, for this run and the next
+    assert_eq!(cancel_event(&restarted, closes_at + 6000).unwrap().phase, EventPhase::Idle);
+    assert_eq!(check_canvas_open(&restarted, closes_at + 6000), Ok(()));
+    let restarted = init_app_state(store, WritePolicy::WriteThrough);
+    assert_eq!(event_status(&restarted, closes_at + 7000).phase, EventPhase::Idle);
+}
+
+// Tests for the board mode
+#[test]
+fn test_board_mode_is_kept_across_restarts() {
+    let store: Arc<dyn CanvasStore> = Arc::new(setup_test_store());
+    let app_state = init_app_state(store.clone(), WritePolicy::WriteThrough);
+    assert_eq!(current_mode(&app_state).mode, BoardMode::Open);
+    assert_eq!(check_mode_allows_pixels(&app_state), Ok(()));
+
+    assert_eq!(set_mode(&app_state, BoardMode::ReadOnly, 1_000).unwrap().changed_at, 1_000);
+    assert_eq!(check_mode_allows_pixels(&app_state), Err(AppError::ReadOnly));
+    // Setting the same mode again isn't a change
+    assert_eq!(set_mode(&app_state, BoardMode::ReadOnly, 2_000).unwrap().changed_at, 1_000);
+
+    set_mode(&app_state, BoardMode::Maintenance, 3_000).unwrap();
+    let restarted = init_app_state(store.clone(), WritePolicy::WriteThrough);
+    assert_eq!(current_mode(&restarted).mode, BoardMode::Maintenance);
+    assert_eq!(check_mode_allows_pixels(&restarted), Err(AppError::Maintenance));
+
+    set_mode(&restarted, BoardMode::Open, 4_000).unwrap();
+    let restarted = init_app_state(store, WritePolicy::WriteThrough);
+    assert_eq!(check_mode_allows_pixels(&restarted), Ok(()));
+}
+
+// The board mode fails closed: an unreadable saved mode or a poisoned lock never reopens a frozen board
+#[test]
+fn test_board_mode_fails_closed() {
+    let store = setup_test_store();
+    assert_eq!(load_mode(&store).unwrap().mode, BoardMode::Open);
+    store.put_setting("mode", "{ not json").unwrap();
+    assert_eq!(load_mode(&store).unwrap().mode, BoardMode::ReadOnly);
+
+    let app_state = init_app_state(Arc::new(setup_test_store()), WritePolicy::WriteThrough);
+    set_mode(&app_state, BoardMode::Maintenance, 1_000).unwrap();
+    let poisoner = app_state.clone();
+    let _ = std::thread::spawn(move || {
+        let _mode = poisoner.mode.write().unwrap();
+        panic!("poison the mode lock");
+    })
+    .join();
+    assert!(app_state.mode.is_poisoned());
+    assert_eq!(check_mode_allows_pixels(&app_state), Err(AppError::Maintenance));
+}
+
+// Tests for the metrics
+#[test]
+fn test_metrics_render() {
+    let metrics = Metrics::default();
+    let start = Instant::now();
+    metrics.pixel_placed();
+    metrics.rejected("read_only");
+    metrics.rejected("read_only");
+    metrics.client_polled("10.0.0.1".parse().unwrap(), start);
+    metrics.client_polled("10.0.0.2".parse().unwrap(), start + ACTIVE_CLIENT_WINDOW);
+    metrics.db_call("write_rows", Duration::from_micros(50));
+    metrics.db_call("write_rows", Duration::from_secs(2));
+
+    let body = metrics.render(7, start + ACTIVE_CLIENT_WINDOW + Duration::from_secs(1));
+    let lines: Vec<&str> = body.lines().collect();
+    for expected in [
+        "rustycanvas_pixels_placed_total 1",
+        "rustycanvas_rejections_total{reason=\"read_only\"} 2",
+        // The first client has gone quiet
+        "rustycanvas_active_clients 1",
+        "rustycanvas_history_size 7",
+        // Buckets are cumulative, the slow call only shows up in +Inf
+        "rustycanvas_db_seconds_bucket{op=\"write_rows\",le=\"0.0001\"} 1",
+        "rustycanvas_db_seconds_bucket{op=\"write_rows\",le=\"0.5\"} 1",
+        "rustycanvas_db_seconds_bucket{op=\"write_rows\",le=\"+Inf\"} 2",
+        "rustycanvas_db_seconds_count{op=\"write_rows\"} 2",
+    ] {
+        assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, body);
+    }
+}
+
+// Readiness probes only write to the store once per WRITE_CHECK_INTERVAL_MS
+#[test]
+fn test_readiness_write_check_is_rate_limited() {
+    let store = Arc::new(MemoryStore::new());
+    let app_state = init_app_state(store.clone(), WritePolicy::WriteThrough);
+    let now = 1_700_000_000_000;
+    let written = || store.get_setting("ready_probe").unwrap();
+
+    assert!(check_readiness(&app_state, now).ready);
+    assert_eq!(written(), Some(now.to_string()));
+
+    // The next probes report the earlier write
+    let readiness = check_readiness(&app_state, now + 5_000);
+    assert!(readiness.ready);
+    assert_eq!(written(), Some(now.to_string()));
+    let store_write = readiness.checks.iter().find(|check| check.name == "store_write").unwrap();
+    assert_eq!(store_write.detail, "store took a write 5s ago");
+
+    // Until the interval is up
+    let later = now + WRITE_CHECK_INTERVAL_MS;
+    assert!(check_readiness(&app_state, later).ready);
+    assert_eq!(written(), Some(later.to_string()));
+}
+
+// Addresses that stopped polling are forgotten even when nobody scrapes /metrics
+#[test]
+fn test_pollers_are_pruned_without_scrapes() {
+    let metrics = Metrics::default();
+    let start = Instant::now();
+    for last in 0..=255u8 {
+        metrics.client_polled(std::net::IpAddr::from([10, 0, 0, last]), start);
+    }
+
+    // One poll after the window has passed drops everyone who went quiet
+    let later = start + ACTIVE_CLIENT_WINDOW * 2;
+    metrics.client_polled("10.0.1.1".parse().unwrap(), later);
+
+    // Counting as of 'start' keeps every address still held, so the other 256 must already be gone
+    assert_eq!(metrics.active_clients(start), 1);
+}
@@ -0,0 +1,37 @@
+// src/archive.rs
+
+// Archive files for 'rustycanvas export' and 'rustycanvas import'
+// Unlike backup.rs these go through a running server (GET /backup and POST /restore), so nothing has to be stopped
+
+// For your knowledge
+// The file is the server's CanvasArchive as pretty JSON: the board as colour strings, the recent history and
+// when it was taken. It doesn't depend on how the server stores things, so it moves between sled and SQLite servers
+// We check the format and version before uploading, so a wrong file fails here with a clear message
+
+use protocol::{CanvasArchive, ARCHIVE_FORMAT, ARCHIVE_VERSION};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use crate::backup::BackupError;
+
+pub fn save_archive(archive: &CanvasArchive, file: &Path) -> Result<(), BackupError> {
+    serde_json::to_writer_pretty(BufWriter::new(File::create(file)?), archive)?;
+    Ok(())
+}
+
+pub fn load_archive(file: &Path) -> Result<CanvasArchive, BackupError> {
+    let archive: CanvasArchive = serde_json::from_reader(BufReader::new(File::open(file)?))?;
+    check_archive(&archive)?;
+    Ok(archive)
+}
+
+// Same rule as the server: our own format, at most the version we know
+pub fn check_archive(archive: &CanvasArchive) -> Result<(), BackupError> {
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(BackupError::UnsupportedFormat(format!("format '{}'", archive.format)));
+    }
+    if archive.version > ARCHIVE_VERSION {
+        return Err(BackupError::UnsupportedFormat(format!("version {}", archive.version)));
+    }
+    Ok(())
+}
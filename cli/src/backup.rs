@@ -0,0 +1,161 @@
+// src/backup.rs
+
+// Offline backup and restore of the server's sled database
+// The server must be stopped first: sled locks its directory while it is open
+
+// For your knowledge
+// The backup copies every sled tree byte-for-byte, so it doesn't care how the server lays out its keys
+// Keys and values are arbitrary bytes, so they are hex-encoded to fit in a JSON file
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+pub const BACKUP_FORMAT: &str = "rustycanvas-sled-backup";
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Backup {
+    pub format: String,
+    pub version: u32,
+    pub trees: Vec<TreeBackup>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TreeBackup {
+    // Hex-encoded tree name (the default tree is "__sled__default")
+    pub name: String,
+    // Hex-encoded (key, value) pairs
+    pub entries: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+pub enum BackupError {
+    Sled(sled::Error),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    // The file isn't one of our backups, or was written by a newer version
+    UnsupportedFormat(String),
+    // Restoring over existing data needs --force
+    NotEmpty,
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Sled(err) => write!(f, "database error: {}", err),
+            BackupError::Io(err) => write!(f, "file error: {}", err),
+            BackupError::Json(err) => write!(f, "invalid backup file: {}", err),
+            BackupError::UnsupportedFormat(what) => write!(f, "unsupported backup: {}", what),
+            BackupError::NotEmpty => write!(f, "target database is not empty (use --force to overwrite it)"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<sled::Error> for BackupError {
+    fn from(err: sled::Error) -> Self {
+        BackupError::Sled(err)
+    }
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(err: std::io::Error) -> Self {
+        BackupError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for BackupError {
+    fn from(err: serde_json::Error) -> Self {
+        BackupError::Json(err)
+    }
+}
+
+// Copy every tree of an open database into a Backup
+pub fn backup_db(db: &sled::Db) -> Result<Backup, BackupError> {
+    let mut trees = Vec::new();
+
+    for name in db.tree_names() {
+        let tree = db.open_tree(&name)?;
+        let mut entries = Vec::new();
+        for kv in tree.iter() {
+            let (key, value) = kv?;
+            entries.push((to_hex(&key), to_hex(&value)));
+        }
+        trees.push(TreeBackup { name: to_hex(&name), entries });
+    }
+
+    Ok(Backup {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        trees,
+    })
+}
+
+// Write a Backup into an open database, returns how many entries were restored
+// Existing data is refused unless 'force', in which case every tree is cleared first
+pub fn restore_db(db: &sled::Db, backup: &Backup, force: bool) -> Result<usize, BackupError> {
+    if backup.format != BACKUP_FORMAT {
+        return Err(BackupError::UnsupportedFormat(format!("format '{}'", backup.format)));
+    }
+    if backup.version > BACKUP_VERSION {
+        return Err(BackupError::UnsupportedFormat(format!("version {}", backup.version)));
+    }
+
+    let has_data = db.tree_names().iter().any(|name| {
+        db.open_tree(name).map(|tree| !tree.is_empty()).unwrap_or(true)
+    });
+    if has_data && !force {
+        return Err(BackupError::NotEmpty);
+    }
+    for name in db.tree_names() {
+        db.open_tree(&name)?.clear()?;
+    }
+
+    let mut restored = 0;
+    for tree_backup in &backup.trees {
+        let tree = db.open_tree(from_hex(&tree_backup.name)?)?;
+        for (key, value) in &tree_backup.entries {
+            tree.insert(from_hex(key)?, from_hex(value)?)?;
+            restored += 1;
+        }
+    }
+
+    db.flush()?;
+    Ok(restored)
+}
+
+// Open the database at 'db_path' and save it to 'file' as JSON
+pub fn backup_to_file(db_path: &Path, file: &Path) -> Result<Backup, BackupError> {
+    let db = sled::open(db_path)?;
+    let backup = backup_db(&db)?;
+    serde_json::to_writer_pretty(BufWriter::new(File::create(file)?), &backup)?;
+    Ok(backup)
+}
+
+// Load 'file' and restore it into the database at 'db_path'
+pub fn restore_from_file(file: &Path, db_path: &Path, force: bool) -> Result<usize, BackupError> {
+    let backup: Backup = serde_json::from_reader(BufReader::new(File::open(file)?))?;
+    let db = sled::open(db_path)?;
+    restore_db(&db, &backup, force)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, BackupError> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(BackupError::UnsupportedFormat(format!("invalid hex string '{}'", hex)));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| BackupError::UnsupportedFormat(format!("invalid hex string '{}'", hex)))
+        })
+        .collect()
+}
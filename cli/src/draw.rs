@@ -0,0 +1,52 @@
+// src/draw.rs
+
+// Converts an image file into the list of pixel updates needed to paint it onto the board
+
+use image::{DynamicImage, GenericImageView, imageops::FilterType};
+use protocol::{CanvasResponse, PixelUpdateInput, Rgb};
+
+// Pixels more transparent than this are left alone
+const ALPHA_THRESHOLD: u8 = 128;
+
+// Scale the image down to fit the area right/below (offset_x, offset_y), keeping its aspect ratio
+// Nearest-neighbour keeps hard pixel-art edges instead of blurring them
+pub fn fit_to_canvas(image: &DynamicImage, canvas: &CanvasResponse, offset_x: u32, offset_y: u32) -> DynamicImage {
+    let max_width = canvas.width.saturating_sub(offset_x);
+    let max_height = canvas.height.saturating_sub(offset_y);
+
+    if image.width() <= max_width && image.height() <= max_height {
+        return image.clone();
+    }
+    image.resize(max_width.max(1), max_height.max(1), FilterType::Nearest)
+}
+
+// Every pixel of 'image' (placed at the offset) that is opaque, on the board and different from what's already there
+pub fn image_to_pixels(image: &DynamicImage, canvas: &CanvasResponse, offset_x: u32, offset_y: u32) -> Vec<PixelUpdateInput> {
+    let mut updates = Vec::new();
+
+    for (ix, iy, pixel) in image.pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a < ALPHA_THRESHOLD {
+            continue;
+        }
+
+        let x = offset_x + ix;
+        let y = offset_y + iy;
+        if x >= canvas.width || y >= canvas.height {
+            continue;
+        }
+
+        let color = Rgb::new(r, g, b);
+        let current = canvas.pixels
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .and_then(|hex| Rgb::parse_hex(hex).ok());
+        if current == Some(color) {
+            continue;
+        }
+
+        updates.push(PixelUpdateInput { x, y, color: color.to_string() });
+    }
+
+    updates
+}
@@ -0,0 +1,9 @@
+// src/lib.rs
+
+// Library half of the 'rustycanvas' command-line tool
+// main.rs only parses arguments, the actual work lives in these modules so /tests/ can reach it
+
+pub mod archive;
+pub mod backup;
+pub mod draw;
+pub mod render;
@@ -0,0 +1,322 @@
+// src/main.rs
+
+// 'rustycanvas' command-line tool for operating a canvas server
+// Run 'rustycanvas --help' for the list of subcommands
+
+use clap::{Parser, Subcommand, ValueEnum};
+use cli::{archive, backup, draw, render};
+use client::{BoardMode, CanvasClient, EventPhase, EventScheduleInput, EventStatus, UpdateEvent};
+use futures_util::StreamExt;
+use protocol::Rgb;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Parser)]
+#[command(name = "rustycanvas", about = "Operate a RustyCanvas server")]
+struct Cli {
+    /// Server root URL
+    #[arg(long, env = "RUSTYCANVAS_SERVER", default_value = "http://127.0.0.1:8080", global = true)]
+    server: String,
+
+    /// Admin token for protected routes (must match the server's ADMIN_TOKEN)
+    #[arg(long, env = "RUSTYCANVAS_ADMIN_TOKEN", global = true, hide_env_values = true)]
+    token: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the current board using truecolor blocks
+    Show,
+    /// Place a single pixel
+    Place {
+        x: u32,
+        y: u32,
+        /// Colour as #RRGGBB
+        color: String,
+    },
+    /// Paint an image file onto the board (scaled down to fit if needed)
+    Draw {
+        image: PathBuf,
+        /// Column of the image's top-left corner
+        #[arg(long, default_value_t = 0)]
+        x: u32,
+        /// Row of the image's top-left corner
+        #[arg(long, default_value_t = 0)]
+        y: u32,
+    },
+    /// Follow live pixel updates until interrupted
+    Tail {
+        /// Poll interval in milliseconds
+        #[arg(long, default_value_t = 500)]
+        interval_ms: u64,
+    },
+    /// Clear the whole board (admin)
+    Reset,
+    /// Download the board and its recent history into an archive file (admin)
+    Export {
+        /// Output file
+        file: PathBuf,
+    },
+    /// Replace the board with an archive file written by 'rustycanvas export' (admin)
+    Import {
+        /// Archive file
+        file: PathBuf,
+    },
+    /// Named snapshots of the board, kept on the server (admin)
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommand,
+    },
+    /// Timed events: open the board for a while, then make it read-only
+    Event {
+        #[command(subcommand)]
+        command: EventCommand,
+    },
+    /// Show the board mode, or freeze/reopen the board (admin)
+    Mode {
+        /// Switch to this mode
+        mode: Option<ModeArg>,
+    },
+    /// Save the sled database to a JSON file (stop the server first)
+    Backup {
+        /// Output file
+        file: PathBuf,
+        /// Path of the server's sled database
+        #[arg(long, default_value = "data/canvas_db")]
+        db: PathBuf,
+    },
+    /// Restore the sled database from a backup file (stop the server first)
+    Restore {
+        /// Backup file written by 'rustycanvas backup'
+        file: PathBuf,
+        /// Path of the server's sled database
+        #[arg(long, default_value = "data/canvas_db")]
+        db: PathBuf,
+        /// Overwrite a database that already has data in it
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotCommand {
+    /// Save the current board as a snapshot
+    Save {
+        /// Letters, digits, '-' and '_'
+        name: String,
+    },
+    /// List the saved snapshots
+    List,
+    /// Put a snapshot back on the board (clients reload it)
+    Restore { name: String },
+    /// Delete a snapshot
+    Delete { name: String },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ModeArg {
+    Open,
+    ReadOnly,
+    Maintenance,
+}
+
+impl From<ModeArg> for BoardMode {
+    fn from(mode: ModeArg) -> Self {
+        match mode {
+            ModeArg::Open => BoardMode::Open,
+            ModeArg::ReadOnly => BoardMode::ReadOnly,
+            ModeArg::Maintenance => BoardMode::Maintenance,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum EventCommand {
+    /// Show the current event and its phase
+    Status,
+    /// Set up an event, replacing any previous one (admin)
+    Schedule {
+        /// How long the board stays open, in minutes
+        minutes: u64,
+        /// Open this many minutes from now instead of right away
+        #[arg(long, default_value_t = 0)]
+        starts_in: u64,
+        /// Save the board as this snapshot when it closes
+        #[arg(long)]
+        snapshot: Option<String>,
+        /// Clear the board when it closes (after the snapshot)
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Drop the event, the board is open as usual again (admin)
+    Cancel,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = CanvasClient::new(cli.server);
+    if let Some(token) = cli.token {
+        client = client.with_admin_token(token);
+    }
+
+    match cli.command {
+        Command::Show => {
+            let canvas = client.get_canvas().await?;
+            print!("{}", render::render_ansi(&canvas));
+        }
+        Command::Place { x, y, color } => {
+            // Catch typos before they reach the server
+            Rgb::parse_hex(&color).map_err(|err| format!("'{}': {}", color, err))?;
+            client.place_pixel(x, y, &color).await?;
+            println!("placed {} at ({}, {})", color, x, y);
+        }
+        Command::Draw { image, x, y } => {
+            let canvas = client.get_canvas().await?;
+            let image = image::open(&image)?;
+            let fitted = draw::fit_to_canvas(&image, &canvas, x, y);
+            let updates = draw::image_to_pixels(&fitted, &canvas, x, y);
+
+            client.place_pixels(&updates).await?;
+            println!("placed {} pixels ({}x{} image at ({}, {}))", updates.len(), fitted.width(), fitted.height(), x, y);
+        }
+        Command::Tail { interval_ms } => {
+            let since = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+            let mut updates = Box::pin(client.updates(since, Duration::from_millis(interval_ms)));
+
+            while let Some(event) = updates.next().await {
+                match event {
+                    Ok(UpdateEvent::Pixel(update)) => println!(
+                        "{} ({:>2}, {:>2}) {} @ {}",
+                        render::swatch(&update.color), update.x, update.y, update.color, update.timestamp
+                    ),
+                    Ok(UpdateEvent::Resync(canvas)) => {
+                        println!("-- fell behind, full board follows --");
+                        print!("{}", render::render_ansi(&canvas));
+                    }
+                    Ok(UpdateEvent::ServerShuttingDown) => eprintln!("-- server is shutting down, will keep retrying --"),
+                    Ok(UpdateEvent::ModeChanged(mode)) => eprintln!("-- board is now {} --", mode_name(mode)),
+                    Err(err) => eprintln!("warning: {}", err),
+                }
+            }
+        }
+        Command::Reset => {
+            let response = client.reset().await?;
+            println!("{}", response.message);
+        }
+        Command::Export { file } => {
+            let archive = client.backup().await?;
+            archive::save_archive(&archive, &file)?;
+            println!("exported {}x{} board and {} updates to {}", archive.width, archive.height, archive.history.len(), file.display());
+        }
+        Command::Import { file } => {
+            let archive = archive::load_archive(&file)?;
+            client.restore(&archive).await?;
+            println!("restored {} (taken at {})", file.display(), archive.created_at);
+        }
+        Command::Snapshot { command } => match command {
+            SnapshotCommand::Save { name } => {
+                let snapshot = client.create_snapshot(&name).await?;
+                println!("saved snapshot '{}'", snapshot.name);
+            }
+            SnapshotCommand::List => {
+                let snapshots = client.snapshots().await?;
+                if snapshots.is_empty() {
+                    println!("no snapshots");
+                }
+                for snapshot in snapshots {
+                    println!(
+                        "{:<24} {}x{}, {} updates, taken at {}",
+                        snapshot.name, snapshot.width, snapshot.height, snapshot.history_len, snapshot.created_at
+                    );
+                }
+            }
+            SnapshotCommand::Restore { name } => {
+                client.restore_snapshot(&name).await?;
+                println!("restored snapshot '{}'", name);
+            }
+            SnapshotCommand::Delete { name } => {
+                client.delete_snapshot(&name).await?;
+                println!("deleted snapshot '{}'", name);
+            }
+        },
+        Command::Event { command } => {
+            let status = match command {
+                EventCommand::Status => client.get_event().await?,
+                EventCommand::Schedule { minutes, starts_in, snapshot, reset } => {
+                    // Times are the server's, so count from its clock rather than ours
+                    let server_time = client.get_event().await?.server_time;
+                    let input = EventScheduleInput {
+                        opens_at: Some(server_time + starts_in * 60 * 1000),
+                        open_minutes: minutes,
+                        snapshot_on_close: snapshot,
+                        reset_on_close: reset,
+                    };
+                    client.schedule_event(&input).await?
+                }
+                EventCommand::Cancel => client.cancel_event().await?,
+            };
+            print_event(&status);
+        }
+        Command::Mode { mode } => {
+            let status = match mode {
+                Some(mode) => client.set_mode(mode.into()).await?,
+                None => client.get_mode().await?,
+            };
+            println!("board is {}", mode_name(status.mode));
+        }
+        Command::Backup { file, db } => {
+            let backup = backup::backup_to_file(&db, &file)?;
+            let entries: usize = backup.trees.iter().map(|tree| tree.entries.len()).sum();
+            println!("backed up {} entries from {} to {}", entries, db.display(), file.display());
+        }
+        Command::Restore { file, db, force } => {
+            let restored = backup::restore_from_file(&file, &db, force)?;
+            println!("restored {} entries from {} into {}", restored, file.display(), db.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn print_event(status: &EventStatus) {
+    let Some(schedule) = &status.schedule else {
+        println!("no event, the board is open");
+        return;
+    };
+    let minutes = |ms: u64| ms.div_ceil(60_000);
+    match status.phase {
+        EventPhase::Scheduled => println!("opens in {} min, then open for {} min", minutes(schedule.opens_at - status.server_time), minutes(schedule.closes_at - schedule.opens_at)),
+        EventPhase::Open => println!("open, closes in {} min", minutes(schedule.closes_at - status.server_time)),
+        EventPhase::Closed | EventPhase::Idle => println!("closed, the board is read-only"),
+    }
+    if let Some(name) = &schedule.snapshot_on_close {
+        println!("saved as snapshot '{}' when it closes", name);
+    }
+    if schedule.reset_on_close {
+        println!("cleared when it closes");
+    }
+}
+
+fn mode_name(mode: BoardMode) -> &'static str {
+    match mode {
+        BoardMode::Open => "open",
+        BoardMode::ReadOnly => "read-only",
+        BoardMode::Maintenance => "in maintenance",
+    }
+}
@@ -0,0 +1,31 @@
+// src/render.rs
+
+// Draws the canvas in a terminal using ANSI truecolor escape codes
+
+// For your knowledge
+// "\x1b[48;2;R;G;Bm" sets the background colour to an exact RGB value, "\x1b[0m" resets it
+// Each pixel is printed as two spaces so it comes out roughly square in most terminal fonts
+
+use protocol::{CanvasResponse, Rgb};
+
+const RESET: &str = "\x1b[0m";
+
+// A two-character block in the given colour, or "??" if the colour isn't valid "#RRGGBB"
+pub fn swatch(color: &str) -> String {
+    match Rgb::parse_hex(color) {
+        Ok(rgb) => format!("\x1b[48;2;{};{};{}m  {}", rgb.r, rgb.g, rgb.b, RESET),
+        Err(_) => "??".to_string(),
+    }
+}
+
+// The whole board, one terminal line per canvas row
+pub fn render_ansi(canvas: &CanvasResponse) -> String {
+    let mut out = String::new();
+    for row in &canvas.pixels {
+        for color in row {
+            out.push_str(&swatch(color));
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,168 @@
+// tests/unit_tests.rs
+
+// Unit tests: the CLI's rendering, image conversion, backup and archive logic (no server needed)
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use image::{DynamicImage, Rgba, RgbaImage};
+use cli::archive::{load_archive, save_archive};
+use cli::backup::{backup_db, restore_db, restore_from_file, Backup, BackupError};
+use cli::draw::{fit_to_canvas, image_to_pixels};
+use cli::render::{render_ansi, swatch};
+use protocol::{CanvasArchive, CanvasResponse, ARCHIVE_FORMAT, ARCHIVE_VERSION, CANVAS_HEIGHT, CANVAS_WIDTH, DEFAULT_COLOR};
+
+// Test helper to build a blank board
+fn blank_canvas() -> CanvasResponse {
+    CanvasResponse {
+        width: CANVAS_WIDTH,
+        height: CANVAS_HEIGHT,
+        pixels: vec![vec![DEFAULT_COLOR.to_string(); CANVAS_WIDTH as usize]; CANVAS_HEIGHT as usize],
+    }
+}
+
+// Test helper for a path of this test's own in the temp dir, so tests running in parallel never share one
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rustycanvas-cli-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&path);
+    let _ = fs::remove_file(&path);
+    path
+}
+
+// Test helper to create a db
+fn setup_test_db(path: &Path) -> sled::Db {
+    let _ = fs::remove_dir_all(path);
+    sled::open(path).expect("Failed to open test db")
+}
+
+#[test]
+fn test_swatch_uses_truecolor_background() {
+    assert_eq!(swatch("#FF8000"), "\x1b[48;2;255;128;0m  \x1b[0m");
+    assert_eq!(swatch("#UNIQUE"), "??");
+}
+
+#[test]
+fn test_render_ansi_has_one_line_per_row() {
+    let mut canvas = blank_canvas();
+    canvas.pixels[0][0] = "#FF0000".to_string();
+
+    let out = render_ansi(&canvas);
+    let lines: Vec<&str> = out.lines().collect();
+
+    assert_eq!(lines.len(), CANVAS_HEIGHT as usize);
+    assert!(lines[0].starts_with("\x1b[48;2;255;0;0m"));
+    assert_eq!(lines[1].matches("\x1b[48;2;0;0;0m").count(), CANVAS_WIDTH as usize);
+}
+
+#[test]
+fn test_image_to_pixels_skips_transparent_and_unchanged() {
+    let mut canvas = blank_canvas();
+    canvas.pixels[0][3] = "#00FF00".to_string();
+
+    let mut img = RgbaImage::new(3, 1);
+    img.put_pixel(0, 0, Rgba([255, 0, 0, 255])); // opaque red -> placed
+    img.put_pixel(1, 0, Rgba([0, 0, 255, 0]));   // transparent -> skipped
+    img.put_pixel(2, 0, Rgba([0, 255, 0, 255])); // same as board -> skipped
+    let img = DynamicImage::ImageRgba8(img);
+
+    let updates = image_to_pixels(&img, &canvas, 1, 0);
+
+    assert_eq!(updates.len(), 1);
+    assert_eq!((updates[0].x, updates[0].y), (1, 0));
+    assert_eq!(updates[0].color, "#FF0000");
+}
+
+#[test]
+fn test_fit_to_canvas_scales_down_large_images() {
+    let canvas = blank_canvas();
+    let img = DynamicImage::ImageRgba8(RgbaImage::new(64, 64));
+
+    let fitted = fit_to_canvas(&img, &canvas, 0, 0);
+    assert_eq!((fitted.width(), fitted.height()), (16, 16));
+
+    // Small images are left alone
+    let small = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+    let fitted = fit_to_canvas(&small, &canvas, 0, 0);
+    assert_eq!((fitted.width(), fitted.height()), (4, 4));
+}
+
+#[test]
+fn test_backup_restore_roundtrip() {
+    let src_path = temp_path("backup_src");
+    let dst_path = temp_path("backup_dst");
+    let file = temp_path("backup.json");
+
+    // Everything goes through Db handles that stay open: sled's flusher thread can still hold a directory's
+    // lock for a moment after its Db is dropped, so re-opening a path in the same process is racy
+    let db = setup_test_db(&src_path);
+    db.insert("5:5", "#ABCDEF").unwrap();
+    db.open_tree("extra").unwrap().insert([0u8, 255], vec![1u8, 2, 3]).unwrap();
+    let backup = backup_db(&db).unwrap();
+    assert!(backup.trees.iter().any(|tree| !tree.entries.is_empty()));
+    serde_json::to_writer(fs::File::create(&file).unwrap(), &backup).unwrap();
+
+    let restored = restore_from_file(&file, &dst_path, false).unwrap();
+    assert_eq!(restored, 2);
+
+    let loaded: Backup = serde_json::from_reader(fs::File::open(&file).unwrap()).unwrap();
+    assert_eq!(loaded, backup);
+    let copy = sled::Config::new().temporary(true).open().unwrap();
+    assert_eq!(restore_db(&copy, &loaded, false).unwrap(), 2);
+    assert_eq!(copy.get("5:5").unwrap().unwrap().as_ref(), b"#ABCDEF");
+    assert_eq!(copy.open_tree("extra").unwrap().get([0u8, 255]).unwrap().unwrap().as_ref(), &[1u8, 2, 3]);
+    drop(db);
+
+    let _ = fs::remove_dir_all(&src_path);
+    let _ = fs::remove_dir_all(&dst_path);
+    let _ = fs::remove_file(&file);
+}
+
+#[test]
+fn test_restore_refuses_non_empty_db_without_force() {
+    let path = temp_path("restore_force");
+    let db = setup_test_db(&path);
+    db.insert("0:0", "#FFFFFF").unwrap();
+
+    let backup = {
+        let mut backup = backup_db(&db).unwrap();
+        backup.trees.iter_mut().for_each(|tree| tree.entries.clear());
+        backup
+    };
+
+    assert!(matches!(restore_db(&db, &backup, false), Err(BackupError::NotEmpty)));
+
+    // --force clears the existing data first
+    restore_db(&db, &backup, true).unwrap();
+    assert!(db.get("0:0").unwrap().is_none());
+    drop(db);
+
+    let _ = fs::remove_dir_all(&path);
+}
+
+#[test]
+fn test_archive_file_roundtrip_and_checks() {
+    let file = temp_path("archive.json");
+    let file = file.as_path();
+    let mut archive = CanvasArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at: 1_700_000_000_000,
+        width: CANVAS_WIDTH,
+        height: CANVAS_HEIGHT,
+        pixels: blank_canvas().pixels,
+        history: vec![],
+    };
+
+    save_archive(&archive, file).unwrap();
+    assert_eq!(load_archive(file).unwrap(), archive);
+
+    // Written by a newer server
+    archive.version = ARCHIVE_VERSION + 1;
+    save_archive(&archive, file).unwrap();
+    assert!(matches!(load_archive(file), Err(BackupError::UnsupportedFormat(_))));
+
+    // A sled backup is not an archive
+    fs::write(file, r#"{ "format": "rustycanvas-sled-backup", "version": 1, "trees": [] }"#).unwrap();
+    assert!(load_archive(file).is_err());
+
+    let _ = fs::remove_file(file);
+}
@@ -0,0 +1,169 @@
+// src/error.rs
+
+// Typed errors for the canvas client
+// The server reports failures as short string codes (e.g. "out_of_bounds"), ErrorCode mirrors them one-to-one
+
+use reqwest::StatusCode;
+use std::fmt;
+
+// Error codes the backend puts in the 'error' / 'message' field of a failed response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorCode {
+    // The server couldn't read the request (a bug in the caller, or a client/server version mismatch)
+    InvalidBody,
+    InvalidQuery,
+    InvalidPath,
+    UnsupportedMediaType,
+    PayloadTooLarge,
+    UnknownRoute,
+    MethodNotAllowed,
+    OutOfBounds,
+    InvalidColor,
+    // A board's report was refused (see firmware-core)
+    InvalidDeviceId,
+    InvalidFirmwareVersion,
+    UnknownDevice,
+    // A scheduled event has the board read-only right now
+    CanvasClosed,
+    // An event that can't be scheduled (see CanvasClient::schedule_event)
+    InvalidDuration,
+    InvalidOpensAt,
+    // An admin froze the board (see CanvasClient::get_mode)
+    ReadOnly,
+    Maintenance,
+    DbReadError,
+    DbWriteError,
+    DbFlushError,
+    DbClearError,
+    Unauthorized,
+    UnknownSnapshot,
+    SnapshotExists,
+    InvalidSnapshotName,
+    // A backup written by a newer server, or not a backup at all
+    UnsupportedArchive,
+    InvalidArchive,
+    // A code this version of the client doesn't know about yet
+    Other(String),
+}
+
+impl ErrorCode {
+    pub fn parse(code: &str) -> Self {
+        match code {
+            "invalid_body" => ErrorCode::InvalidBody,
+            "invalid_query" => ErrorCode::InvalidQuery,
+            "invalid_path" => ErrorCode::InvalidPath,
+            "unsupported_media_type" => ErrorCode::UnsupportedMediaType,
+            "payload_too_large" => ErrorCode::PayloadTooLarge,
+            "unknown_route" => ErrorCode::UnknownRoute,
+            "method_not_allowed" => ErrorCode::MethodNotAllowed,
+            "out_of_bounds" => ErrorCode::OutOfBounds,
+            "invalid_color" => ErrorCode::InvalidColor,
+            "invalid_device_id" => ErrorCode::InvalidDeviceId,
+            "invalid_firmware_version" => ErrorCode::InvalidFirmwareVersion,
+            "unknown_device" => ErrorCode::UnknownDevice,
+            "canvas_closed" => ErrorCode::CanvasClosed,
+            "invalid_duration" => ErrorCode::InvalidDuration,
+            "invalid_opens_at" => ErrorCode::InvalidOpensAt,
+            "read_only" => ErrorCode::ReadOnly,
+            "maintenance" => ErrorCode::Maintenance,
+            "db_read_error" => ErrorCode::DbReadError,
+            "db_write_error" => ErrorCode::DbWriteError,
+            "db_flush_error" => ErrorCode::DbFlushError,
+            "db_clear_error" => ErrorCode::DbClearError,
+            "unauthorized" => ErrorCode::Unauthorized,
+            "unknown_snapshot" => ErrorCode::UnknownSnapshot,
+            "snapshot_exists" => ErrorCode::SnapshotExists,
+            "invalid_snapshot_name" => ErrorCode::InvalidSnapshotName,
+            "unsupported_archive" => ErrorCode::UnsupportedArchive,
+            "invalid_archive" => ErrorCode::InvalidArchive,
+            other => ErrorCode::Other(other.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorCode::InvalidBody => "invalid_body",
+            ErrorCode::InvalidQuery => "invalid_query",
+            ErrorCode::InvalidPath => "invalid_path",
+            ErrorCode::UnsupportedMediaType => "unsupported_media_type",
+            ErrorCode::PayloadTooLarge => "payload_too_large",
+            ErrorCode::UnknownRoute => "unknown_route",
+            ErrorCode::MethodNotAllowed => "method_not_allowed",
+            ErrorCode::OutOfBounds => "out_of_bounds",
+            ErrorCode::InvalidColor => "invalid_color",
+            ErrorCode::InvalidDeviceId => "invalid_device_id",
+            ErrorCode::InvalidFirmwareVersion => "invalid_firmware_version",
+            ErrorCode::UnknownDevice => "unknown_device",
+            ErrorCode::CanvasClosed => "canvas_closed",
+            ErrorCode::InvalidDuration => "invalid_duration",
+            ErrorCode::InvalidOpensAt => "invalid_opens_at",
+            ErrorCode::ReadOnly => "read_only",
+            ErrorCode::Maintenance => "maintenance",
+            ErrorCode::DbReadError => "db_read_error",
+            ErrorCode::DbWriteError => "db_write_error",
+            ErrorCode::DbFlushError => "db_flush_error",
+            ErrorCode::DbClearError => "db_clear_error",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::UnknownSnapshot => "unknown_snapshot",
+            ErrorCode::SnapshotExists => "snapshot_exists",
+            ErrorCode::InvalidSnapshotName => "invalid_snapshot_name",
+            ErrorCode::UnsupportedArchive => "unsupported_archive",
+            ErrorCode::InvalidArchive => "invalid_archive",
+            ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    // The request never got a response (connection refused, timeout, bad URL, ...)
+    Transport(reqwest::Error),
+    // The server answered with one of its own error codes
+    Server { status: StatusCode, code: ErrorCode },
+    // The server answered with a non-success status and a body we couldn't interpret
+    // (e.g. a proxy's error page, or an older server's plaintext rejection for a malformed query string)
+    UnexpectedStatus { status: StatusCode, body: String },
+    // A success response whose body didn't match the expected JSON shape
+    Decode(reqwest::Error),
+    // One pixel of a batch failed; pixels before 'index' were placed
+    Batch { index: usize, source: Box<ClientError> },
+}
+
+impl ClientError {
+    // The server error code, if this error came from the server
+    pub fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            ClientError::Server { code, .. } => Some(code),
+            ClientError::Batch { source, .. } => source.code(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(err) => write!(f, "request failed: {}", err),
+            ClientError::Server { status, code } => write!(f, "server returned {} ({})", code, status),
+            ClientError::UnexpectedStatus { status, body } => write!(f, "unexpected status {}: {}", status, body),
+            ClientError::Decode(err) => write!(f, "could not decode response: {}", err),
+            ClientError::Batch { index, source } => write!(f, "batch failed at pixel {}: {}", index, source),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Transport(err) | ClientError::Decode(err) => Some(err),
+            ClientError::Batch { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,374 @@
+// src/lib.rs
+
+// Typed async client for the RustyCanvas backend
+// Wraps every route in create_router so bots and scripts don't have to hand-roll HTTP, except the two
+// the boards themselves call (POST /devices/register and POST /devices/heartbeat, see firmware-core)
+
+// For your knowledge
+// Request/response bodies come from the shared protocol crate, so they always match what the server sends
+// Every method returns Result<_, ClientError>, where server-side failures keep the server's error code
+
+pub mod error;
+pub mod stream;
+
+pub use error::{ClientError, ErrorCode};
+pub use stream::UpdateEvent;
+pub use protocol::{
+    BoardMode,
+    CanvasArchive,
+    CanvasResponse,
+    CanvasConfig,
+    ClearCanvasResponse,
+    DeviceStatus,
+    EventPhase,
+    EventResponse,
+    EventSchedule,
+    EventScheduleInput,
+    EventStatus,
+    HealthResponse,
+    ModeResponse,
+    ModeStatus,
+    PixelUpdate,
+    PixelUpdateInput,
+    PixelUpdateResponse,
+    ReadinessCheck,
+    ReadinessResponse,
+    SnapshotInfo,
+    SnapshotResponse,
+    SnapshotsResponse,
+    TestPattern,
+    UpdatesResponse,
+    VersionResponse,
+};
+
+use protocol::{DeviceReportResponse, DevicesResponse, GetUpdatesInput, ModeInput, SnapshotInput, TestPatternInput};
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+#[derive(Clone, Debug)]
+pub struct CanvasClient {
+    http: reqwest::Client,
+    base_url: String,
+    admin_token: Option<String>,
+}
+
+impl CanvasClient {
+    // base_url is the server root, e.g. "http://127.0.0.1:8080"
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    // Use a pre-configured reqwest client (timeouts, proxies, default headers, ...)
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        CanvasClient { http, base_url, admin_token: None }
+    }
+
+    // Token sent as "Authorization: Bearer <token>" on admin routes (the server's ADMIN_TOKEN)
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    // GET /canvas
+    pub async fn get_canvas(&self) -> Result<CanvasResponse, ClientError> {
+        let response = self.http.get(self.url("/canvas")).send().await.map_err(ClientError::Transport)?;
+        decode(response).await
+    }
+
+    // POST /pixel
+    pub async fn place_pixel(&self, x: u32, y: u32, color: &str) -> Result<(), ClientError> {
+        let input = PixelUpdateInput { x, y, color: color.to_string() };
+        self.send_pixel(&input).await
+    }
+
+    // Places each pixel in order with POST /pixel, stopping at the first failure
+    // The server has no batch route, so this is a client-side convenience
+    pub async fn place_pixels(&self, pixels: &[PixelUpdateInput]) -> Result<(), ClientError> {
+        for (index, input) in pixels.iter().enumerate() {
+            self.send_pixel(input)
+                .await
+                .map_err(|source| ClientError::Batch { index, source: Box::new(source) })?;
+        }
+        Ok(())
+    }
+
+    async fn send_pixel(&self, input: &PixelUpdateInput) -> Result<(), ClientError> {
+        let response = self.http
+            .post(self.url("/pixel"))
+            .json(input)
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+
+        let body: PixelUpdateResponse = decode(response).await?;
+        match body {
+            PixelUpdateResponse { success: true, .. } => Ok(()),
+            // A 200 with success=false shouldn't happen, but don't silently drop it
+            PixelUpdateResponse { error, .. } => Err(ClientError::Server {
+                status: reqwest::StatusCode::OK,
+                code: ErrorCode::parse(error.as_deref().unwrap_or_default()),
+            }),
+        }
+    }
+
+    // GET /updates?since=...
+    pub async fn get_updates(&self, since: u64) -> Result<UpdatesResponse, ClientError> {
+        let response = self.http
+            .get(self.url("/updates"))
+            .query(&GetUpdatesInput { since })
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode(response).await
+    }
+
+    // POST /reset (admin)
+    pub async fn reset(&self) -> Result<ClearCanvasResponse, ClientError> {
+        let response = self.admin(self.http.post(self.url("/reset")))
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode(response).await
+    }
+
+    // GET /backup (admin): the whole board and recent history, ready to be saved to a file
+    pub async fn backup(&self) -> Result<CanvasArchive, ClientError> {
+        let response = self.admin(self.http.get(self.url("/backup")))
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode(response).await
+    }
+
+    // POST /restore (admin): replace the board with an archive from backup()
+    pub async fn restore(&self, archive: &CanvasArchive) -> Result<(), ClientError> {
+        let response = self.admin(self.http.post(self.url("/restore")))
+            .json(archive)
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode_snapshot(response).await.map(|_| ())
+    }
+
+    // GET /snapshots (admin)
+    pub async fn snapshots(&self) -> Result<Vec<SnapshotInfo>, ClientError> {
+        let response = self.admin(self.http.get(self.url("/snapshots")))
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        let body: SnapshotsResponse = decode(response).await?;
+        Ok(body.snapshots)
+    }
+
+    // POST /snapshots (admin): save the current board on the server under 'name'
+    pub async fn create_snapshot(&self, name: &str) -> Result<SnapshotInfo, ClientError> {
+        let response = self.admin(self.http.post(self.url("/snapshots")))
+            .json(&SnapshotInput { name: name.to_string() })
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode_named_snapshot(response).await
+    }
+
+    // POST /snapshots/{name}/restore (admin)
+    pub async fn restore_snapshot(&self, name: &str) -> Result<SnapshotInfo, ClientError> {
+        let response = self.admin(self.http.post(self.url(&format!("/snapshots/{}/restore", name))))
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode_named_snapshot(response).await
+    }
+
+    // DELETE /snapshots/{name} (admin)
+    pub async fn delete_snapshot(&self, name: &str) -> Result<SnapshotInfo, ClientError> {
+        let response = self.admin(self.http.delete(self.url(&format!("/snapshots/{}", name))))
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode_named_snapshot(response).await
+    }
+
+    // GET /event: whether a timed event has the board open or read-only
+    pub async fn get_event(&self) -> Result<EventStatus, ClientError> {
+        let response = self.http.get(self.url("/event")).send().await.map_err(ClientError::Transport)?;
+        decode(response).await
+    }
+
+    // POST /event (admin): set up a timed event, replacing any previous one
+    pub async fn schedule_event(&self, input: &EventScheduleInput) -> Result<EventStatus, ClientError> {
+        let response = self.admin(self.http.post(self.url("/event")))
+            .json(input)
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode_event(response).await
+    }
+
+    // DELETE /event (admin): drop the event, the board is open as usual again
+    pub async fn cancel_event(&self) -> Result<EventStatus, ClientError> {
+        let response = self.admin(self.http.delete(self.url("/event")))
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        decode_event(response).await
+    }
+
+    // GET /mode: whether the board is open, read-only or in maintenance
+    pub async fn get_mode(&self) -> Result<ModeStatus, ClientError> {
+        let response = self.http.get(self.url("/mode")).send().await.map_err(ClientError::Transport)?;
+        decode(response).await
+    }
+
+    // POST /mode (admin): freeze or reopen the board
+    pub async fn set_mode(&self, mode: BoardMode) -> Result<ModeStatus, ClientError> {
+        let response = self.admin(self.http.post(self.url("/mode")))
+            .json(&ModeInput { mode })
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        let body: ModeResponse = decode(response).await?;
+        match body {
+            ModeResponse { success: true, mode: Some(mode), .. } => Ok(mode),
+            ModeResponse { error, .. } => Err(ClientError::Server {
+                status: reqwest::StatusCode::OK,
+                code: ErrorCode::parse(error.as_deref().unwrap_or("missing_mode")),
+            }),
+        }
+    }
+
+    // GET /devices (admin): every LED board that has registered, and whether it's online
+    pub async fn devices(&self) -> Result<Vec<DeviceStatus>, ClientError> {
+        let response = self.admin(self.http.get(self.url("/devices")))
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        let body: DevicesResponse = decode(response).await?;
+        Ok(body.devices)
+    }
+
+    // POST /devices/{device_id}/test-pattern (admin): the board shows it after its next heartbeat
+    pub async fn queue_test_pattern(&self, device_id: &str, pattern: TestPattern) -> Result<(), ClientError> {
+        let response = self.admin(self.http.post(self.url(&format!("/devices/{}/test-pattern", device_id))))
+            .json(&TestPatternInput { pattern })
+            .send()
+            .await
+            .map_err(ClientError::Transport)?;
+        let body: DeviceReportResponse = decode(response).await?;
+        match body {
+            DeviceReportResponse { success: true, .. } => Ok(()),
+            DeviceReportResponse { error, .. } => Err(ClientError::Server {
+                status: reqwest::StatusCode::OK,
+                code: ErrorCode::parse(error.as_deref().unwrap_or_default()),
+            }),
+        }
+    }
+
+    // GET /healthz: the server process is up
+    pub async fn health(&self) -> Result<HealthResponse, ClientError> {
+        let response = self.http.get(self.url("/healthz")).send().await.map_err(ClientError::Transport)?;
+        decode(response).await
+    }
+
+    // GET /readyz: whether the server can take traffic, and each check it ran
+    // A server that isn't ready answers 503 with the same body, so that is returned rather than an error
+    pub async fn readiness(&self) -> Result<ReadinessResponse, ClientError> {
+        let response = self.http.get(self.url("/readyz")).send().await.map_err(ClientError::Transport)?;
+        if response.status() == reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            return response.json().await.map_err(ClientError::Decode);
+        }
+        decode(response).await
+    }
+
+    // GET /version: the server's build and the board's fixed settings
+    pub async fn version(&self) -> Result<VersionResponse, ClientError> {
+        let response = self.http.get(self.url("/version")).send().await.map_err(ClientError::Transport)?;
+        decode(response).await
+    }
+
+    // GET /metrics: the Prometheus text, as is
+    pub async fn metrics(&self) -> Result<String, ClientError> {
+        let response = self.http.get(self.url("/metrics")).send().await.map_err(ClientError::Transport)?;
+        let status = response.status();
+        let body = response.text().await.map_err(ClientError::Transport)?;
+        if !status.is_success() {
+            return Err(ClientError::UnexpectedStatus { status, body });
+        }
+        Ok(body)
+    }
+
+    fn admin(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+// Turn a response into T, or into a typed error for non-success statuses
+async fn decode<T: DeserializeOwned>(response: Response) -> Result<T, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return response.json::<T>().await.map_err(ClientError::Decode);
+    }
+
+    let body = response.text().await.map_err(ClientError::Transport)?;
+    match error_code_from_body(&body) {
+        Some(code) => Err(ClientError::Server { status, code }),
+        None => Err(ClientError::UnexpectedStatus { status, body }),
+    }
+}
+
+// The snapshot routes answer SnapshotResponse, with the snapshot on success (except POST /restore)
+async fn decode_snapshot(response: Response) -> Result<Option<SnapshotInfo>, ClientError> {
+    let body: SnapshotResponse = decode(response).await?;
+    match body {
+        SnapshotResponse { success: true, snapshot, .. } => Ok(snapshot),
+        // A 200 with success=false shouldn't happen, but don't silently drop it
+        SnapshotResponse { error, .. } => Err(ClientError::Server {
+            status: reqwest::StatusCode::OK,
+            code: ErrorCode::parse(error.as_deref().unwrap_or_default()),
+        }),
+    }
+}
+
+// For the routes that always describe the snapshot they acted on
+async fn decode_named_snapshot(response: Response) -> Result<SnapshotInfo, ClientError> {
+    decode_snapshot(response).await?.ok_or_else(|| ClientError::UnexpectedStatus {
+        status: reqwest::StatusCode::OK,
+        body: "snapshot missing from the response".to_string(),
+    })
+}
+
+// The event routes answer EventResponse, with the event's status on success
+async fn decode_event(response: Response) -> Result<EventStatus, ClientError> {
+    let body: EventResponse = decode(response).await?;
+    match body {
+        EventResponse { success: true, event: Some(event), .. } => Ok(event),
+        EventResponse { error, .. } => Err(ClientError::Server {
+            status: reqwest::StatusCode::OK,
+            code: ErrorCode::parse(error.as_deref().unwrap_or("missing_event")),
+        }),
+    }
+}
+
+// Failed responses carry the code in 'error' (ErrorResponse, every route)
+// Older servers put it in 'message' on POST /reset, so that is still looked at
+fn error_code_from_body(body: &str) -> Option<ErrorCode> {
+    #[derive(serde::Deserialize)]
+    struct ErrorBody {
+        error: Option<String>,
+        message: Option<String>,
+    }
+
+    let parsed: ErrorBody = serde_json::from_str(body).ok()?;
+    parsed.error.or(parsed.message).map(|code| ErrorCode::parse(&code))
+}
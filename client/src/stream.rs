@@ -0,0 +1,99 @@
+// src/stream.rs
+
+// Turns GET /updates polling into an async Stream of individual events
+
+// For your knowledge
+// The server has no push channel, so the stream polls /updates every 'poll_interval'
+// It remembers the newest timestamp it has seen and only asks for updates after that
+// If the server says reset_required (we fell too far behind), the stream refetches the whole canvas and yields it as a Resync,
+// then carries on from the server's latest_timestamp
+// When the server starts shutting down it says so in its answers, the stream yields ServerShuttingDown once and
+// keeps polling (expect errors until the server is back)
+// Every answer also carries the board mode, the stream yields ModeChanged whenever it differs from the last one
+// (so a stream started while the board is frozen begins with one)
+
+use crate::{BoardMode, CanvasClient, CanvasResponse, ClientError, PixelUpdate};
+use futures_util::stream::{self, Stream};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateEvent {
+    // A single pixel changed
+    Pixel(PixelUpdate),
+    // The client missed too much history, here is the full board instead
+    Resync(CanvasResponse),
+    // The server is stopping, polls will fail for a while
+    ServerShuttingDown,
+    // An admin switched the board to open, read-only or maintenance
+    ModeChanged(BoardMode),
+}
+
+struct PollState {
+    client: CanvasClient,
+    since: u64,
+    poll_interval: Duration,
+    pending: VecDeque<UpdateEvent>,
+    first_poll: bool,
+    shutting_down: bool,
+    mode: BoardMode,
+}
+
+impl CanvasClient {
+    // Stream every update after 'since' (milliseconds since the UNIX epoch, like PixelUpdate::timestamp)
+    // Errors are yielded as items and polling carries on, so callers decide whether to give up
+    pub fn updates(&self, since: u64, poll_interval: Duration) -> impl Stream<Item = Result<UpdateEvent, ClientError>> + 'static {
+        let state = PollState {
+            client: self.clone(),
+            since,
+            poll_interval,
+            pending: VecDeque::new(),
+            first_poll: true,
+            shutting_down: false,
+            mode: BoardMode::Open,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.pending.pop_front() {
+                    return Some((Ok(event), state));
+                }
+
+                if !state.first_poll {
+                    tokio::time::sleep(state.poll_interval).await;
+                }
+                state.first_poll = false;
+
+                if let Err(err) = poll_once(&mut state).await {
+                    return Some((Err(err), state));
+                }
+            }
+        })
+    }
+}
+
+async fn poll_once(state: &mut PollState) -> Result<(), ClientError> {
+    let response = state.client.get_updates(state.since).await?;
+
+    if response.shutting_down && !state.shutting_down {
+        state.pending.push_back(UpdateEvent::ServerShuttingDown);
+    }
+    state.shutting_down = response.shutting_down;
+    if response.mode != state.mode {
+        state.mode = response.mode;
+        state.pending.push_back(UpdateEvent::ModeChanged(response.mode));
+    }
+
+    if response.reset_required {
+        let canvas = state.client.get_canvas().await?;
+        state.since = response.latest_timestamp;
+        state.pending.push_back(UpdateEvent::Resync(canvas));
+        return Ok(());
+    }
+
+    for update in response.updates {
+        state.since = state.since.max(update.timestamp);
+        state.pending.push_back(UpdateEvent::Pixel(update));
+    }
+    Ok(())
+}
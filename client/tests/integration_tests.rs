@@ -0,0 +1,332 @@
+// tests/integration_tests.rs
+
+// Integration tests: run the real backend router in-process and drive it through the client
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use futures_util::StreamExt;
+use tokio::net::TcpListener;
+use backend::server::routes::create_router;
+use backend::server::state::init_app_state;
+use backend::server::store::MemoryStore;
+use backend::server::cache::WritePolicy;
+use client::{BoardMode, CanvasClient, ClientError, ErrorCode, EventPhase, EventScheduleInput, PixelUpdateInput, TestPattern, UpdateEvent};
+use protocol::DeviceReport;
+
+// Test helper to serve the backend (on an in-memory store) on a random local port and return a client pointed at it
+async fn spawn_server() -> CanvasClient {
+    let app = create_router(init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    CanvasClient::new(format!("http://{}", addr))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+// Test for GET /canvas
+#[tokio::test]
+async fn test_client_get_canvas() {
+    let client = spawn_server().await;
+
+    let canvas = client.get_canvas().await.unwrap();
+
+    assert_eq!(canvas.width, 32);
+    assert_eq!(canvas.height, 16);
+    assert_eq!(canvas.pixels[0][0], "#000000");
+}
+
+// Test for POST /pixel
+#[tokio::test]
+async fn test_client_place_pixel() {
+    let client = spawn_server().await;
+
+    client.place_pixel(3, 4, "#FF0000").await.unwrap();
+
+    let canvas = client.get_canvas().await.unwrap();
+    assert_eq!(canvas.pixels[4][3], "#FF0000");
+}
+
+// Test for POST /pixel error codes
+#[tokio::test]
+async fn test_client_out_of_bounds_is_typed() {
+    let client = spawn_server().await;
+
+    let err = client.place_pixel(999, 0, "#FF0000").await.unwrap_err();
+
+    match err {
+        ClientError::Server { status, code } => {
+            assert_eq!(status.as_u16(), 400);
+            assert_eq!(code, ErrorCode::OutOfBounds);
+        }
+        other => panic!("expected a server error, got {:?}", other),
+    }
+}
+
+// Test for a request the server can't use: the error body is the same on every route, so it's typed too
+#[tokio::test]
+async fn test_client_invalid_color_is_typed() {
+    let client = spawn_server().await;
+
+    let err = client.place_pixel(0, 0, "red").await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::InvalidColor));
+}
+
+// Test for batches of POST /pixel
+#[tokio::test]
+async fn test_client_place_pixels_batch() {
+    let client = spawn_server().await;
+
+    let batch = vec![
+        PixelUpdateInput { x: 0, y: 0, color: "#00FF00".to_string() },
+        PixelUpdateInput { x: 1, y: 0, color: "#0000FF".to_string() },
+        PixelUpdateInput { x: 100, y: 0, color: "#FFFFFF".to_string() },
+        PixelUpdateInput { x: 2, y: 0, color: "#FFFFFF".to_string() },
+    ];
+
+    let err = client.place_pixels(&batch).await.unwrap_err();
+    assert!(matches!(err, ClientError::Batch { index: 2, .. }));
+    assert_eq!(err.code(), Some(&ErrorCode::OutOfBounds));
+
+    // Everything before the failure was placed, nothing after it
+    let canvas = client.get_canvas().await.unwrap();
+    assert_eq!(canvas.pixels[0][0], "#00FF00");
+    assert_eq!(canvas.pixels[0][1], "#0000FF");
+    assert_eq!(canvas.pixels[0][2], "#000000");
+}
+
+// Test for GET /updates
+#[tokio::test]
+async fn test_client_get_updates() {
+    let client = spawn_server().await;
+    let start = now_millis() - 1000;
+
+    client.place_pixel(10, 10, "#ABCDEF").await.unwrap();
+
+    let updates = client.get_updates(start).await.unwrap();
+    assert!(!updates.reset_required);
+    assert_eq!(updates.updates.len(), 1);
+    assert_eq!(updates.updates[0].color, "#ABCDEF");
+}
+
+// Test for POST /reset
+#[tokio::test]
+async fn test_client_reset() {
+    let client = spawn_server().await;
+
+    client.place_pixel(5, 5, "#FF0000").await.unwrap();
+    let reset = client.reset().await.unwrap();
+    assert!(reset.success);
+
+    let canvas = client.get_canvas().await.unwrap();
+    assert_eq!(canvas.pixels[5][5], "#000000");
+}
+
+// Test for POST /reset with an admin token configured on the server
+#[tokio::test]
+async fn test_client_reset_with_admin_token() {
+    let mut app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.admin_token = Some("secret".to_string());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, create_router(app_state)).await.unwrap();
+    });
+
+    let err = CanvasClient::new(base_url.clone()).reset().await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::Unauthorized));
+
+    let reset = CanvasClient::new(base_url).with_admin_token("secret").reset().await.unwrap();
+    assert!(reset.success);
+}
+
+// Test for backups and snapshots, and the update stream noticing a restore
+#[tokio::test]
+async fn test_client_backup_and_snapshots() {
+    let client = spawn_server().await;
+    client.place_pixel(1, 2, "#ABCDEF").await.unwrap();
+
+    let archive = client.backup().await.unwrap();
+    assert_eq!(archive.pixels[2][1], "#ABCDEF");
+
+    let saved = client.create_snapshot("first").await.unwrap();
+    assert_eq!(saved.name, "first");
+    let err = client.create_snapshot("first").await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::SnapshotExists));
+    assert_eq!(client.snapshots().await.unwrap(), vec![saved.clone()]);
+
+    client.reset().await.unwrap();
+    let mut updates = Box::pin(client.updates(now_millis(), Duration::from_millis(20)));
+    // Let the stream make its first poll before the board changes under it
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    client.restore(&archive).await.unwrap();
+    assert_eq!(client.get_canvas().await.unwrap().pixels[2][1], "#ABCDEF");
+    let event = tokio::time::timeout(Duration::from_secs(5), updates.next()).await.unwrap().unwrap().unwrap();
+    assert!(matches!(event, UpdateEvent::Resync(canvas) if canvas.pixels[2][1] == "#ABCDEF"));
+
+    client.reset().await.unwrap();
+    assert_eq!(client.restore_snapshot("first").await.unwrap(), saved);
+    assert_eq!(client.get_canvas().await.unwrap().pixels[2][1], "#ABCDEF");
+
+    assert_eq!(client.delete_snapshot("first").await.unwrap(), saved);
+    let err = client.restore_snapshot("first").await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::UnknownSnapshot));
+}
+
+// Test for scheduled events: the board is read-only until the event opens
+#[tokio::test]
+async fn test_client_scheduled_event() {
+    let client = spawn_server().await;
+    assert_eq!(client.get_event().await.unwrap().phase, EventPhase::Idle);
+
+    let input = EventScheduleInput { opens_at: Some(now_millis() + 3_600_000), open_minutes: 5, snapshot_on_close: None, reset_on_close: false };
+    assert_eq!(client.schedule_event(&input).await.unwrap().phase, EventPhase::Scheduled);
+
+    let err = client.place_pixel(0, 0, "#FFFFFF").await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::CanvasClosed));
+
+    assert_eq!(client.cancel_event().await.unwrap().phase, EventPhase::Idle);
+    client.place_pixel(0, 0, "#FFFFFF").await.unwrap();
+
+    // Events the server won't take
+    let input = EventScheduleInput { opens_at: None, open_minutes: 0, snapshot_on_close: None, reset_on_close: false };
+    let err = client.schedule_event(&input).await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::InvalidDuration));
+    let input = EventScheduleInput { opens_at: Some(1), open_minutes: 5, snapshot_on_close: None, reset_on_close: false };
+    let err = client.schedule_event(&input).await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::InvalidOpensAt));
+}
+
+// Test for the polling update stream
+#[tokio::test]
+async fn test_client_update_stream() {
+    let client = spawn_server().await;
+    let start = now_millis() - 1000;
+
+    let mut updates = Box::pin(client.updates(start, Duration::from_millis(20)));
+
+    client.place_pixel(1, 1, "#111111").await.unwrap();
+    client.place_pixel(2, 2, "#222222").await.unwrap();
+
+    let mut colors = Vec::new();
+    while colors.len() < 2 {
+        let event = tokio::time::timeout(Duration::from_secs(5), updates.next())
+            .await
+            .expect("stream stalled")
+            .unwrap()
+            .unwrap();
+        match event {
+            UpdateEvent::Pixel(update) => colors.push(update.color),
+            UpdateEvent::Resync(_) => panic!("unexpected resync"),
+            UpdateEvent::ServerShuttingDown => panic!("unexpected shutdown"),
+            UpdateEvent::ModeChanged(mode) => panic!("unexpected mode change to {:?}", mode),
+        }
+    }
+
+    assert_eq!(colors, vec!["#111111", "#222222"]);
+}
+
+// Test for the update stream while the server is shutting down
+#[tokio::test]
+async fn test_client_update_stream_reports_shutdown_once() {
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    app_state.shutdown.trigger();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = CanvasClient::new(format!("http://{}", listener.local_addr().unwrap()));
+    tokio::spawn(async move {
+        axum::serve(listener, create_router(app_state)).await.unwrap();
+    });
+
+    let mut updates = Box::pin(client.updates(now_millis() - 1000, Duration::from_millis(20)));
+    let first = tokio::time::timeout(Duration::from_secs(5), updates.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!(first, UpdateEvent::ServerShuttingDown);
+
+    // Still polling, and the pixel arrives without a second notice
+    client.place_pixel(1, 1, "#111111").await.unwrap();
+    let next = tokio::time::timeout(Duration::from_secs(5), updates.next()).await.unwrap().unwrap().unwrap();
+    assert!(matches!(next, UpdateEvent::Pixel(update) if update.color == "#111111"));
+}
+
+// Test for the board mode, and its announcement on the update stream
+#[tokio::test]
+async fn test_client_board_mode() {
+    let client = spawn_server().await;
+    assert_eq!(client.get_mode().await.unwrap().mode, BoardMode::Open);
+    let mut updates = Box::pin(client.updates(now_millis() - 1000, Duration::from_millis(20)));
+
+    assert_eq!(client.set_mode(BoardMode::ReadOnly).await.unwrap().mode, BoardMode::ReadOnly);
+    let err = client.place_pixel(0, 0, "#FFFFFF").await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::ReadOnly));
+    let next = tokio::time::timeout(Duration::from_secs(5), updates.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!(next, UpdateEvent::ModeChanged(BoardMode::ReadOnly));
+
+    client.set_mode(BoardMode::Open).await.unwrap();
+    let next = tokio::time::timeout(Duration::from_secs(5), updates.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!(next, UpdateEvent::ModeChanged(BoardMode::Open));
+    client.place_pixel(0, 0, "#FFFFFF").await.unwrap();
+}
+
+// Test for the device routes, with a board registering the way the firmware does
+#[tokio::test]
+async fn test_client_devices_and_test_pattern() {
+    let client = spawn_server().await;
+    assert!(client.devices().await.unwrap().is_empty());
+    let err = client.queue_test_pattern("board-a", TestPattern::ColorBars).await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::UnknownDevice));
+
+    // Boards' own reports are refused with codes the client knows too
+    let report = DeviceReport { device_id: "not a valid id!".to_string(), firmware_version: "0.1.0".to_string(), uptime_secs: 1, rssi: None, last_applied: 0 };
+    let response = reqwest::Client::new().post(format!("{}/devices/register", client.base_url())).json(&report).send().await.unwrap();
+    let body: protocol::ErrorResponse = response.json().await.unwrap();
+    assert_eq!(ErrorCode::parse(&body.error), ErrorCode::InvalidDeviceId);
+
+    let report = DeviceReport { device_id: "board-a".to_string(), firmware_version: "0.1.0".to_string(), uptime_secs: 1, rssi: None, last_applied: 0 };
+    let response = reqwest::Client::new().post(format!("{}/devices/register", client.base_url())).json(&report).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    client.queue_test_pattern("board-a", TestPattern::ColorBars).await.unwrap();
+    let devices = client.devices().await.unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].device_id, "board-a");
+    assert_eq!(devices[0].pending_test_pattern, Some(TestPattern::ColorBars));
+}
+
+// Test for the routes a supervisor or dashboard polls
+#[tokio::test]
+async fn test_client_health_readiness_version_and_metrics() {
+    let client = spawn_server().await;
+
+    assert_eq!(client.health().await.unwrap().status, "ok");
+    let readiness = client.readiness().await.unwrap();
+    assert!(readiness.ready);
+    assert!(readiness.checks.iter().all(|check| check.ok));
+
+    let version = client.version().await.unwrap();
+    assert_eq!(version.canvas.width, 32);
+    assert_eq!(version.canvas.height, 16);
+
+    client.place_pixel(0, 0, "#FF0000").await.unwrap();
+    let metrics = client.metrics().await.unwrap();
+    assert!(metrics.lines().any(|line| line == "rustycanvas_pixels_placed_total 1"), "{}", metrics);
+}
+
+// Test for transport failures
+#[tokio::test]
+async fn test_client_connection_refused() {
+    // Bind then drop a listener so the port is (almost certainly) closed
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let client = CanvasClient::new(format!("http://{}/", addr));
+    let err = client.get_canvas().await.unwrap_err();
+
+    assert!(matches!(err, ClientError::Transport(_)));
+}
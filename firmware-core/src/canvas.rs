@@ -0,0 +1,153 @@
+// src/canvas.rs
+
+// Turns the raw bytes of a GET /canvas HTTP response into a CanvasFrame
+
+// For your knowledge
+// parse_canvas_response() needs the whole response in one buffer (fine on the host, ~9 KB on the board)
+// CanvasStream does the same job chunk by chunk as the bytes come off the socket (see stream.rs)
+
+use crate::http::ResponseParser;
+use crate::stream::{Event, JsonTokenizer, StreamError};
+use protocol::{CanvasFrame, Rgb};
+
+// Also returned when parsing GET /updates responses (updates.rs)
+#[derive(Debug, Clone, PartialEq)]
+pub enum CanvasParseError {
+    // The response isn't valid UTF-8 (usually means it was cut off mid-character)
+    NotUtf8,
+    // No blank line between headers and body
+    MissingBody,
+    // The body isn't the JSON we expect (or was truncated)
+    Json(serde_json_core::de::Error),
+}
+
+// Split a raw HTTP response into (headers, body) at the first blank line
+pub fn split_http_response(response: &str) -> Option<(&str, &str)> {
+    let body_start = response.find("\r\n\r\n")?;
+    Some((&response[..body_start], &response[body_start + 4..]))
+}
+
+// Parse a JSON body from GET /canvas
+pub fn parse_canvas_json(body: &str) -> Result<CanvasFrame, CanvasParseError> {
+    serde_json_core::from_str::<CanvasFrame>(body)
+        .map(|(frame, _)| frame)
+        .map_err(CanvasParseError::Json)
+}
+
+// The body of a full HTTP response (status line, headers and body) as text
+pub fn response_body(response: &[u8]) -> Result<&str, CanvasParseError> {
+    let response = core::str::from_utf8(response).map_err(|_| CanvasParseError::NotUtf8)?;
+    let (_, body) = split_http_response(response).ok_or(CanvasParseError::MissingBody)?;
+    Ok(body)
+}
+
+// Parse a full HTTP response from GET /canvas
+pub fn parse_canvas_response(response: &[u8]) -> Result<CanvasFrame, CanvasParseError> {
+    parse_canvas_json(response_body(response)?)
+}
+
+// Which top-level field of the canvas object we are in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CanvasField {
+    Width,
+    Height,
+    Pixels,
+    Other,
+}
+
+// Writes pixels into the frame as the events arrive
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CanvasSink {
+    field: CanvasField,
+    row: u32,
+    col: u32,
+}
+
+impl CanvasSink {
+    fn on_event(&mut self, event: Event<'_>, depth: usize, frame: &mut CanvasFrame) -> Result<(), StreamError> {
+        match (event, depth) {
+            (Event::Key(key), 1) => {
+                self.field = match key {
+                    "width" => CanvasField::Width,
+                    "height" => CanvasField::Height,
+                    "pixels" => CanvasField::Pixels,
+                    _ => CanvasField::Other,
+                };
+            }
+            (Event::Number(number), 1) => {
+                let value = number.parse::<u32>().map_err(|_| StreamError::InvalidNumber)?;
+                match self.field {
+                    CanvasField::Width => frame.width = value,
+                    CanvasField::Height => frame.height = value,
+                    _ => {}
+                }
+            }
+            // A row starts
+            (Event::BeginArray, 3) if self.field == CanvasField::Pixels => self.col = 0,
+            (Event::EndArray, 3) if self.field == CanvasField::Pixels => self.row += 1,
+            (Event::Str(color), 3) if self.field == CanvasField::Pixels => {
+                let color = Rgb::parse_hex(color).map_err(|_| StreamError::InvalidColor)?;
+                // Pixels outside our fixed-size frame are dropped, like the buffered parser would refuse them
+                frame.set(self.col, self.row, color);
+                self.col += 1;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+// Streaming parser for a full GET /canvas HTTP response
+// Pixels are written into the frame as soon as they arrive, so after an error the frame holds
+// a mix of old and new pixels (callers should treat it as stale and fetch again)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanvasStream {
+    http: ResponseParser,
+    json: JsonTokenizer,
+    sink: CanvasSink,
+}
+
+impl Default for CanvasStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CanvasStream {
+    pub fn new() -> Self {
+        CanvasStream {
+            http: ResponseParser::new(),
+            json: JsonTokenizer::new(),
+            sink: CanvasSink { field: CanvasField::Other, row: 0, col: 0 },
+        }
+    }
+
+    // Feed the next chunk of the response, in the order it came off the socket
+    pub fn feed(&mut self, chunk: &[u8], frame: &mut CanvasFrame) -> Result<(), StreamError> {
+        let json = &mut self.json;
+        let sink = &mut self.sink;
+        self.http
+            .feed(chunk, |body| json.feed(body, |event, depth| sink.on_event(event, depth, frame)))
+            .map(|_| ())
+    }
+
+    // True once the whole response has arrived, so there's no need to wait for more (see http.rs)
+    pub fn is_complete(&self) -> bool {
+        self.http.is_complete()
+    }
+
+    // Whether the connection can be reused for the next request
+    pub fn keep_alive(&self) -> bool {
+        self.http.keep_alive()
+    }
+
+    // Call once the response is complete or the connection has closed, fails if it was cut short
+    pub fn finish(&mut self) -> Result<(), StreamError> {
+        self.http.finish()?;
+        if self.json.is_complete() {
+            Ok(())
+        } else {
+            Err(StreamError::Incomplete)
+        }
+    }
+}
@@ -0,0 +1,251 @@
+// src/captive.rs
+
+// The two tiny servers that make the setup network usable: DHCP hands out addresses, DNS points every name at us
+
+// For your knowledge
+// A phone joining the board's setup network (portal.rs) expects DHCP to give it an address. smoltcp only has
+// a DHCP client, so DhcpServer answers DISCOVER/REQUEST itself from a handful of addresses after our own
+// Then dns_reply() answers every A query with the board's address, whatever name was asked for. That sends the
+// phone's connectivity check (and any URL typed in) to our web server, which is what pops up the setup page
+// Both work on raw UDP payloads, so they are tested on the host with captured packets
+
+use core::net::Ipv4Addr;
+
+pub const DHCP_SERVER_PORT: u16 = 67;
+pub const DHCP_CLIENT_PORT: u16 = 68;
+pub const DNS_PORT: u16 = 53;
+
+// Phones only stay on the setup network for a few minutes, a handful of addresses is plenty
+pub const MAX_LEASES: usize = 4;
+const LEASE_SECS: u32 = 3600;
+
+// Replies are padded to the minimum BOOTP size, some clients ignore anything shorter
+pub const DHCP_REPLY_LEN: usize = 300;
+
+const BOOTP_REQUEST: u8 = 1;
+const BOOTP_REPLY: u8 = 2;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+const OPTIONS_START: usize = 240;
+
+const DHCP_DISCOVER: u8 = 1;
+const DHCP_OFFER: u8 = 2;
+const DHCP_REQUEST: u8 = 3;
+const DHCP_ACK: u8 = 5;
+const DHCP_NAK: u8 = 6;
+const DHCP_RELEASE: u8 = 7;
+
+const OPT_SUBNET_MASK: u8 = 1;
+const OPT_ROUTER: u8 = 3;
+const OPT_DNS: u8 = 6;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_LEASE_TIME: u8 = 51;
+const OPT_MESSAGE_TYPE: u8 = 53;
+const OPT_SERVER_ID: u8 = 54;
+const OPT_END: u8 = 255;
+const OPT_PAD: u8 = 0;
+
+// Hands out server_ip + 1 ..= server_ip + MAX_LEASES on a /24 network, with us as router and DNS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DhcpServer {
+    server_ip: Ipv4Addr,
+    // MAC address holding each address
+    leases: [Option<[u8; 6]>; MAX_LEASES],
+    // When every address is taken, the next one to give away
+    next_evict: usize,
+}
+
+impl DhcpServer {
+    pub fn new(server_ip: Ipv4Addr) -> Self {
+        DhcpServer { server_ip, leases: [None; MAX_LEASES], next_evict: 0 }
+    }
+
+    // Address handed out for lease slot 'index'
+    pub fn lease_ip(&self, index: usize) -> Ipv4Addr {
+        let [a, b, c, d] = self.server_ip.octets();
+        Ipv4Addr::new(a, b, c, d.wrapping_add(1 + index as u8))
+    }
+
+    // Handle one packet received on port 67. Returns the length of the reply written to 'reply'
+    // (to be broadcast to port 68), or None if there is nothing to send
+    pub fn handle(&mut self, request: &[u8], reply: &mut [u8; DHCP_REPLY_LEN]) -> Option<usize> {
+        if request.len() < OPTIONS_START || request[0] != BOOTP_REQUEST || request[236..240] != MAGIC_COOKIE {
+            return None;
+        }
+        // Only Ethernet (htype 1, 6-byte addresses), which is what Wi-Fi clients use
+        if request[1] != 1 || request[2] != 6 {
+            return None;
+        }
+        let mut mac = [0u8; 6];
+        mac.copy_from_slice(&request[28..34]);
+
+        let message_type = find_option(request, OPT_MESSAGE_TYPE)?.first().copied()?;
+        let reply_type = match message_type {
+            DHCP_DISCOVER => DHCP_OFFER,
+            DHCP_REQUEST => {
+                let requested = find_option(request, OPT_REQUESTED_IP)
+                    .filter(|ip| ip.len() == 4)
+                    .map(|ip| Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]))
+                    .unwrap_or(Ipv4Addr::new(request[12], request[13], request[14], request[15]));
+                // A client asking for an address we didn't give it (e.g. from another network) is told no,
+                // and starts again with a DISCOVER
+                match self.lease_for(&mac) {
+                    Some(index) if self.lease_ip(index) == requested => DHCP_ACK,
+                    _ => DHCP_NAK,
+                }
+            }
+            DHCP_RELEASE => {
+                if let Some(index) = self.lease_for(&mac) {
+                    self.leases[index] = None;
+                }
+                return None;
+            }
+            _ => return None,
+        };
+
+        let your_ip = if reply_type == DHCP_NAK {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            let index = self.allocate(mac);
+            self.lease_ip(index)
+        };
+        Some(self.write_reply(request, reply_type, your_ip, reply))
+    }
+
+    fn lease_for(&self, mac: &[u8; 6]) -> Option<usize> {
+        self.leases.iter().position(|lease| lease.as_ref() == Some(mac))
+    }
+
+    fn allocate(&mut self, mac: [u8; 6]) -> usize {
+        if let Some(index) = self.lease_for(&mac) {
+            return index;
+        }
+        let index = match self.leases.iter().position(|lease| lease.is_none()) {
+            Some(index) => index,
+            None => {
+                let index = self.next_evict;
+                self.next_evict = (self.next_evict + 1) % MAX_LEASES;
+                index
+            }
+        };
+        self.leases[index] = Some(mac);
+        index
+    }
+
+    fn write_reply(&self, request: &[u8], reply_type: u8, your_ip: Ipv4Addr, reply: &mut [u8; DHCP_REPLY_LEN]) -> usize {
+        reply.fill(0);
+        reply[0] = BOOTP_REPLY;
+        reply[1] = 1;
+        reply[2] = 6;
+        // Transaction id, flags and client hardware address are echoed back
+        reply[4..8].copy_from_slice(&request[4..8]);
+        reply[10..12].copy_from_slice(&request[10..12]);
+        reply[16..20].copy_from_slice(&your_ip.octets());
+        reply[20..24].copy_from_slice(&self.server_ip.octets());
+        reply[28..44].copy_from_slice(&request[28..44]);
+        reply[236..240].copy_from_slice(&MAGIC_COOKIE);
+
+        let server = self.server_ip.octets();
+        let mut options = OptionWriter { buf: reply, pos: OPTIONS_START };
+        options.put(OPT_MESSAGE_TYPE, &[reply_type]);
+        options.put(OPT_SERVER_ID, &server);
+        if reply_type != DHCP_NAK {
+            options.put(OPT_LEASE_TIME, &LEASE_SECS.to_be_bytes());
+            options.put(OPT_SUBNET_MASK, &[255, 255, 255, 0]);
+            options.put(OPT_ROUTER, &server);
+            options.put(OPT_DNS, &server);
+        }
+        options.buf[options.pos] = OPT_END;
+        DHCP_REPLY_LEN
+    }
+}
+
+struct OptionWriter<'a> {
+    buf: &'a mut [u8; DHCP_REPLY_LEN],
+    pos: usize,
+}
+
+impl OptionWriter<'_> {
+    fn put(&mut self, code: u8, data: &[u8]) {
+        self.buf[self.pos] = code;
+        self.buf[self.pos + 1] = data.len() as u8;
+        self.buf[self.pos + 2..self.pos + 2 + data.len()].copy_from_slice(data);
+        self.pos += 2 + data.len();
+    }
+}
+
+// The data of option 'code' in a DHCP packet
+fn find_option(packet: &[u8], code: u8) -> Option<&[u8]> {
+    let mut pos = OPTIONS_START;
+    while pos < packet.len() {
+        match packet[pos] {
+            OPT_PAD => pos += 1,
+            OPT_END => return None,
+            found => {
+                let len = *packet.get(pos + 1)? as usize;
+                let data = packet.get(pos + 2..pos + 2 + len)?;
+                if found == code {
+                    return Some(data);
+                }
+                pos += 2 + len;
+            }
+        }
+    }
+    None
+}
+
+// Answer a DNS query (the UDP payload received on port 53) with 'answer_ip' for every A question
+// Other query types get an empty answer. Returns the reply length, or None for packets we can't answer
+pub fn dns_reply(query: &[u8], answer_ip: Ipv4Addr, reply: &mut [u8]) -> Option<usize> {
+    const HEADER_LEN: usize = 12;
+    const TYPE_A: [u8; 2] = [0, 1];
+    const CLASS_IN: [u8; 2] = [0, 1];
+
+    // Must be a standard query (QR = 0, opcode 0) with exactly one question
+    if query.len() < HEADER_LEN || query[2] & 0xF8 != 0 || query[4..6] != [0, 1] {
+        return None;
+    }
+
+    // Skip the name (length-prefixed labels ending in a zero byte), then type and class
+    let mut pos = HEADER_LEN;
+    loop {
+        let len = *query.get(pos)? as usize;
+        // Compression pointers aren't allowed in the only question of a query
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        pos += 1 + len;
+        if len == 0 {
+            break;
+        }
+    }
+    let question_end = pos + 4;
+    let question = query.get(HEADER_LEN..question_end)?;
+    let is_a = question[question.len() - 4..question.len() - 2] == TYPE_A
+        && question[question.len() - 2..] == CLASS_IN;
+
+    let answer_len = if is_a { 16 } else { 0 };
+    let reply = reply.get_mut(..question_end + answer_len)?;
+
+    // Header: same id, response + recursion desired (copied) + recursion available, no error
+    reply[0..2].copy_from_slice(&query[0..2]);
+    reply[2] = 0x80 | (query[2] & 0x01);
+    reply[3] = 0x80;
+    reply[4..6].copy_from_slice(&[0, 1]);
+    reply[6..8].copy_from_slice(&[0, is_a as u8]);
+    // No authority or additional records (any EDNS record in the query is dropped)
+    reply[8..12].fill(0);
+    reply[HEADER_LEN..question_end].copy_from_slice(question);
+
+    if is_a {
+        let answer = &mut reply[question_end..];
+        // Name is a pointer back to the question at offset 12
+        answer[0..2].copy_from_slice(&[0xC0, HEADER_LEN as u8]);
+        answer[2..4].copy_from_slice(&TYPE_A);
+        answer[4..6].copy_from_slice(&CLASS_IN);
+        // Short TTL so nothing remembers our fake answers once the board has joined the real network
+        answer[6..10].copy_from_slice(&60u32.to_be_bytes());
+        answer[10..12].copy_from_slice(&4u16.to_be_bytes());
+        answer[12..16].copy_from_slice(&answer_ip.octets());
+    }
+    Some(reply.len())
+}
@@ -0,0 +1,85 @@
+// src/color.rs
+
+// Colour handling between the wire format ("#RRGGBB") and what the LEDs actually need
+
+// For your knowledge
+// LEDs respond linearly to the value we send, but our eyes don't: without gamma correction
+// dark colours look washed out and everything above half brightness looks the same
+// GAMMA8 maps a perceptual 0-255 value to the linear LED value (gamma 2.8, the usual choice for WS2812)
+// Global brightness then scales every channel down, which also keeps 512 LEDs inside the power supply's limits
+
+pub use protocol::{ParseColorError, Rgb};
+
+#[rustfmt::skip]
+pub const GAMMA8: [u8; 256] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   1,   1,   1,   1,
+      1,   1,   1,   1,   1,   1,   1,   1,   1,   2,   2,   2,   2,   2,   2,   2,
+      2,   3,   3,   3,   3,   3,   3,   3,   4,   4,   4,   4,   4,   5,   5,   5,
+      5,   6,   6,   6,   6,   7,   7,   7,   7,   8,   8,   8,   9,   9,   9,  10,
+     10,  10,  11,  11,  11,  12,  12,  13,  13,  13,  14,  14,  15,  15,  16,  16,
+     17,  17,  18,  18,  19,  19,  20,  20,  21,  21,  22,  22,  23,  24,  24,  25,
+     25,  26,  27,  27,  28,  29,  29,  30,  31,  32,  32,  33,  34,  35,  35,  36,
+     37,  38,  39,  39,  40,  41,  42,  43,  44,  45,  46,  47,  48,  49,  50,  50,
+     51,  52,  54,  55,  56,  57,  58,  59,  60,  61,  62,  63,  64,  66,  67,  68,
+     69,  70,  72,  73,  74,  75,  77,  78,  79,  81,  82,  83,  85,  86,  87,  89,
+     90,  92,  93,  95,  96,  98,  99, 101, 102, 104, 105, 107, 109, 110, 112, 114,
+    115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137, 138, 140, 142,
+    144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213,
+    215, 218, 220, 223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+// Parse "#RRGGBB" (either case)
+pub fn parse_hex(s: &str) -> Result<Rgb, ParseColorError> {
+    Rgb::parse_hex(s)
+}
+
+// 16-bit 5:6:5, used by most SPI TFT/OLED controllers
+pub fn to_rgb565(color: Rgb) -> u16 {
+    ((color.r as u16 >> 3) << 11) | ((color.g as u16 >> 2) << 5) | (color.b as u16 >> 3)
+}
+
+// 12-bit 4:4:4 in the low bits, used by cheap HUB75 drivers with 4-bit BCM
+pub fn to_rgb444(color: Rgb) -> u16 {
+    ((color.r as u16 >> 4) << 8) | ((color.g as u16 >> 4) << 4) | (color.b as u16 >> 4)
+}
+
+// Per-channel PWM duty cycles for a timer with 'bits' of resolution (1..=16), e.g. 10 bits -> 0..=1023
+pub fn to_pwm_duty(color: Rgb, bits: u8) -> [u16; 3] {
+    let bits = bits.clamp(1, 16) as u32;
+    let max = (1u32 << bits) - 1;
+    let scale = |channel: u8| ((channel as u32 * max + 127) / 255) as u16;
+    [scale(color.r), scale(color.g), scale(color.b)]
+}
+
+// Gamma and brightness applied to every pixel just before it is sent to the LEDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorCorrection {
+    // 0 = off, 255 = full power
+    pub brightness: u8,
+    pub gamma: bool,
+}
+
+impl Default for ColorCorrection {
+    // No change at all, so drivers behave exactly as before unless configured
+    fn default() -> Self {
+        ColorCorrection { brightness: 255, gamma: false }
+    }
+}
+
+impl ColorCorrection {
+    pub const fn new(brightness: u8, gamma: bool) -> Self {
+        ColorCorrection { brightness, gamma }
+    }
+
+    pub fn apply(&self, color: Rgb) -> Rgb {
+        let channel = |value: u8| {
+            let value = if self.gamma { GAMMA8[value as usize] } else { value };
+            // (value * brightness) / 255, rounded, without division on the hot path
+            let scaled = value as u32 * self.brightness as u32 + 128;
+            ((scaled + (scaled >> 8)) >> 8) as u8
+        };
+        Rgb::new(channel(color.r), channel(color.g), channel(color.b))
+    }
+}
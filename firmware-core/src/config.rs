@@ -0,0 +1,251 @@
+// src/config.rs
+
+// The settings a board needs to get online, entered once in the setup portal (portal.rs) and kept in flash
+
+// For your knowledge
+// In flash the config is a fixed-size record: magic, version, each string as a length byte plus a fixed-size
+// field, then a CRC32 of everything before it. Fixed sizes mean no allocator is needed, and the CRC catches
+// half-written records. Erased flash reads back as all 0xFF, which is how we spot a board that was never set up
+// The server can be an IP address, a hostname (resolved with DNS, or mDNS for ".local" names) or left empty,
+// in which case the board looks for a backend advertising itself on the LAN (see discovery.rs)
+
+use core::fmt;
+use core::net::Ipv4Addr;
+use heapless::String;
+
+pub const MAX_SSID_LEN: usize = 32;
+// WPA2 passphrases are 8 to 63 characters, a raw key is 64 hex digits
+pub const MAX_PASSWORD_LEN: usize = 64;
+pub const MAX_SERVER_LEN: usize = 64;
+
+const MAGIC: [u8; 4] = *b"RCFG";
+const VERSION: u8 = 1;
+
+pub const CONFIG_RECORD_LEN: usize = MAGIC.len() + 1 + (1 + MAX_SSID_LEN) + (1 + MAX_PASSWORD_LEN) + (1 + MAX_SERVER_LEN) + 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    // Nothing has been saved yet
+    Erased,
+    BadMagic,
+    UnsupportedVersion(u8),
+    // The record was damaged or only partly written
+    BadChecksum,
+    // Checksum is fine but the contents make no sense (lengths out of range, not UTF-8)
+    Corrupt,
+    MissingSsid,
+    SsidTooLong,
+    // Must be empty (open network) or 8 to 64 characters
+    BadPassword,
+    ServerTooLong,
+    // Not "http://<ip or hostname>[:port]"
+    BadServer,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Erased => write!(f, "no configuration saved"),
+            ConfigError::BadMagic => write!(f, "flash doesn't hold a configuration"),
+            ConfigError::UnsupportedVersion(v) => write!(f, "configuration version {} is not supported", v),
+            ConfigError::BadChecksum => write!(f, "configuration is damaged"),
+            ConfigError::Corrupt => write!(f, "configuration is corrupt"),
+            ConfigError::MissingSsid => write!(f, "Wi-Fi network name is required"),
+            ConfigError::SsidTooLong => write!(f, "Wi-Fi network name is longer than 32 characters"),
+            ConfigError::BadPassword => write!(f, "Wi-Fi password must be empty or 8 to 64 characters"),
+            ConfigError::ServerTooLong => write!(f, "server address is too long"),
+            ConfigError::BadServer => write!(f, "server must look like http://192.168.1.10:8080 or http://canvas.local:8080"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerHost {
+    Ip(Ipv4Addr),
+    // Needs a lookup before we can connect
+    Name(String<MAX_SERVER_LEN>),
+}
+
+impl ServerHost {
+    // Names under ".local" are answered by the machine itself over mDNS rather than by a DNS server
+    pub fn is_mdns(&self) -> bool {
+        match self {
+            ServerHost::Ip(_) => false,
+            ServerHost::Name(name) => {
+                let name = name.strip_suffix('.').unwrap_or(name);
+                name.len() > 6 && name[name.len() - 6..].eq_ignore_ascii_case(".local")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ServerHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerHost::Ip(ip) => write!(f, "{}", ip),
+            ServerHost::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+// Where the backend lives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerAddress {
+    pub host: ServerHost,
+    pub port: u16,
+}
+
+impl ServerAddress {
+    // Accepts "http://192.168.2.169:8080", "canvas.local:8080", "192.168.2.169" (port 80) and so on
+    pub fn parse(url: &str) -> Result<Self, ConfigError> {
+        let rest = url.trim();
+        let rest = rest.strip_prefix("http://").unwrap_or(rest);
+        let rest = rest.strip_suffix('/').unwrap_or(rest);
+
+        let (host, port) = match rest.split_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| ConfigError::BadServer)?),
+            None => (rest, 80),
+        };
+        if port == 0 {
+            return Err(ConfigError::BadServer);
+        }
+        let host = match host.parse() {
+            Ok(ip) => ServerHost::Ip(ip),
+            Err(_) if is_hostname(host) => {
+                ServerHost::Name(String::try_from(host).map_err(|_| ConfigError::ServerTooLong)?)
+            }
+            Err(_) => return Err(ConfigError::BadServer),
+        };
+        Ok(ServerAddress { host, port })
+    }
+}
+
+// "host:port", which is also what the Host header wants
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+// Letters, digits and '-' in dot-separated labels of up to 63 characters, like DNS wants
+// The last label can't be all digits, so a mistyped IP address ("192.168.2") isn't taken for a name
+fn is_hostname(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    let labels_ok = host.split('.').all(|label| {
+        (1..=63).contains(&label.len())
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    });
+    let last = host.rsplit('.').next().unwrap_or("");
+    labels_ok && !last.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceConfig {
+    pub ssid: String<MAX_SSID_LEN>,
+    pub password: String<MAX_PASSWORD_LEN>,
+    // As entered, checked with ServerAddress::parse(). Empty means "find it on the LAN"
+    pub server: String<MAX_SERVER_LEN>,
+}
+
+impl DeviceConfig {
+    // Validates everything, so a saved config is always usable
+    pub fn new(ssid: &str, password: &str, server: &str) -> Result<Self, ConfigError> {
+        if ssid.is_empty() {
+            return Err(ConfigError::MissingSsid);
+        }
+        if !(password.is_empty() || (8..=MAX_PASSWORD_LEN).contains(&password.len())) {
+            return Err(ConfigError::BadPassword);
+        }
+        let server = server.trim();
+        if !server.is_empty() {
+            ServerAddress::parse(server)?;
+        }
+
+        Ok(DeviceConfig {
+            ssid: String::try_from(ssid).map_err(|_| ConfigError::SsidTooLong)?,
+            password: String::try_from(password).map_err(|_| ConfigError::BadPassword)?,
+            server: String::try_from(server).map_err(|_| ConfigError::ServerTooLong)?,
+        })
+    }
+
+    // None when the server should be discovered with mDNS
+    pub fn server_address(&self) -> Result<Option<ServerAddress>, ConfigError> {
+        if self.server.is_empty() {
+            return Ok(None);
+        }
+        ServerAddress::parse(&self.server).map(Some)
+    }
+
+    // The bytes to write to flash
+    pub fn to_record(&self) -> [u8; CONFIG_RECORD_LEN] {
+        let mut record = [0u8; CONFIG_RECORD_LEN];
+        record[..MAGIC.len()].copy_from_slice(&MAGIC);
+        record[MAGIC.len()] = VERSION;
+
+        let mut pos = MAGIC.len() + 1;
+        for (field, capacity) in [
+            (self.ssid.as_str(), MAX_SSID_LEN),
+            (self.password.as_str(), MAX_PASSWORD_LEN),
+            (self.server.as_str(), MAX_SERVER_LEN),
+        ] {
+            record[pos] = field.len() as u8;
+            record[pos + 1..pos + 1 + field.len()].copy_from_slice(field.as_bytes());
+            pos += 1 + capacity;
+        }
+
+        let crc = crc32(&record[..pos]);
+        record[pos..pos + 4].copy_from_slice(&crc.to_le_bytes());
+        record
+    }
+
+    // Read back a record written by to_record()
+    pub fn from_record(record: &[u8]) -> Result<Self, ConfigError> {
+        let record = record.get(..CONFIG_RECORD_LEN).ok_or(ConfigError::Corrupt)?;
+        if record.iter().all(|&b| b == 0xFF) {
+            return Err(ConfigError::Erased);
+        }
+        if record[..MAGIC.len()] != MAGIC {
+            return Err(ConfigError::BadMagic);
+        }
+        if record[MAGIC.len()] != VERSION {
+            return Err(ConfigError::UnsupportedVersion(record[MAGIC.len()]));
+        }
+
+        let crc_at = CONFIG_RECORD_LEN - 4;
+        let stored = u32::from_le_bytes([record[crc_at], record[crc_at + 1], record[crc_at + 2], record[crc_at + 3]]);
+        if crc32(&record[..crc_at]) != stored {
+            return Err(ConfigError::BadChecksum);
+        }
+
+        let mut pos = MAGIC.len() + 1;
+        let mut field = |capacity: usize| -> Result<&str, ConfigError> {
+            let len = record[pos] as usize;
+            if len > capacity {
+                return Err(ConfigError::Corrupt);
+            }
+            let text = core::str::from_utf8(&record[pos + 1..pos + 1 + len]).map_err(|_| ConfigError::Corrupt)?;
+            pos += 1 + capacity;
+            Ok(text)
+        };
+        let ssid = field(MAX_SSID_LEN)?;
+        let password = field(MAX_PASSWORD_LEN)?;
+        let server = field(MAX_SERVER_LEN)?;
+
+        DeviceConfig::new(ssid, password, server).map_err(|_| ConfigError::Corrupt)
+    }
+}
+
+// CRC-32 (IEEE, as used by zip and Ethernet), bit by bit since it only runs at boot and on save
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,134 @@
+// src/device.rs
+
+// Reporting in to the backend: registering after boot, then a heartbeat every so often
+
+// For your knowledge
+// The backend keeps a list of boards (GET /devices) so you can tell which displays are up without walking over to them
+// Once online the board POSTs a protocol::DeviceReport to /devices/register, then the same report to
+// /devices/heartbeat every HEARTBEAT_INTERVAL_SECS. The firmware asks Reporter what's due between polls
+// A heartbeat answered with 404 means the server doesn't know us (its database was wiped), so we register again
+// A report that fails for any other reason waits for the next interval, the display matters more than the list
+// The reply can carry a test pattern an admin queued for this board (see patterns.rs)
+
+use core::fmt::{self, Write};
+use heapless::String;
+use protocol::TestPattern;
+use serde::{Deserialize, Serialize};
+
+use crate::http::write_post_request;
+
+pub use protocol::HEARTBEAT_INTERVAL_SECS;
+
+pub const REGISTER_PATH: &str = "/devices/register";
+pub const HEARTBEAT_PATH: &str = "/devices/heartbeat";
+
+// "rustycanvas-" and 12 hex digits
+pub const MAX_DEVICE_ID_LEN: usize = 24;
+pub const MAX_FIRMWARE_VERSION_LEN: usize = 32;
+
+// Longest report body, and the whole request around it
+pub const MAX_REPORT_BODY_LEN: usize = 192;
+// Longest reply body we read (the replies are only a few fields)
+pub const MAX_REPLY_LEN: usize = 256;
+pub const MAX_REPORT_REQUEST_LEN: usize = 512;
+
+const HEARTBEAT_INTERVAL_MS: u64 = HEARTBEAT_INTERVAL_SECS * 1000;
+
+// Fixed-size version of protocol::DeviceReport
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceReport {
+    pub device_id: String<MAX_DEVICE_ID_LEN>,
+    pub firmware_version: String<MAX_FIRMWARE_VERSION_LEN>,
+    pub uptime_secs: u64,
+    pub rssi: Option<i32>,
+    pub last_applied: u64,
+}
+
+// Fixed-size version of protocol::DeviceReportResponse, only what the board acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+struct ReportReply {
+    #[serde(default)]
+    test_pattern: Option<TestPattern>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    Register,
+    Heartbeat,
+}
+
+impl ReportKind {
+    pub fn path(self) -> &'static str {
+        match self {
+            ReportKind::Register => REGISTER_PATH,
+            ReportKind::Heartbeat => HEARTBEAT_PATH,
+        }
+    }
+}
+
+// A stable ID for the board, made from its Wi-Fi MAC address, e.g. "rustycanvas-a1b2c3d4e5f6"
+pub fn device_id(mac: [u8; 6]) -> String<MAX_DEVICE_ID_LEN> {
+    let mut id = String::new();
+    let _ = id.push_str("rustycanvas-");
+    for byte in mac {
+        let _ = write!(id, "{:02x}", byte);
+    }
+    id
+}
+
+// Write the POST request for a report. Fails if the request doesn't fit in 'out'
+pub fn write_report_request<W: Write>(out: &mut W, host: &str, kind: ReportKind, report: &DeviceReport) -> fmt::Result {
+    let mut body = [0u8; MAX_REPORT_BODY_LEN];
+    let len = serde_json_core::to_slice(report, &mut body).map_err(|_| fmt::Error)?;
+    let body = core::str::from_utf8(&body[..len]).map_err(|_| fmt::Error)?;
+    write_post_request(out, host, kind.path(), body, true)
+}
+
+// The test pattern in a 200 reply, None if there isn't one (or the reply can't be read)
+pub fn parse_report_reply(body: &[u8]) -> Option<TestPattern> {
+    serde_json_core::from_slice::<ReportReply>(body).ok()?.0.test_pattern
+}
+
+// When to report, and which kind of report is next
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Reporter {
+    registered: bool,
+    // Uptime (ms) of the next report, None means as soon as we're online
+    next_due_ms: Option<u64>,
+}
+
+impl Reporter {
+    pub fn new() -> Self {
+        Reporter { registered: false, next_due_ms: None }
+    }
+
+    pub fn is_registered(&self) -> bool {
+        self.registered
+    }
+
+    // The report to send now, if one is due ('now_ms' is the board's uptime)
+    pub fn due(&self, now_ms: u64) -> Option<ReportKind> {
+        if self.next_due_ms.is_some_and(|due| now_ms < due) {
+            return None;
+        }
+        Some(if self.registered { ReportKind::Heartbeat } else { ReportKind::Register })
+    }
+
+    // The server answered a report with 'status'
+    // A report that never got an answer (the connection dropped) isn't recorded, it's sent again once we reconnect
+    pub fn on_response(&mut self, kind: ReportKind, status: u16, now_ms: u64) {
+        match (kind, status) {
+            (ReportKind::Register, 200) => {
+                self.registered = true;
+                self.next_due_ms = Some(now_ms + HEARTBEAT_INTERVAL_MS);
+            }
+            // The server has forgotten us, register straight away
+            (ReportKind::Heartbeat, 404) => {
+                self.registered = false;
+                self.next_due_ms = None;
+            }
+            // Sent, or refused for a reason trying again straight away won't fix (e.g. a server without the route)
+            _ => self.next_due_ms = Some(now_ms + HEARTBEAT_INTERVAL_MS),
+        }
+    }
+}
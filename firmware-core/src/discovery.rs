@@ -0,0 +1,251 @@
+// src/discovery.rs
+
+// Finding the backend: DNS lookups for a configured hostname, and mDNS/DNS-SD when no server is configured
+
+// For your knowledge
+// The board only has raw UDP, so this is a tiny DNS client that writes queries and reads answers
+// A hostname is looked up with an A query, sent to the DNS server DHCP gave us (or to the mDNS group for
+// ".local" names). With no server configured, we ask the mDNS group for PTR records of protocol::MDNS_SERVICE_TYPE,
+// which the backend advertises. The answer names an instance, its SRV record gives host and port, and the
+// host's A record (normally sent along in the same packet) gives the address
+// Queries are sent from a port other than 5353, which makes them "one-shot" mDNS queries (RFC 6762 section 5.1):
+// the responder replies straight to us, so we don't need to join the multicast group
+
+use crate::config::MAX_SERVER_LEN;
+use core::net::Ipv4Addr;
+use heapless::String;
+
+pub use protocol::MDNS_SERVICE_TYPE;
+
+pub const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+pub const MDNS_PORT: u16 = 5353;
+
+// Big enough for a query for any name we accept (header + name + type and class)
+pub const MAX_QUERY_LEN: usize = HEADER_LEN + MAX_SERVER_LEN + 2 + 4;
+
+const HEADER_LEN: usize = 12;
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+// Names can point back into the packet, this stops a malicious packet looping forever
+const MAX_POINTERS: usize = 16;
+// Longest name we compare (instance names are often longer than host names)
+const MAX_NAME_LEN: usize = 128;
+
+// What an mDNS service answer told us
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceAnswer {
+    // The SRV target, e.g. "laptop.local"
+    pub host: String<MAX_SERVER_LEN>,
+    pub port: u16,
+    // None if the responder didn't include the host's address, look it up with an A query
+    pub ip: Option<Ipv4Addr>,
+}
+
+// A query for the address of 'host'. Returns the query length
+// 'recursive' asks a DNS server to do the whole lookup, mDNS queries don't set it
+pub fn write_a_query(id: u16, host: &str, recursive: bool, out: &mut [u8]) -> Option<usize> {
+    write_query(id, host, TYPE_A, recursive, out)
+}
+
+// An mDNS query for every backend on the LAN
+pub fn write_service_query(id: u16, out: &mut [u8]) -> Option<usize> {
+    write_query(id, MDNS_SERVICE_TYPE, TYPE_PTR, false, out)
+}
+
+// The first address in an answer to query 'id'
+// Any CNAME records are skipped over, the resolver puts the final A record in the answer too
+pub fn parse_a_answer(packet: &[u8], id: u16) -> Option<Ipv4Addr> {
+    answer_records(packet, id)?
+        .find(|record| record.rtype == TYPE_A && record.data.len() == 4)
+        .map(|record| Ipv4Addr::new(record.data[0], record.data[1], record.data[2], record.data[3]))
+}
+
+// The first backend in an answer to service query 'id'
+pub fn parse_service_answer(packet: &[u8], id: u16) -> Option<ServiceAnswer> {
+    // Answer: _rustycanvas._tcp.local PTR <instance>
+    let instance = answer_records(packet, id)?
+        .find(|record| record.rtype == TYPE_PTR && name_eq(packet, record.name_at, MDNS_SERVICE_TYPE))?
+        .data_at;
+
+    // Additional records: <instance> SRV <port> <host>, then <host> A <ip>
+    let srv = answer_records(packet, id)?.find(|record| {
+        record.rtype == TYPE_SRV && record.data.len() > 6 && names_eq(packet, record.name_at, instance)
+    })?;
+    let port = u16::from_be_bytes([srv.data[4], srv.data[5]]);
+    let host_at = srv.data_at + 6;
+    let host = read_name(packet, host_at)?;
+
+    let ip = answer_records(packet, id)?
+        .find(|record| record.rtype == TYPE_A && record.data.len() == 4 && names_eq(packet, record.name_at, host_at))
+        .map(|record| Ipv4Addr::new(record.data[0], record.data[1], record.data[2], record.data[3]));
+
+    Some(ServiceAnswer { host, port, ip })
+}
+
+fn write_query(id: u16, name: &str, qtype: u16, recursive: bool, out: &mut [u8]) -> Option<usize> {
+    let header = out.get_mut(..HEADER_LEN)?;
+    header.fill(0);
+    header[0..2].copy_from_slice(&id.to_be_bytes());
+    if recursive {
+        header[2] = 0x01;
+    }
+    // One question
+    header[5] = 1;
+
+    let mut pos = HEADER_LEN;
+    for label in name.strip_suffix('.').unwrap_or(name).split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return None;
+        }
+        let dest = out.get_mut(pos..pos + 1 + label.len())?;
+        dest[0] = label.len() as u8;
+        dest[1..].copy_from_slice(label.as_bytes());
+        pos += 1 + label.len();
+    }
+    let tail = out.get_mut(pos..pos + 5)?;
+    tail[0] = 0;
+    tail[1..3].copy_from_slice(&qtype.to_be_bytes());
+    tail[3..5].copy_from_slice(&CLASS_IN.to_be_bytes());
+    Some(pos + 5)
+}
+
+struct Record<'a> {
+    // Offset of the owner name, for comparing names (which may be compressed)
+    name_at: usize,
+    rtype: u16,
+    data_at: usize,
+    data: &'a [u8],
+}
+
+// Every answer, authority and additional record of a successful response to query 'id'
+fn answer_records(packet: &[u8], id: u16) -> Option<Records<'_>> {
+    if packet.len() < HEADER_LEN || packet[0..2] != id.to_be_bytes() {
+        return None;
+    }
+    // Must be a response (QR set) with no error
+    if packet[2] & 0x80 == 0 || packet[3] & 0x0F != 0 {
+        return None;
+    }
+    let count = |at: usize| u16::from_be_bytes([packet[at], packet[at + 1]]);
+
+    let mut pos = HEADER_LEN;
+    for _ in 0..count(4) {
+        pos = skip_name(packet, pos)? + 4;
+    }
+    let remaining = count(6) as usize + count(8) as usize + count(10) as usize;
+    Some(Records { packet, pos, remaining })
+}
+
+struct Records<'a> {
+    packet: &'a [u8],
+    pos: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Record<'a>;
+
+    fn next(&mut self) -> Option<Record<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let name_at = self.pos;
+        let fixed_at = skip_name(self.packet, name_at)?;
+        // Type, class, TTL, data length
+        let fixed = self.packet.get(fixed_at..fixed_at + 10)?;
+        let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+        // mDNS uses the top bit of the class as the cache-flush flag
+        let class = u16::from_be_bytes([fixed[2], fixed[3]]) & 0x7FFF;
+        let data_len = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let data_at = fixed_at + 10;
+        let data = self.packet.get(data_at..data_at + data_len)?;
+        self.pos = data_at + data_len;
+
+        if class != CLASS_IN {
+            // Not an internet record, skip it but keep going
+            return self.next();
+        }
+        Some(Record { name_at, rtype, data_at, data })
+    }
+}
+
+// Position just after the name starting at 'pos'
+fn skip_name(packet: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *packet.get(pos)? as usize;
+        match len {
+            0 => return Some(pos + 1),
+            // A pointer ends the name
+            _ if len & 0xC0 == 0xC0 => return Some(pos + 2),
+            _ if len & 0xC0 != 0 => return None,
+            _ => pos += 1 + len,
+        }
+    }
+}
+
+// Calls 'f' with each label of the name at 'pos', following pointers. None if the name is malformed
+fn for_each_label<'a>(packet: &'a [u8], mut pos: usize, mut f: impl FnMut(&'a [u8]) -> bool) -> Option<bool> {
+    let mut pointers = 0;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some(true);
+        }
+        if len & 0xC0 == 0xC0 {
+            pointers += 1;
+            if pointers > MAX_POINTERS {
+                return None;
+            }
+            pos = ((len & 0x3F) << 8) | *packet.get(pos + 1)? as usize;
+            continue;
+        }
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        if !f(packet.get(pos + 1..pos + 1 + len)?) {
+            return Some(false);
+        }
+        pos += 1 + len;
+    }
+}
+
+// Does the name at 'pos' spell 'expected' (ignoring case)?
+fn name_eq(packet: &[u8], pos: usize, expected: &str) -> bool {
+    let mut expected = expected.strip_suffix('.').unwrap_or(expected).split('.');
+    let matched = for_each_label(packet, pos, |label| {
+        expected.next().is_some_and(|want| want.as_bytes().eq_ignore_ascii_case(label))
+    });
+    matched == Some(true) && expected.next().is_none()
+}
+
+// Do the names at 'a' and 'b' match (ignoring case)?
+fn names_eq(packet: &[u8], a: usize, b: usize) -> bool {
+    let mut name: String<MAX_NAME_LEN> = String::new();
+    match read_name_into(packet, a, &mut name) {
+        Some(()) => name_eq(packet, b, &name),
+        None => false,
+    }
+}
+
+// The name at 'pos' as "host.local", None if it's malformed or longer than a server address can be
+fn read_name(packet: &[u8], pos: usize) -> Option<String<MAX_SERVER_LEN>> {
+    let mut name = String::new();
+    read_name_into(packet, pos, &mut name)?;
+    Some(name)
+}
+
+fn read_name_into<const N: usize>(packet: &[u8], pos: usize, name: &mut String<N>) -> Option<()> {
+    let mut ok = true;
+    let complete = for_each_label(packet, pos, |label| {
+        let label = core::str::from_utf8(label).ok();
+        ok = label.is_some_and(|label| {
+            (name.is_empty() || name.push('.').is_ok()) && name.push_str(label).is_ok()
+        });
+        ok
+    })?;
+    (complete && ok).then_some(())
+}
@@ -0,0 +1,180 @@
+// src/display.rs
+
+// Driver for the 32x16 addressable LED matrix (WS2812B / "NeoPixel" chain)
+
+// For your knowledge
+// The matrix is one long chain of 512 LEDs. Each LED takes 24 bits (green, red, blue) and passes the rest on
+// Bits are sent as pulse widths: a '1' is a long high pulse, a '0' a short one. Holding the line low latches the frame
+// We generate those pulses with SPI at 2.4 MHz: every LED bit becomes 3 SPI bits, '110' for 1 and '100' for 0
+// That only needs an embedded-hal SpiBus, so the driver is identical on the ESP32 and in host tests
+//
+// LedMatrix double buffers: set_pixel() draws into the back buffer, show() sends the back buffer to the chain
+// and copies it to the front buffer (what the LEDs are displaying). Nothing is sent if the frame didn't change
+// Colour correction (color.rs) is applied only while sending, so the buffers always hold the canvas colours
+
+use crate::color::ColorCorrection;
+use crate::render::Panel;
+use embedded_hal::spi::SpiBus;
+use protocol::frame::{FRAME_HEIGHT, FRAME_WIDTH};
+use protocol::Rgb;
+
+pub const LED_COUNT: usize = FRAME_WIDTH * FRAME_HEIGHT;
+
+// SPI clock the pulse encoding is designed for (3 SPI bits per 1.25 us LED bit)
+pub const WS2812_SPI_HZ: u32 = 2_400_000;
+
+// 3 SPI bytes per colour byte, 3 colour bytes per LED
+const BYTES_PER_LED: usize = 9;
+// >= 280 us of low line to latch (newer WS2812B parts need more than the 50 us in old datasheets)
+const RESET_BYTES: usize = 90;
+pub const WS2812_FRAME_BYTES: usize = LED_COUNT * BYTES_PER_LED + RESET_BYTES;
+
+// How the chain snakes through the matrix, starting from the top-left LED
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wiring {
+    // Every row runs left to right
+    Progressive,
+    // Rows alternate direction (left to right, then right to left, ...), the usual flexible-matrix layout
+    Serpentine,
+}
+
+impl Wiring {
+    // Position of (x, y) along the chain
+    pub fn chain_index(self, x: usize, y: usize) -> usize {
+        match self {
+            Wiring::Serpentine if y % 2 == 1 => y * FRAME_WIDTH + (FRAME_WIDTH - 1 - x),
+            _ => y * FRAME_WIDTH + x,
+        }
+    }
+}
+
+// Anything that can push a full set of colours down the LED chain, in chain order
+pub trait LedChain {
+    type Error;
+
+    fn write(&mut self, leds: &[Rgb; LED_COUNT]) -> Result<(), Self::Error>;
+}
+
+// Encode one colour byte as 3 SPI bytes, most significant bit first
+pub fn encode_ws2812_byte(byte: u8) -> [u8; 3] {
+    let mut bits: u32 = 0;
+    for i in (0..8).rev() {
+        let pattern = if byte & (1 << i) != 0 { 0b110 } else { 0b100 };
+        bits = (bits << 3) | pattern;
+    }
+    [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8]
+}
+
+// WS2812B chain driven from the MOSI pin of an SPI bus running at WS2812_SPI_HZ
+pub struct Ws2812Spi<SPI> {
+    spi: SPI,
+    buffer: [u8; WS2812_FRAME_BYTES],
+}
+
+impl<SPI: SpiBus> Ws2812Spi<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        Ws2812Spi { spi, buffer: [0; WS2812_FRAME_BYTES] }
+    }
+
+    // The bytes sent by the last write() (for debugging and tests)
+    pub fn encoded(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl<SPI: SpiBus> LedChain for Ws2812Spi<SPI> {
+    type Error = SPI::Error;
+
+    fn write(&mut self, leds: &[Rgb; LED_COUNT]) -> Result<(), Self::Error> {
+        for (led, out) in leds.iter().zip(self.buffer.chunks_exact_mut(BYTES_PER_LED)) {
+            // WS2812 expects green first
+            out[0..3].copy_from_slice(&encode_ws2812_byte(led.g));
+            out[3..6].copy_from_slice(&encode_ws2812_byte(led.r));
+            out[6..9].copy_from_slice(&encode_ws2812_byte(led.b));
+        }
+        // The trailing RESET_BYTES stay zero: that's the latch
+
+        // Send it all in one transfer so there are no gaps between LEDs
+        self.spi.write(&self.buffer)?;
+        self.spi.flush()
+    }
+}
+
+pub struct LedMatrix<C> {
+    chain: C,
+    wiring: Wiring,
+    correction: ColorCorrection,
+    back: [[Rgb; FRAME_WIDTH]; FRAME_HEIGHT],
+    front: [[Rgb; FRAME_WIDTH]; FRAME_HEIGHT],
+    // Chain-ordered scratch space for the next write
+    ordered: [Rgb; LED_COUNT],
+    // Force the next show() to transmit even if the frame looks unchanged (e.g. right after power-on)
+    needs_refresh: bool,
+}
+
+impl<C: LedChain> LedMatrix<C> {
+    pub fn new(chain: C, wiring: Wiring) -> Self {
+        LedMatrix {
+            chain,
+            wiring,
+            correction: ColorCorrection::default(),
+            back: [[Rgb::BLACK; FRAME_WIDTH]; FRAME_HEIGHT],
+            front: [[Rgb::BLACK; FRAME_WIDTH]; FRAME_HEIGHT],
+            ordered: [Rgb::BLACK; LED_COUNT],
+            needs_refresh: true,
+        }
+    }
+
+    // What the LEDs are currently displaying
+    pub fn front(&self) -> &[[Rgb; FRAME_WIDTH]; FRAME_HEIGHT] {
+        &self.front
+    }
+
+    pub fn chain(&self) -> &C {
+        &self.chain
+    }
+
+    // Gamma/brightness applied on the way out, the buffers keep the original colours
+    pub fn set_correction(&mut self, correction: ColorCorrection) {
+        if correction != self.correction {
+            self.correction = correction;
+            self.needs_refresh = true;
+        }
+    }
+
+    // Fill the back buffer with one colour
+    pub fn clear(&mut self, color: Rgb) {
+        self.back = [[color; FRAME_WIDTH]; FRAME_HEIGHT];
+    }
+}
+
+impl<C: LedChain> Panel for LedMatrix<C> {
+    type Error = C::Error;
+
+    fn size(&self) -> (u32, u32) {
+        (FRAME_WIDTH as u32, FRAME_HEIGHT as u32)
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: Rgb) {
+        if let Some(pixel) = self.back.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)) {
+            *pixel = color;
+        }
+    }
+
+    fn show(&mut self) -> Result<(), Self::Error> {
+        if !self.needs_refresh && self.back == self.front {
+            return Ok(());
+        }
+
+        for (y, row) in self.back.iter().enumerate() {
+            for (x, color) in row.iter().enumerate() {
+                self.ordered[self.wiring.chain_index(x, y)] = self.correction.apply(*color);
+            }
+        }
+
+        self.chain.write(&self.ordered)?;
+        self.front = self.back;
+        self.needs_refresh = false;
+        Ok(())
+    }
+}
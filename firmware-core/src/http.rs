@@ -0,0 +1,254 @@
+// src/http.rs
+
+// A small HTTP/1.1 client for the firmware: request writing and an incremental response parser
+
+// For your knowledge
+// The response parser reads the status line and headers, then hands the body to a callback piece by piece
+// The body ends in one of three ways, decided by the headers:
+//  - 'Transfer-Encoding: chunked': hex size lines with data in between, a 0-size chunk ends it
+//  - 'Content-Length: n': exactly n bytes
+//  - neither: the server closes the connection (the only option in HTTP/1.0)
+// In the first two cases we know the response is complete without waiting for the server to hang up,
+// which is what makes keep-alive possible: the next request reuses the same TCP connection
+// Anything other than 200 is reported as an error as soon as the status line has been read
+
+use crate::stream::StreamError;
+use core::fmt;
+use heapless::Vec;
+
+// Longest status/header/chunk-size line we keep. Longer lines are cut short, which is fine
+// since the headers we care about (and chunk sizes) are short
+pub const MAX_LINE_LEN: usize = 128;
+
+// Write a GET request. 'host' should include the port if it isn't 80, e.g. "192.168.2.169:8080"
+pub fn write_get_request<W: fmt::Write>(out: &mut W, host: &str, path: &str, keep_alive: bool) -> fmt::Result {
+    let connection = if keep_alive { "keep-alive" } else { "close" };
+    write!(
+        out,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nConnection: {}\r\n\r\n",
+        path, host, connection
+    )
+}
+
+// Write a POST request with a JSON body, same 'host' as write_get_request
+pub fn write_post_request<W: fmt::Write>(out: &mut W, host: &str, path: &str, body: &str, keep_alive: bool) -> fmt::Result {
+    let connection = if keep_alive { "keep-alive" } else { "close" };
+    write!(
+        out,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n{}",
+        path,
+        host,
+        body.len(),
+        connection,
+        body
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    StatusLine,
+    Headers,
+    ChunkSize,
+    ChunkData { remaining: u64 },
+    // The \r\n after a chunk's data
+    ChunkEnd,
+    Trailers,
+    Body { remaining: u64 },
+    UntilClose,
+    Done,
+}
+
+// Incremental parser for one response
+// Feed it whatever the socket returns. Create a new one for each response on a kept-alive connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseParser {
+    state: State,
+    line: Vec<u8, MAX_LINE_LEN>,
+    status: Option<u16>,
+    content_length: Option<u64>,
+    chunked: bool,
+    keep_alive: bool,
+}
+
+impl Default for ResponseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseParser {
+    pub fn new() -> Self {
+        ResponseParser {
+            state: State::StatusLine,
+            line: Vec::new(),
+            status: None,
+            content_length: None,
+            chunked: false,
+            keep_alive: true,
+        }
+    }
+
+    // The status code, once the status line has been read
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    // 'Content-Length', if the server sent one
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    pub fn is_chunked(&self) -> bool {
+        self.chunked
+    }
+
+    // Whether the connection can carry another request once this response is complete
+    pub fn keep_alive(&self) -> bool {
+        self.keep_alive && self.state != State::UntilClose
+    }
+
+    // True once the whole body has been received (never true for bodies that end when the connection closes,
+    // use finish() for those)
+    pub fn is_complete(&self) -> bool {
+        self.state == State::Done
+    }
+
+    // Feed the next bytes off the socket. Body bytes are passed to 'on_body' (not necessarily all at once)
+    // Returns how many bytes of 'chunk' were used, anything after the end of the response is left alone
+    pub fn feed<F>(&mut self, chunk: &[u8], mut on_body: F) -> Result<usize, StreamError>
+    where
+        F: FnMut(&[u8]) -> Result<(), StreamError>,
+    {
+        let mut pos = 0;
+        while pos < chunk.len() {
+            let rest = &chunk[pos..];
+            match self.state {
+                State::Done => break,
+                State::UntilClose => {
+                    on_body(rest)?;
+                    pos = chunk.len();
+                }
+                State::Body { remaining } | State::ChunkData { remaining } => {
+                    let take = rest.len().min(usize::try_from(remaining).unwrap_or(usize::MAX));
+                    on_body(&rest[..take])?;
+                    pos += take;
+                    self.consume_body(take as u64);
+                }
+                _ => {
+                    // Line-based states, one byte at a time
+                    pos += 1;
+                    let byte = rest[0];
+                    if byte == b'\n' {
+                        self.end_line()?;
+                    } else if byte != b'\r' {
+                        // Over-long lines are truncated (see MAX_LINE_LEN)
+                        let _ = self.line.push(byte);
+                    }
+                }
+            }
+        }
+        Ok(pos)
+    }
+
+    // Call when no more bytes are coming (the server closed the connection)
+    // Only bodies that were supposed to end that way are complete
+    pub fn finish(&mut self) -> Result<(), StreamError> {
+        match self.state {
+            State::Done => Ok(()),
+            State::UntilClose => {
+                self.state = State::Done;
+                self.keep_alive = false;
+                Ok(())
+            }
+            _ => Err(StreamError::Incomplete),
+        }
+    }
+
+    fn consume_body(&mut self, taken: u64) {
+        self.state = match self.state {
+            State::Body { remaining } if remaining == taken => State::Done,
+            State::Body { remaining } => State::Body { remaining: remaining - taken },
+            State::ChunkData { remaining } if remaining == taken => State::ChunkEnd,
+            State::ChunkData { remaining } => State::ChunkData { remaining: remaining - taken },
+            other => other,
+        };
+    }
+
+    fn end_line(&mut self) -> Result<(), StreamError> {
+        // Header names and values are ASCII, anything else can't be a header we use
+        let line = core::str::from_utf8(&self.line).unwrap_or("");
+
+        match self.state {
+            State::StatusLine => {
+                let (http_1_0, status) = parse_status_line(line).ok_or(StreamError::BadStatusLine)?;
+                self.keep_alive = !http_1_0;
+                self.status = Some(status);
+                if status != 200 {
+                    return Err(StreamError::HttpStatus(status));
+                }
+                self.state = State::Headers;
+            }
+            State::Headers if line.is_empty() => self.state = self.body_state(),
+            State::Headers => {
+                if let Some((name, value)) = line.split_once(':') {
+                    let value = value.trim();
+                    if name.eq_ignore_ascii_case("content-length") {
+                        self.content_length = Some(value.parse().map_err(|_| StreamError::BadHeader)?);
+                    } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                        self.chunked = value.eq_ignore_ascii_case("chunked");
+                    } else if name.eq_ignore_ascii_case("connection") {
+                        if value.eq_ignore_ascii_case("close") {
+                            self.keep_alive = false;
+                        } else if value.eq_ignore_ascii_case("keep-alive") {
+                            self.keep_alive = true;
+                        }
+                    }
+                }
+            }
+            State::ChunkSize => {
+                // Chunk extensions (after ';') are allowed and ignored
+                let size = line.split(';').next().unwrap_or("").trim();
+                let size = u64::from_str_radix(size, 16).map_err(|_| StreamError::BadChunk)?;
+                self.state = if size == 0 { State::Trailers } else { State::ChunkData { remaining: size } };
+            }
+            State::ChunkEnd if line.is_empty() => self.state = State::ChunkSize,
+            State::ChunkEnd => return Err(StreamError::BadChunk),
+            State::Trailers if line.is_empty() => self.state = State::Done,
+            _ => {}
+        }
+
+        self.line.clear();
+        Ok(())
+    }
+
+    // Where the body goes once the headers are done
+    fn body_state(&self) -> State {
+        // Chunked wins over Content-Length if a server sends both (RFC 9112)
+        if self.chunked {
+            State::ChunkSize
+        } else {
+            match self.content_length {
+                Some(0) => State::Done,
+                Some(length) => State::Body { remaining: length },
+                None => State::UntilClose,
+            }
+        }
+    }
+}
+
+// "HTTP/1.1 200 OK" -> (false, 200)
+fn parse_status_line(line: &str) -> Option<(bool, u16)> {
+    let mut parts = line.splitn(3, ' ');
+    let version = parts.next()?;
+    let code = parts.next()?;
+
+    let http_1_0 = match version {
+        "HTTP/1.0" => true,
+        "HTTP/1.1" => false,
+        _ => return None,
+    };
+    if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((http_1_0, code.parse().ok()?))
+}
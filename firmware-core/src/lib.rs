@@ -0,0 +1,26 @@
+// src/lib.rs
+
+// Hardware-independent half of the firmware
+// Everything here runs the same on the ESP32 and on a desktop, so it is unit tested on the host
+// and reused by the board simulator
+
+// For your knowledge
+// 'cargo test' inside /hardware/ would build for the ESP32 target, where tests can't run
+// Keeping this logic in its own crate lets us run 'cargo test' here with no board attached
+
+#![no_std]
+
+pub mod canvas;
+pub mod captive;
+pub mod color;
+pub mod config;
+pub mod device;
+pub mod discovery;
+pub mod display;
+pub mod http;
+pub mod link;
+pub mod patterns;
+pub mod portal;
+pub mod render;
+pub mod stream;
+pub mod updates;
@@ -0,0 +1,186 @@
+// src/link.rs
+
+// Keeping the board online: what to try next after each success or failure, and how long to wait first
+
+// For your knowledge
+// The firmware's main loop runs one step for the current LinkState (join Wi-Fi, get an address, find the server,
+// connect, poll) and reports how it went as a LinkEvent. Link picks the next state and the delay before the next step
+// Failures back off exponentially (1 s, 2 s, 4 s ... up to a minute) so a dead router or server isn't hammered,
+// and each success resets the backoff. Losing Wi-Fi goes back to the start, which runs DHCP again
+// While we're not online the panel shows a status dot over the last canvas (status_color), so a frozen picture
+// can be told apart from a quiet canvas. The firmware feeds its watchdog on every event that is_progress()
+
+use protocol::Rgb;
+
+const BACKOFF_BASE_MS: u64 = 1000;
+const BACKOFF_MAX_MS: u64 = 60_000;
+
+// Connection attempts to an address before we look the server up again (it may have moved)
+pub const MAX_CONNECT_FAILURES: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    JoiningWifi,
+    // Waiting for DHCP
+    WaitingForAddress,
+    // Resolving the configured hostname, or discovering a server over mDNS
+    FindingServer,
+    // Opening the TCP connection
+    Connecting,
+    // Polling the server
+    Online,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkEvent {
+    WifiJoined,
+    WifiFailed,
+    // The access point dropped us, whatever we were doing
+    WifiLost,
+    AddressAcquired,
+    AddressTimeout,
+    ServerFound,
+    ServerNotFound,
+    Connected,
+    ConnectFailed,
+    PollSucceeded,
+    // Request, response or connection failed while online
+    PollFailed,
+}
+
+impl LinkEvent {
+    // Did this step get us anywhere? (the firmware's watchdog only reboots if nothing does for a long time)
+    pub fn is_progress(self) -> bool {
+        matches!(
+            self,
+            LinkEvent::WifiJoined
+                | LinkEvent::AddressAcquired
+                | LinkEvent::ServerFound
+                | LinkEvent::Connected
+                | LinkEvent::PollSucceeded
+        )
+    }
+}
+
+// Exponential backoff, doubling per consecutive failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Backoff {
+    failures: u32,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Backoff { failures: 0 }
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    // Count a failure, and return how long to wait before trying again
+    pub fn fail(&mut self) -> u64 {
+        self.failures = self.failures.saturating_add(1);
+        let shift = (self.failures - 1).min(16);
+        (BACKOFF_BASE_MS << shift).min(BACKOFF_MAX_MS)
+    }
+
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    state: LinkState,
+    backoff: Backoff,
+    // Failed attempts to connect to the current address, see MAX_CONNECT_FAILURES
+    connect_failures: u32,
+    poll_interval_ms: u64,
+}
+
+impl Link {
+    // 'poll_interval_ms' is the wait between successful polls
+    pub fn new(poll_interval_ms: u64) -> Self {
+        Link { state: LinkState::JoiningWifi, backoff: Backoff::new(), connect_failures: 0, poll_interval_ms }
+    }
+
+    pub fn state(&self) -> LinkState {
+        self.state
+    }
+
+    // Failures in a row, whatever the state
+    pub fn failures(&self) -> u32 {
+        self.backoff.failures()
+    }
+
+    // Record the outcome of the last step. Returns how long to wait (in ms) before running the next one
+    pub fn on(&mut self, event: LinkEvent) -> u64 {
+        let (next, wait) = match event {
+            LinkEvent::WifiLost => {
+                self.backoff.reset();
+                (LinkState::JoiningWifi, 0)
+            }
+            LinkEvent::WifiJoined => self.advance(LinkState::WaitingForAddress),
+            LinkEvent::WifiFailed => (LinkState::JoiningWifi, self.backoff.fail()),
+            LinkEvent::AddressAcquired => self.advance(LinkState::FindingServer),
+            // Maybe the association is stale, join again (which starts DHCP over)
+            LinkEvent::AddressTimeout => (LinkState::JoiningWifi, self.backoff.fail()),
+            LinkEvent::ServerFound => {
+                self.connect_failures = 0;
+                self.advance(LinkState::Connecting)
+            }
+            LinkEvent::ServerNotFound => (LinkState::FindingServer, self.backoff.fail()),
+            // The backoff is only reset by a successful poll, so a server that accepts connections
+            // but fails every request isn't reconnected to in a tight loop
+            LinkEvent::Connected => (LinkState::Online, 0),
+            LinkEvent::ConnectFailed => {
+                self.connect_failures += 1;
+                let next = if self.connect_failures >= MAX_CONNECT_FAILURES {
+                    self.connect_failures = 0;
+                    LinkState::FindingServer
+                } else {
+                    LinkState::Connecting
+                };
+                (next, self.backoff.fail())
+            }
+            LinkEvent::PollSucceeded => {
+                self.backoff.reset();
+                (LinkState::Online, self.poll_interval_ms)
+            }
+            // Reconnect. The first retry goes straight away, a kept-alive connection timing out while idle is normal
+            LinkEvent::PollFailed => {
+                let first = self.backoff.failures() == 0;
+                let wait = self.backoff.fail();
+                (LinkState::Connecting, if first { 0 } else { wait })
+            }
+        };
+        self.state = next;
+        wait
+    }
+
+    // Colour of the status dot to draw over the canvas, None when everything is fine
+    // Red once a step has failed a few times in a row, otherwise a colour for how far we got
+    pub fn status_color(&self) -> Option<Rgb> {
+        if self.state == LinkState::Online {
+            return None;
+        }
+        if self.backoff.failures() >= 3 {
+            return Some(Rgb::new(255, 0, 0));
+        }
+        Some(match self.state {
+            // Amber
+            LinkState::JoiningWifi => Rgb::new(255, 120, 0),
+            // Yellow
+            LinkState::WaitingForAddress => Rgb::new(255, 255, 0),
+            // Purple
+            LinkState::FindingServer => Rgb::new(160, 0, 255),
+            // Blue
+            LinkState::Connecting | LinkState::Online => Rgb::new(0, 80, 255),
+        })
+    }
+
+    fn advance(&mut self, next: LinkState) -> (LinkState, u64) {
+        self.backoff.reset();
+        (next, 0)
+    }
+}
@@ -0,0 +1,73 @@
+// src/patterns.rs
+
+// Test patterns for checking a panel: its wiring, every LED's colours, and the power supply
+
+// For your knowledge
+// A pattern is drawn as a sequence of steps, each one a full frame shown for a while
+// The pixel walk has a step per pixel (so a dead LED, or a panel wired the other way, shows up as a jump);
+// the others are a single frame held for a few seconds
+// The firmware runs all of them in order when BOOT is held at power-up, or one at a time when an admin asks
+// for it through the backend (POST /devices/{device_id}/test-pattern, picked up with the next heartbeat)
+
+use protocol::{CanvasFrame, Rgb, TestPattern, PALETTE};
+
+// How long each pixel of the walk stays lit
+pub const PIXEL_WALK_STEP_MS: u64 = 30;
+// How long the single-frame patterns are held
+pub const HOLD_MS: u64 = 5000;
+// Full white is a burn-in test, give the supply time to sag or heat up
+pub const FULL_WHITE_MS: u64 = 30_000;
+
+// Steps in 'pattern' for a frame of this size
+pub fn step_count(pattern: TestPattern, frame: &CanvasFrame) -> u32 {
+    match pattern {
+        TestPattern::PixelWalk => frame.width * frame.height,
+        TestPattern::ColorBars | TestPattern::FullWhite | TestPattern::Gradient => 1,
+    }
+}
+
+// The pixel lit by step 'step' of the walk, in canvas order (left to right, then top to bottom)
+pub fn walk_position(step: u32, frame: &CanvasFrame) -> (u32, u32) {
+    (step % frame.width.max(1), step / frame.width.max(1))
+}
+
+// Draw step 'step' of 'pattern' over the whole frame. Returns how long to show it, in ms
+pub fn draw_step(pattern: TestPattern, step: u32, frame: &mut CanvasFrame) -> u64 {
+    let (width, height) = (frame.width, frame.height);
+    match pattern {
+        TestPattern::PixelWalk => {
+            let lit = walk_position(step, frame);
+            fill(frame, |x, y| if (x, y) == lit { Rgb::WHITE } else { Rgb::BLACK });
+            PIXEL_WALK_STEP_MS
+        }
+        TestPattern::ColorBars => {
+            let bars = PALETTE.len() as u32;
+            fill(frame, |x, _| {
+                let bar = (x * bars / width.max(1)) as usize;
+                Rgb::parse_hex(PALETTE[bar]).unwrap_or(Rgb::BLACK)
+            });
+            HOLD_MS
+        }
+        TestPattern::FullWhite => {
+            fill(frame, |_, _| Rgb::WHITE);
+            FULL_WHITE_MS
+        }
+        TestPattern::Gradient => {
+            fill(frame, |x, y| Rgb::new(ramp(x, width), ramp(y, height), 0));
+            HOLD_MS
+        }
+    }
+}
+
+// 0 at the first position to 255 at the last
+fn ramp(position: u32, size: u32) -> u8 {
+    (position * 255 / size.saturating_sub(1).max(1)) as u8
+}
+
+fn fill(frame: &mut CanvasFrame, color: impl Fn(u32, u32) -> Rgb) {
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            frame.set(x, y, color(x, y));
+        }
+    }
+}
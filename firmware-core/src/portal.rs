@@ -0,0 +1,221 @@
+// src/portal.rs
+
+// The setup page served while the board is in provisioning mode
+
+// For your knowledge
+// With no saved config the board starts its own open Wi-Fi network (SETUP_SSID) and answers every web request
+// with a form asking for the Wi-Fi name, password and (optionally) backend address. Phones and laptops probe a fixed URL
+// after joining a network, get our form back instead of what they expected, and pop it up as a "sign in" page
+// (that's the captive portal, captive.rs makes every hostname resolve to us so the probe reaches us)
+// Posting the form to /save validates it into a DeviceConfig, which the firmware writes to flash before rebooting
+
+use crate::config::{ConfigError, DeviceConfig, MAX_PASSWORD_LEN, MAX_SERVER_LEN, MAX_SSID_LEN};
+use core::fmt::{self, Write};
+use heapless::String;
+
+// Name of the board's own network while it's being set up
+pub const SETUP_SSID: &str = "RustyCanvas-Setup";
+
+// Largest request we accept (the form post is well under this)
+pub const MAX_REQUEST_LEN: usize = 1024;
+
+// Largest response we write (the form page with an error message)
+pub const MAX_RESPONSE_LEN: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortalRequest<'a> {
+    pub method: Method,
+    pub path: &'a str,
+    pub body: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsedRequest<'a> {
+    // Keep reading, the headers or body aren't all here yet
+    Incomplete,
+    Complete(PortalRequest<'a>),
+    // Not HTTP, or too broken to answer
+    Invalid,
+}
+
+// What to do with a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortalAction {
+    // Show the form, with a message if the last attempt was rejected
+    ShowForm(Option<ConfigError>),
+    // Valid settings were posted: save them and reboot
+    Save(DeviceConfig),
+}
+
+// Parse what has been received so far on a portal connection
+pub fn parse_request(buf: &[u8]) -> ParsedRequest<'_> {
+    let Some(header_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return ParsedRequest::Incomplete;
+    };
+    let Ok(head) = core::str::from_utf8(&buf[..header_end]) else {
+        return ParsedRequest::Invalid;
+    };
+
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split(' ');
+    let method = match request_line.next() {
+        Some("GET") => Method::Get,
+        Some("POST") => Method::Post,
+        Some(_) => Method::Other,
+        None => return ParsedRequest::Invalid,
+    };
+    let Some(path) = request_line.next() else {
+        return ParsedRequest::Invalid;
+    };
+
+    let mut content_length = 0;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                match value.trim().parse() {
+                    Ok(length) => content_length = length,
+                    Err(_) => return ParsedRequest::Invalid,
+                }
+            }
+        }
+    }
+
+    let body = &buf[header_end + 4..];
+    if body.len() < content_length {
+        return ParsedRequest::Incomplete;
+    }
+    match core::str::from_utf8(&body[..content_length]) {
+        Ok(body) => ParsedRequest::Complete(PortalRequest { method, path, body }),
+        Err(_) => ParsedRequest::Invalid,
+    }
+}
+
+// Every URL shows the form (that's what makes the captive portal pop up), only POST /save changes anything
+pub fn handle_request(request: &PortalRequest<'_>) -> PortalAction {
+    if request.method == Method::Post && request.path == "/save" {
+        match parse_setup_form(request.body) {
+            Ok(config) => PortalAction::Save(config),
+            Err(e) => PortalAction::ShowForm(Some(e)),
+        }
+    } else {
+        PortalAction::ShowForm(None)
+    }
+}
+
+// Decode "ssid=...&password=...&server=..." (application/x-www-form-urlencoded)
+pub fn parse_setup_form(body: &str) -> Result<DeviceConfig, ConfigError> {
+    let mut ssid: String<MAX_SSID_LEN> = String::new();
+    let mut password: String<MAX_PASSWORD_LEN> = String::new();
+    let mut server: String<MAX_SERVER_LEN> = String::new();
+
+    for pair in body.split('&') {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match name {
+            "ssid" => ssid = url_decode(value).ok_or(ConfigError::SsidTooLong)?,
+            "password" => password = url_decode(value).ok_or(ConfigError::BadPassword)?,
+            "server" => server = url_decode(value).ok_or(ConfigError::ServerTooLong)?,
+            _ => {}
+        }
+    }
+
+    DeviceConfig::new(&ssid, &password, &server)
+}
+
+// Undo form encoding ('+' is a space, %XX is a byte). None if it doesn't fit in N bytes or isn't valid
+pub fn url_decode<const N: usize>(value: &str) -> Option<String<N>> {
+    let mut bytes: heapless::Vec<u8, N> = heapless::Vec::new();
+    let mut input = value.bytes();
+    while let Some(byte) = input.next() {
+        let decoded = match byte {
+            b'+' => b' ',
+            b'%' => {
+                let high = (input.next()? as char).to_digit(16)?;
+                let low = (input.next()? as char).to_digit(16)?;
+                (high * 16 + low) as u8
+            }
+            other => other,
+        };
+        bytes.push(decoded).ok()?;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+// The form page as a full HTTP response
+pub fn write_form_response<W: Write>(out: &mut W, error: Option<ConfigError>) -> fmt::Result {
+    write_html_response(out, |body| write_form_body(body, error))
+}
+
+// The "saved, rebooting" page as a full HTTP response
+pub fn write_saved_response<W: Write>(out: &mut W, config: &DeviceConfig) -> fmt::Result {
+    write_html_response(out, |body| {
+        write!(body, "{}<h1>Saved</h1><p>Rebooting to join <b>", PAGE_HEAD)?;
+        write_escaped(body, &config.ssid)?;
+        write!(body, "</b>. You can close this page.</p></body></html>")
+    })
+}
+
+const PAGE_HEAD: &str = "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+<meta name=\"viewport\" content=\"width=device-width,initial-scale=1\"><title>RustyCanvas setup</title>\
+<style>body{font-family:sans-serif;max-width:24em;margin:2em auto;padding:0 1em}\
+input{display:block;width:100%;margin:.3em 0 1em;padding:.4em}.err{color:#b00}</style></head><body>";
+
+fn write_form_body(out: &mut dyn Write, error: Option<ConfigError>) -> fmt::Result {
+    write!(out, "{}<h1>RustyCanvas setup</h1>", PAGE_HEAD)?;
+    if let Some(error) = error {
+        write!(out, "<p class=\"err\">{}</p>", error)?;
+    }
+    write!(
+        out,
+        "<form method=\"post\" action=\"/save\">\
+<label>Wi-Fi network<input name=\"ssid\" maxlength=\"32\" required></label>\
+<label>Wi-Fi password<input name=\"password\" type=\"password\" maxlength=\"64\"></label>\
+<label>Canvas server<input name=\"server\" placeholder=\"leave empty to find it automatically\"></label>\
+<button type=\"submit\">Save and reboot</button></form></body></html>"
+    )
+}
+
+// Counts bytes so we can send Content-Length before the body
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+fn write_html_response<W, F>(out: &mut W, body: F) -> fmt::Result
+where
+    W: Write,
+    F: Fn(&mut dyn Write) -> fmt::Result,
+{
+    let mut counter = ByteCounter(0);
+    body(&mut counter)?;
+    write!(
+        out,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\
+Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        counter.0
+    )?;
+    body(out)
+}
+
+fn write_escaped<W: Write + ?Sized>(out: &mut W, text: &str) -> fmt::Result {
+    for c in text.chars() {
+        match c {
+            '<' => out.write_str("&lt;")?,
+            '>' => out.write_str("&gt;")?,
+            '&' => out.write_str("&amp;")?,
+            '"' => out.write_str("&quot;")?,
+            _ => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
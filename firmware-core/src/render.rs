@@ -0,0 +1,53 @@
+// src/render.rs
+
+// Rendering a CanvasFrame onto a display
+
+// For your knowledge
+// 'Panel' is implemented by anything that can show pixels: the real LED panel in the firmware,
+// the simulator's PNG/terminal output, or a mock in the tests
+// The rendering code only talks to the trait, so the same code path is exercised everywhere
+
+use protocol::{CanvasFrame, Rgb};
+
+pub trait Panel {
+    // What can go wrong when pushing a frame out (use core::convert::Infallible if nothing can)
+    type Error;
+
+    // Width and height in pixels
+    fn size(&self) -> (u32, u32);
+
+    // Stage one pixel, it doesn't have to be visible until show() is called
+    fn set_pixel(&mut self, x: u32, y: u32, color: Rgb);
+
+    // Make everything staged since the last show() visible
+    fn show(&mut self) -> Result<(), Self::Error>;
+}
+
+// Side of the square status dot drawn in the top-right corner
+pub const STATUS_DOT_SIZE: u32 = 2;
+
+// Draw the whole frame, clipped to the panel size, then show it
+pub fn render_frame<P: Panel>(frame: &CanvasFrame, panel: &mut P) -> Result<(), P::Error> {
+    render_frame_with_status(frame, panel, None)
+}
+
+// Same as render_frame, with a status dot over the top-right corner when 'status' is set (see link.rs)
+pub fn render_frame_with_status<P: Panel>(frame: &CanvasFrame, panel: &mut P, status: Option<Rgb>) -> Result<(), P::Error> {
+    let (panel_width, panel_height) = panel.size();
+
+    for (y, row) in frame.pixels.iter().enumerate().take(panel_height as usize) {
+        for (x, color) in row.iter().enumerate().take(panel_width as usize) {
+            panel.set_pixel(x as u32, y as u32, *color);
+        }
+    }
+
+    if let Some(color) = status {
+        for y in 0..STATUS_DOT_SIZE.min(panel_height) {
+            for x in panel_width.saturating_sub(STATUS_DOT_SIZE)..panel_width {
+                panel.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    panel.show()
+}
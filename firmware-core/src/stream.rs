@@ -0,0 +1,258 @@
+// src/stream.rs
+
+// Incremental (push) parsing of HTTP responses, so the firmware never has to hold a whole response in RAM
+
+// For your knowledge
+// The socket hands us the response in chunks of whatever size the network felt like, split anywhere:
+// in the middle of a header, a colour string or a number. Both parsers here work byte by byte and keep
+// just enough state between chunks to carry on where they stopped
+//
+// http::ResponseParser deals with the status line, headers and body framing and hands on the body bytes
+// JsonTokenizer turns body bytes into events ('{', key, string, number, ...) and calls back for each one,
+// together with the nesting depth. canvas.rs and updates.rs turn those events into pixels
+// Only the token being read is buffered (at most MAX_TOKEN_LEN bytes), which is plenty for our responses
+
+use heapless::Vec;
+
+// Longest string/number we buffer ("#RRGGBB" is 7, a u64 timestamp 20)
+pub const MAX_TOKEN_LEN: usize = 64;
+// Deepest nesting we track (the canvas needs 3: object -> rows -> row)
+pub const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamError {
+    // The first line isn't "HTTP/1.x <code> ..."
+    BadStatusLine,
+    // The server answered with something other than 200
+    HttpStatus(u16),
+    // A header we rely on (Content-Length) has a value we can't use
+    BadHeader,
+    // A chunked body with a bad size line or missing line break
+    BadChunk,
+    // A byte that can't appear at this point in JSON
+    UnexpectedByte(u8),
+    // A closing bracket that doesn't match the open one
+    MismatchedBracket,
+    // A string or number longer than MAX_TOKEN_LEN
+    TokenTooLong,
+    // More than MAX_DEPTH nested objects/arrays
+    TooDeep,
+    // A string that isn't valid UTF-8, or uses an escape we don't support (\u)
+    BadString,
+    // A number we couldn't fit in the field it belongs to
+    InvalidNumber,
+    // A colour that isn't "#RRGGBB"
+    InvalidColor,
+    // An object is missing a field we need
+    MissingField,
+    // The response ended before the JSON was closed
+    Incomplete,
+}
+
+// One piece of JSON, borrowed from the tokenizer until the callback returns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event<'a> {
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    // An object key (without quotes)
+    Key(&'a str),
+    // A string value (without quotes, escapes resolved)
+    Str(&'a str),
+    // A number exactly as written, the consumer parses it into the type it needs
+    Number(&'a str),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Object { expect_key: bool },
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lex {
+    // Between tokens
+    Idle,
+    // Inside "...", 'escape' after a backslash
+    Str { key: bool, escape: bool },
+    // Inside a number or true/false/null (they end at the next delimiter)
+    Bare,
+}
+
+// Streaming JSON tokenizer
+// The depth passed with each event is the number of open containers: 1 for the top-level keys,
+// and Begin/End events get the depth of the container they open/close
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonTokenizer {
+    lex: Lex,
+    token: Vec<u8, MAX_TOKEN_LEN>,
+    stack: Vec<Container, MAX_DEPTH>,
+    started: bool,
+}
+
+impl Default for JsonTokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonTokenizer {
+    pub fn new() -> Self {
+        JsonTokenizer { lex: Lex::Idle, token: Vec::new(), stack: Vec::new(), started: false }
+    }
+
+    // True once the top-level value has been closed
+    pub fn is_complete(&self) -> bool {
+        self.started && self.stack.is_empty() && self.lex == Lex::Idle
+    }
+
+    pub fn feed<F>(&mut self, bytes: &[u8], mut on_event: F) -> Result<(), StreamError>
+    where
+        F: FnMut(Event<'_>, usize) -> Result<(), StreamError>,
+    {
+        for &byte in bytes {
+            self.feed_byte(byte, &mut on_event)?;
+        }
+        Ok(())
+    }
+
+    fn feed_byte<F>(&mut self, byte: u8, on_event: &mut F) -> Result<(), StreamError>
+    where
+        F: FnMut(Event<'_>, usize) -> Result<(), StreamError>,
+    {
+        match self.lex {
+            Lex::Str { key, escape: true } => {
+                let resolved = match byte {
+                    b'"' | b'\\' | b'/' => byte,
+                    b'n' => b'\n',
+                    b't' => b'\t',
+                    b'r' => b'\r',
+                    b'b' => 0x08,
+                    b'f' => 0x0C,
+                    _ => return Err(StreamError::BadString),
+                };
+                self.push_token(resolved)?;
+                self.lex = Lex::Str { key, escape: false };
+                Ok(())
+            }
+            Lex::Str { key, escape: false } => match byte {
+                b'\\' => {
+                    self.lex = Lex::Str { key, escape: true };
+                    Ok(())
+                }
+                b'"' => {
+                    self.lex = Lex::Idle;
+                    self.emit_string(key, on_event)
+                }
+                _ => self.push_token(byte),
+            },
+            Lex::Bare => {
+                if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'+' | b'.') {
+                    return self.push_token(byte);
+                }
+                // Anything else ends the token, and is then handled as usual
+                self.lex = Lex::Idle;
+                self.emit_bare(on_event)?;
+                self.feed_idle(byte, on_event)
+            }
+            Lex::Idle => self.feed_idle(byte, on_event),
+        }
+    }
+
+    fn feed_idle<F>(&mut self, byte: u8, on_event: &mut F) -> Result<(), StreamError>
+    where
+        F: FnMut(Event<'_>, usize) -> Result<(), StreamError>,
+    {
+        // Whatever follows the top-level value (there shouldn't be anything) is ignored
+        if self.started && self.stack.is_empty() {
+            return Ok(());
+        }
+
+        match byte {
+            b' ' | b'\t' | b'\r' | b'\n' => Ok(()),
+            b'{' | b'[' => {
+                let container = if byte == b'{' {
+                    Container::Object { expect_key: true }
+                } else {
+                    Container::Array
+                };
+                self.stack.push(container).map_err(|_| StreamError::TooDeep)?;
+                self.started = true;
+                let event = if byte == b'{' { Event::BeginObject } else { Event::BeginArray };
+                on_event(event, self.stack.len())
+            }
+            b'}' | b']' => {
+                let (event, matches) = match (byte, self.stack.last()) {
+                    (b'}', Some(Container::Object { .. })) => (Event::EndObject, true),
+                    (b']', Some(Container::Array)) => (Event::EndArray, true),
+                    _ => (Event::Null, false),
+                };
+                if !matches {
+                    return Err(StreamError::MismatchedBracket);
+                }
+                on_event(event, self.stack.len())?;
+                self.stack.pop();
+                Ok(())
+            }
+            b'"' => {
+                let key = matches!(self.stack.last(), Some(Container::Object { expect_key: true }));
+                self.lex = Lex::Str { key, escape: false };
+                Ok(())
+            }
+            b':' => Ok(()),
+            b',' => {
+                if let Some(Container::Object { expect_key }) = self.stack.last_mut() {
+                    *expect_key = true;
+                }
+                Ok(())
+            }
+            b'-' | b'0'..=b'9' | b't' | b'f' | b'n' if !self.stack.is_empty() => {
+                self.lex = Lex::Bare;
+                self.push_token(byte)
+            }
+            _ => Err(StreamError::UnexpectedByte(byte)),
+        }
+    }
+
+    fn push_token(&mut self, byte: u8) -> Result<(), StreamError> {
+        self.token.push(byte).map_err(|_| StreamError::TokenTooLong)
+    }
+
+    fn emit_string<F>(&mut self, key: bool, on_event: &mut F) -> Result<(), StreamError>
+    where
+        F: FnMut(Event<'_>, usize) -> Result<(), StreamError>,
+    {
+        let text = core::str::from_utf8(&self.token).map_err(|_| StreamError::BadString)?;
+        let event = if key { Event::Key(text) } else { Event::Str(text) };
+        on_event(event, self.stack.len())?;
+
+        if key {
+            if let Some(Container::Object { expect_key }) = self.stack.last_mut() {
+                *expect_key = false;
+            }
+        }
+        self.token.clear();
+        Ok(())
+    }
+
+    fn emit_bare<F>(&mut self, on_event: &mut F) -> Result<(), StreamError>
+    where
+        F: FnMut(Event<'_>, usize) -> Result<(), StreamError>,
+    {
+        // Only ASCII was pushed, so this can't fail
+        let text = core::str::from_utf8(&self.token).map_err(|_| StreamError::BadString)?;
+        let event = match text {
+            "true" => Event::Bool(true),
+            "false" => Event::Bool(false),
+            "null" => Event::Null,
+            _ if text.starts_with(|c: char| c == '-' || c.is_ascii_digit()) => Event::Number(text),
+            _ => return Err(StreamError::UnexpectedByte(self.token[0])),
+        };
+        on_event(event, self.stack.len())?;
+        self.token.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,379 @@
+// src/updates.rs
+
+// Following GET /updates?since=... so the firmware only downloads what changed
+
+// For your knowledge
+// The firmware loads the full board once, then polls /updates with the newest timestamp it has applied
+// Before that first load it asks /updates where the server's history ends (latest_timestamp), so it starts
+// from there rather than from 0, which would replay history the canvas already holds (or that a reset erased)
+// A poll returns at most the server's history (50 entries) and is usually empty, instead of the ~9 KB canvas
+// If we fell too far behind, the server answers reset_required: we reload the full canvas and continue from
+// the server's latest_timestamp. The firmware has no wall clock, so 'since' only ever comes from the server
+// Applying an update we already have is harmless (it sets the same colour), so overlaps are fine, gaps are not
+//
+// On the board, responses are parsed while they download: CanvasSync::start_response() gives a SyncStream
+// for whatever request_path() asked for, and finish_response() updates the cursor once the socket closes
+
+use crate::canvas::{response_body, CanvasParseError, CanvasStream};
+use crate::http::ResponseParser;
+use crate::stream::{Event, JsonTokenizer, StreamError};
+use core::fmt::Write;
+use heapless::{String, Vec};
+use protocol::{CanvasFrame, Rgb};
+use serde::Deserialize;
+
+// The backend keeps the last 50 updates, a single response never holds more
+pub const MAX_UPDATES: usize = 50;
+
+// Long enough for "/updates?since=" followed by any u64
+pub const MAX_PATH_LEN: usize = 40;
+
+// No update is newer than this, so the answer to it carries nothing but the server's latest_timestamp
+const LATEST_ONLY: u64 = u64::MAX;
+
+// Fixed-size version of protocol::PixelUpdate (the colour is parsed straight into an Rgb)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct PixelDelta {
+    pub x: u32,
+    pub y: u32,
+    pub color: Rgb,
+    pub timestamp: u64,
+}
+
+// Fixed-size version of protocol::UpdatesResponse
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct UpdatesFrame {
+    pub updates: Vec<PixelDelta, MAX_UPDATES>,
+    pub reset_required: bool,
+    #[serde(default)]
+    pub latest_timestamp: u64,
+}
+
+// Parse a JSON body from GET /updates
+pub fn parse_updates_json(body: &str) -> Result<UpdatesFrame, CanvasParseError> {
+    serde_json_core::from_str::<UpdatesFrame>(body)
+        .map(|(updates, _)| updates)
+        .map_err(CanvasParseError::Json)
+}
+
+// Parse a full HTTP response from GET /updates
+pub fn parse_updates_response(response: &[u8]) -> Result<UpdatesFrame, CanvasParseError> {
+    parse_updates_json(response_body(response)?)
+}
+
+// What a GET /updates response amounted to, once all its updates have been written to the frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UpdatesSummary {
+    // Pixels written into the frame
+    pub applied: usize,
+    // Largest timestamp among the updates (0 if there were none)
+    pub newest: u64,
+    pub reset_required: bool,
+    pub latest_timestamp: u64,
+}
+
+// Which field of an /updates response we are in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpdatesField {
+    Updates,
+    ResetRequired,
+    LatestTimestamp,
+    X,
+    Y,
+    Color,
+    Timestamp,
+    Other,
+}
+
+// Applies each update to the frame as soon as its object closes
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UpdatesSink {
+    top_field: UpdatesField,
+    field: UpdatesField,
+    x: Option<u32>,
+    y: Option<u32>,
+    color: Option<Rgb>,
+    timestamp: Option<u64>,
+    summary: UpdatesSummary,
+}
+
+impl UpdatesSink {
+    fn on_event(&mut self, event: Event<'_>, depth: usize, frame: &mut CanvasFrame) -> Result<(), StreamError> {
+        let in_update = self.top_field == UpdatesField::Updates && depth == 3;
+
+        match event {
+            Event::Key(key) if depth == 1 => {
+                self.top_field = match key {
+                    "updates" => UpdatesField::Updates,
+                    "reset_required" => UpdatesField::ResetRequired,
+                    "latest_timestamp" => UpdatesField::LatestTimestamp,
+                    _ => UpdatesField::Other,
+                };
+            }
+            Event::Bool(value) if depth == 1 && self.top_field == UpdatesField::ResetRequired => {
+                self.summary.reset_required = value;
+            }
+            Event::Number(number) if depth == 1 && self.top_field == UpdatesField::LatestTimestamp => {
+                self.summary.latest_timestamp = parse_number(number)?;
+            }
+            Event::BeginObject if in_update => {
+                self.x = None;
+                self.y = None;
+                self.color = None;
+                self.timestamp = None;
+            }
+            Event::Key(key) if in_update => {
+                self.field = match key {
+                    "x" => UpdatesField::X,
+                    "y" => UpdatesField::Y,
+                    "color" => UpdatesField::Color,
+                    "timestamp" => UpdatesField::Timestamp,
+                    _ => UpdatesField::Other,
+                };
+            }
+            Event::Number(number) if in_update => match self.field {
+                UpdatesField::X => self.x = Some(parse_number(number)?),
+                UpdatesField::Y => self.y = Some(parse_number(number)?),
+                UpdatesField::Timestamp => self.timestamp = Some(parse_number(number)?),
+                _ => {}
+            },
+            Event::Str(color) if in_update && self.field == UpdatesField::Color => {
+                self.color = Some(Rgb::parse_hex(color).map_err(|_| StreamError::InvalidColor)?);
+            }
+            Event::EndObject if in_update => {
+                let (Some(x), Some(y), Some(color), Some(timestamp)) = (self.x, self.y, self.color, self.timestamp) else {
+                    return Err(StreamError::MissingField);
+                };
+                if frame.set(x, y, color) {
+                    self.summary.applied += 1;
+                }
+                self.summary.newest = self.summary.newest.max(timestamp);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn parse_number<T: core::str::FromStr>(number: &str) -> Result<T, StreamError> {
+    number.parse().map_err(|_| StreamError::InvalidNumber)
+}
+
+// Streaming parser for a full GET /updates HTTP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdatesStream {
+    http: ResponseParser,
+    json: JsonTokenizer,
+    sink: UpdatesSink,
+}
+
+impl Default for UpdatesStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UpdatesStream {
+    pub fn new() -> Self {
+        UpdatesStream {
+            http: ResponseParser::new(),
+            json: JsonTokenizer::new(),
+            sink: UpdatesSink {
+                top_field: UpdatesField::Other,
+                field: UpdatesField::Other,
+                x: None,
+                y: None,
+                color: None,
+                timestamp: None,
+                summary: UpdatesSummary::default(),
+            },
+        }
+    }
+
+    // Feed the next chunk of the response, updates are written into the frame as they complete
+    pub fn feed(&mut self, chunk: &[u8], frame: &mut CanvasFrame) -> Result<(), StreamError> {
+        let json = &mut self.json;
+        let sink = &mut self.sink;
+        self.http
+            .feed(chunk, |body| json.feed(body, |event, depth| sink.on_event(event, depth, frame)))
+            .map(|_| ())
+    }
+
+    // True once the whole response has arrived, so there's no need to wait for more (see http.rs)
+    pub fn is_complete(&self) -> bool {
+        self.http.is_complete()
+    }
+
+    // Whether the connection can be reused for the next request
+    pub fn keep_alive(&self) -> bool {
+        self.http.keep_alive()
+    }
+
+    // Call once the response is complete or the connection has closed, fails if it was cut short
+    pub fn finish(&mut self) -> Result<UpdatesSummary, StreamError> {
+        self.http.finish()?;
+        if self.json.is_complete() {
+            Ok(self.sink.summary)
+        } else {
+            Err(StreamError::Incomplete)
+        }
+    }
+}
+
+// Parser for whichever response CanvasSync asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncStream {
+    Canvas(CanvasStream),
+    Updates(UpdatesStream),
+}
+
+impl SyncStream {
+    pub fn feed(&mut self, chunk: &[u8], frame: &mut CanvasFrame) -> Result<(), StreamError> {
+        match self {
+            SyncStream::Canvas(stream) => stream.feed(chunk, frame),
+            SyncStream::Updates(stream) => stream.feed(chunk, frame),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        match self {
+            SyncStream::Canvas(stream) => stream.is_complete(),
+            SyncStream::Updates(stream) => stream.is_complete(),
+        }
+    }
+
+    pub fn keep_alive(&self) -> bool {
+        match self {
+            SyncStream::Canvas(stream) => stream.keep_alive(),
+            SyncStream::Updates(stream) => stream.keep_alive(),
+        }
+    }
+}
+
+// What applying a response did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStep {
+    // We know where the server's history ends, the full canvas comes next
+    Positioned,
+    // The full canvas was loaded
+    Loaded,
+    // This many pixels were written into the frame (0 if nothing changed)
+    Applied(usize),
+    // We missed history, the full canvas has to be fetched again
+    Resync,
+}
+
+// Decides what to request next and keeps the 'since' cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanvasSync {
+    since: u64,
+    needs_full: bool,
+    // Only until the first full canvas: 'since' hasn't come from the server yet
+    needs_cursor: bool,
+}
+
+impl Default for CanvasSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CanvasSync {
+    // Starts by asking where the server's history ends, then for the full canvas
+    pub fn new() -> Self {
+        CanvasSync { since: 0, needs_full: true, needs_cursor: true }
+    }
+
+    // Newest server timestamp we have applied
+    pub fn since(&self) -> u64 {
+        self.since
+    }
+
+    pub fn needs_full_canvas(&self) -> bool {
+        self.needs_full
+    }
+
+    // Path for the next GET: "/canvas" or "/updates?since=<since>"
+    pub fn request_path(&self) -> String<MAX_PATH_LEN> {
+        let mut path = String::new();
+        // Can't overflow: MAX_PATH_LEN fits the longest u64
+        if self.needs_cursor {
+            let _ = write!(path, "/updates?since={}", LATEST_ONLY);
+        } else if self.needs_full {
+            let _ = path.push_str("/canvas");
+        } else {
+            let _ = write!(path, "/updates?since={}", self.since);
+        }
+        path
+    }
+
+    // Call once a GET /canvas response has been parsed and drawn
+    pub fn full_canvas_loaded(&mut self) {
+        self.needs_full = false;
+        self.needs_cursor = false;
+    }
+
+    // Force a full reload on the next request (e.g. after a response we couldn't parse)
+    pub fn resync(&mut self) {
+        self.needs_full = true;
+    }
+
+    // Apply a (buffered) GET /updates response to the frame
+    pub fn apply(&mut self, response: &UpdatesFrame, frame: &mut CanvasFrame) -> SyncStep {
+        let mut summary = UpdatesSummary {
+            reset_required: response.reset_required,
+            latest_timestamp: response.latest_timestamp,
+            ..UpdatesSummary::default()
+        };
+        if !response.reset_required {
+            for update in &response.updates {
+                if frame.set(update.x, update.y, update.color) {
+                    summary.applied += 1;
+                }
+                summary.newest = summary.newest.max(update.timestamp);
+            }
+        }
+        self.finish_updates(&summary)
+    }
+
+    // Move the cursor on once a GET /updates response has been written to the frame
+    pub fn finish_updates(&mut self, summary: &UpdatesSummary) -> SyncStep {
+        if self.needs_cursor {
+            // The canvas we are about to fetch already contains everything up to latest_timestamp
+            self.since = summary.latest_timestamp;
+            self.needs_cursor = false;
+            return SyncStep::Positioned;
+        }
+        if summary.reset_required {
+            // The canvas we are about to fetch already contains everything up to latest_timestamp
+            self.since = summary.latest_timestamp;
+            self.needs_full = true;
+            return SyncStep::Resync;
+        }
+
+        self.since = self.since.max(summary.newest);
+        SyncStep::Applied(summary.applied)
+    }
+
+    // A streaming parser for the response to request_path()
+    pub fn start_response(&self) -> SyncStream {
+        if self.needs_full && !self.needs_cursor {
+            SyncStream::Canvas(CanvasStream::new())
+        } else {
+            SyncStream::Updates(UpdatesStream::new())
+        }
+    }
+
+    // Call once the response to request_path() is complete (or the connection closed)
+    // On error nothing moves, call resync() to start again from the full canvas
+    pub fn finish_response(&mut self, stream: &mut SyncStream) -> Result<SyncStep, StreamError> {
+        match stream {
+            SyncStream::Canvas(stream) => {
+                stream.finish()?;
+                self.full_canvas_loaded();
+                Ok(SyncStep::Loaded)
+            }
+            SyncStream::Updates(stream) => Ok(self.finish_updates(&stream.finish()?)),
+        }
+    }
+}
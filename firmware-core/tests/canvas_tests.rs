@@ -0,0 +1,139 @@
+// tests/canvas_tests.rs
+
+// Host tests for the firmware's canvas parsing and rendering
+
+use firmware_core::canvas::{parse_canvas_response, CanvasParseError};
+use firmware_core::render::{render_frame, render_frame_with_status, Panel, STATUS_DOT_SIZE};
+use protocol::{CanvasFrame, Rgb};
+
+// Test helper to build a GET /canvas JSON body with every pixel set to 'fill' except (x, y) = 'color'
+fn canvas_json(fill: &str, x: usize, y: usize, color: &str) -> String {
+    let rows: Vec<String> = (0..16)
+        .map(|row| {
+            let cells: Vec<String> = (0..32)
+                .map(|col| format!("\"{}\"", if (col, row) == (x, y) { color } else { fill }))
+                .collect();
+            format!("[{}]", cells.join(","))
+        })
+        .collect();
+    format!("{{\"width\":32,\"height\":16,\"pixels\":[{}]}}", rows.join(","))
+}
+
+// Test helper to wrap a body the way axum sends it
+fn http_response(body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.0 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+// Records everything drawn to it
+struct MockPanel {
+    width: u32,
+    height: u32,
+    pixels: Vec<(u32, u32, Rgb)>,
+    shows: usize,
+}
+
+impl MockPanel {
+    fn new(width: u32, height: u32) -> Self {
+        MockPanel { width, height, pixels: Vec::new(), shows: 0 }
+    }
+}
+
+impl Panel for MockPanel {
+    type Error = core::convert::Infallible;
+
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: Rgb) {
+        self.pixels.push((x, y, color));
+    }
+
+    fn show(&mut self) -> Result<(), Self::Error> {
+        self.shows += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_full_response() {
+    let body = canvas_json("#000000", 7, 3, "#12AB34");
+
+    let frame = parse_canvas_response(&http_response(&body)).unwrap();
+
+    assert_eq!((frame.width, frame.height), (32, 16));
+    assert_eq!(frame.get(7, 3), Some(Rgb::new(0x12, 0xAB, 0x34)));
+    assert_eq!(frame.get(0, 0), Some(Rgb::BLACK));
+}
+
+#[test]
+fn test_parse_rejects_missing_body() {
+    let result = parse_canvas_response(b"HTTP/1.0 200 OK\r\ncontent-type: application/json\r\n");
+    assert_eq!(result, Err(CanvasParseError::MissingBody));
+}
+
+#[test]
+fn test_parse_rejects_truncated_body() {
+    let body = canvas_json("#000000", 0, 0, "#FFFFFF");
+    let response = http_response(&body);
+
+    // Same as the firmware silently dropping bytes past its buffer
+    let result = parse_canvas_response(&response[..response.len() - 100]);
+    assert!(matches!(result, Err(CanvasParseError::Json(_))));
+}
+
+#[test]
+fn test_parse_rejects_invalid_utf8() {
+    let mut response = http_response(&canvas_json("#000000", 0, 0, "#FFFFFF"));
+    response[5] = 0xFF;
+    assert_eq!(parse_canvas_response(&response), Err(CanvasParseError::NotUtf8));
+}
+
+#[test]
+fn test_render_frame_draws_every_pixel_then_shows() {
+    let mut frame = CanvasFrame::default();
+    frame.set(31, 15, Rgb::WHITE);
+    let mut panel = MockPanel::new(32, 16);
+
+    render_frame(&frame, &mut panel).unwrap();
+
+    assert_eq!(panel.pixels.len(), 32 * 16);
+    assert_eq!(panel.pixels.last(), Some(&(31, 15, Rgb::WHITE)));
+    assert_eq!(panel.shows, 1);
+}
+
+#[test]
+fn test_render_frame_clips_to_smaller_panel() {
+    let frame = CanvasFrame::default();
+    let mut panel = MockPanel::new(8, 4);
+
+    render_frame(&frame, &mut panel).unwrap();
+
+    assert_eq!(panel.pixels.len(), 8 * 4);
+    assert!(panel.pixels.iter().all(|&(x, y, _)| x < 8 && y < 4));
+}
+
+#[test]
+fn test_status_dot_is_drawn_over_the_frame() {
+    let frame = CanvasFrame::default();
+    let mut panel = MockPanel::new(32, 16);
+    let red = Rgb::new(255, 0, 0);
+
+    render_frame_with_status(&frame, &mut panel, Some(red)).unwrap();
+
+    // The dot is drawn after the canvas, so it wins
+    let dot: Vec<_> = panel.pixels[32 * 16..].to_vec();
+    assert_eq!(dot.len(), (STATUS_DOT_SIZE * STATUS_DOT_SIZE) as usize);
+    assert!(dot.iter().all(|&(x, y, color)| x >= 32 - STATUS_DOT_SIZE && y < STATUS_DOT_SIZE && color == red));
+    assert_eq!(panel.shows, 1);
+
+    // No status, no dot
+    let mut panel = MockPanel::new(32, 16);
+    render_frame_with_status(&frame, &mut panel, None).unwrap();
+    assert_eq!(panel.pixels.len(), 32 * 16);
+}
@@ -0,0 +1,145 @@
+// tests/captive_tests.rs
+
+// Host tests for the setup network's DHCP and DNS servers
+
+use core::net::Ipv4Addr;
+use firmware_core::captive::{dns_reply, DhcpServer, DHCP_REPLY_LEN, MAX_LEASES};
+
+const AP_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 4, 1);
+
+// Test helper to build a DHCP packet like a phone sends, with 'options' after the message type
+fn dhcp_packet(message_type: u8, mac: [u8; 6], options: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0u8; 240];
+    packet[0] = 1; // BOOTREQUEST
+    packet[1] = 1;
+    packet[2] = 6;
+    packet[4..8].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+    packet[10] = 0x80; // broadcast flag
+    packet[28..34].copy_from_slice(&mac);
+    packet[236..240].copy_from_slice(&[99, 130, 83, 99]);
+    packet.extend_from_slice(&[53, 1, message_type]);
+    packet.extend_from_slice(options);
+    packet.push(255);
+    packet
+}
+
+// Test helper to read option 'code' out of a reply
+fn reply_option(reply: &[u8], code: u8) -> Option<Vec<u8>> {
+    let mut pos = 240;
+    while pos < reply.len() && reply[pos] != 255 {
+        let len = reply[pos + 1] as usize;
+        if reply[pos] == code {
+            return Some(reply[pos + 2..pos + 2 + len].to_vec());
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+fn your_ip(reply: &[u8]) -> Ipv4Addr {
+    Ipv4Addr::new(reply[16], reply[17], reply[18], reply[19])
+}
+
+// Test helper to build a DNS query for 'name'
+fn dns_query(name: &str, qtype: u16) -> Vec<u8> {
+    let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.split('.') {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&qtype.to_be_bytes());
+    query.extend_from_slice(&[0, 1]);
+    query
+}
+
+#[test]
+fn test_discover_request_ack() {
+    let mut server = DhcpServer::new(AP_IP);
+    let mut reply = [0u8; DHCP_REPLY_LEN];
+    let mac = [2, 0, 0, 0, 0, 1];
+
+    let len = server.handle(&dhcp_packet(1, mac, &[]), &mut reply).unwrap();
+    assert_eq!(len, DHCP_REPLY_LEN);
+    assert_eq!(reply[0], 2);
+    assert_eq!(&reply[4..8], &[0xDE, 0xAD, 0xBE, 0xEF]);
+    assert_eq!(&reply[28..34], &mac);
+    assert_eq!(reply_option(&reply, 53), Some(vec![2])); // OFFER
+    assert_eq!(reply_option(&reply, 6), Some(AP_IP.octets().to_vec())); // we are the DNS server
+    let offered = your_ip(&reply);
+    assert_eq!(offered, Ipv4Addr::new(192, 168, 4, 2));
+
+    let request = dhcp_packet(3, mac, &[50, 4, 192, 168, 4, 2]);
+    server.handle(&request, &mut reply).unwrap();
+    assert_eq!(reply_option(&reply, 53), Some(vec![5])); // ACK
+    assert_eq!(your_ip(&reply), offered);
+
+    // Asking again gives the same address
+    server.handle(&dhcp_packet(1, mac, &[]), &mut reply).unwrap();
+    assert_eq!(your_ip(&reply), offered);
+}
+
+#[test]
+fn test_request_for_unknown_address_is_refused() {
+    let mut server = DhcpServer::new(AP_IP);
+    let mut reply = [0u8; DHCP_REPLY_LEN];
+
+    // The phone remembers an address from its home network
+    let request = dhcp_packet(3, [2, 0, 0, 0, 0, 9], &[50, 4, 10, 0, 0, 7]);
+    server.handle(&request, &mut reply).unwrap();
+    assert_eq!(reply_option(&reply, 53), Some(vec![6])); // NAK
+    assert_eq!(your_ip(&reply), Ipv4Addr::UNSPECIFIED);
+}
+
+#[test]
+fn test_leases_are_distinct_and_reused() {
+    let mut server = DhcpServer::new(AP_IP);
+    let mut reply = [0u8; DHCP_REPLY_LEN];
+
+    let mut given = Vec::new();
+    for i in 0..MAX_LEASES as u8 {
+        server.handle(&dhcp_packet(1, [2, 0, 0, 0, 0, i], &[]), &mut reply).unwrap();
+        given.push(your_ip(&reply));
+    }
+    given.dedup();
+    assert_eq!(given.len(), MAX_LEASES);
+
+    // Release frees the address for the next phone
+    assert!(server.handle(&dhcp_packet(7, [2, 0, 0, 0, 0, 0], &[]), &mut reply).is_none());
+    server.handle(&dhcp_packet(1, [2, 0, 0, 0, 0, 99], &[]), &mut reply).unwrap();
+    assert_eq!(your_ip(&reply), Ipv4Addr::new(192, 168, 4, 2));
+
+    // Garbage is ignored
+    assert!(server.handle(&[0u8; 100], &mut reply).is_none());
+}
+
+#[test]
+fn test_dns_answers_everything_with_our_address() {
+    let mut reply = [0u8; 512];
+    let query = dns_query("connectivitycheck.gstatic.com", 1);
+    let len = dns_reply(&query, AP_IP, &mut reply).unwrap();
+
+    assert_eq!(len, query.len() + 16);
+    assert_eq!(&reply[0..2], &[0x12, 0x34]);
+    assert_eq!(reply[2] & 0x80, 0x80); // response
+    assert_eq!(&reply[6..8], &[0, 1]); // one answer
+    assert_eq!(&reply[12..query.len()], &query[12..]);
+    assert_eq!(&reply[len - 4..len], &AP_IP.octets());
+}
+
+#[test]
+fn test_dns_other_queries() {
+    let mut reply = [0u8; 512];
+
+    // AAAA gets an empty answer, so the phone falls back to IPv4
+    let query = dns_query("example.com", 28);
+    let len = dns_reply(&query, AP_IP, &mut reply).unwrap();
+    assert_eq!(len, query.len());
+    assert_eq!(&reply[6..8], &[0, 0]);
+
+    // Responses and truncated packets are ignored
+    let mut response = dns_query("example.com", 1);
+    response[2] |= 0x80;
+    assert!(dns_reply(&response, AP_IP, &mut reply).is_none());
+    assert!(dns_reply(&dns_query("example.com", 1)[..20], AP_IP, &mut reply).is_none());
+}
@@ -0,0 +1,90 @@
+// tests/color_tests.rs
+
+// Host tests for colour parsing, native format conversion, gamma and brightness
+
+use core::convert::Infallible;
+use firmware_core::color::{
+    parse_hex, to_pwm_duty, to_rgb444, to_rgb565, ColorCorrection, ParseColorError, Rgb, GAMMA8,
+};
+use firmware_core::display::{LedChain, LedMatrix, Wiring, LED_COUNT};
+use firmware_core::render::Panel;
+
+#[derive(Default)]
+struct MockChain {
+    last: Vec<Rgb>,
+}
+
+impl LedChain for MockChain {
+    type Error = Infallible;
+
+    fn write(&mut self, leds: &[Rgb; LED_COUNT]) -> Result<(), Self::Error> {
+        self.last = leds.to_vec();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_hex() {
+    assert_eq!(parse_hex("#1a2B3c"), Ok(Rgb::new(0x1A, 0x2B, 0x3C)));
+    assert_eq!(parse_hex("#12345"), Err(ParseColorError::WrongLength));
+    assert_eq!(parse_hex("#12345Z"), Err(ParseColorError::InvalidHexDigit));
+}
+
+#[test]
+fn test_rgb565() {
+    assert_eq!(to_rgb565(Rgb::WHITE), 0xFFFF);
+    assert_eq!(to_rgb565(Rgb::BLACK), 0x0000);
+    assert_eq!(to_rgb565(Rgb::new(255, 0, 0)), 0xF800);
+    assert_eq!(to_rgb565(Rgb::new(0, 255, 0)), 0x07E0);
+    assert_eq!(to_rgb565(Rgb::new(0, 0, 255)), 0x001F);
+}
+
+#[test]
+fn test_rgb444() {
+    assert_eq!(to_rgb444(Rgb::WHITE), 0x0FFF);
+    assert_eq!(to_rgb444(Rgb::new(0x12, 0x34, 0x56)), 0x0135);
+}
+
+#[test]
+fn test_pwm_duty() {
+    assert_eq!(to_pwm_duty(Rgb::WHITE, 10), [1023, 1023, 1023]);
+    assert_eq!(to_pwm_duty(Rgb::new(0, 128, 255), 8), [0, 128, 255]);
+    assert_eq!(to_pwm_duty(Rgb::new(128, 0, 0), 16)[0], 32896);
+}
+
+#[test]
+fn test_gamma_table_is_monotonic_with_fixed_ends() {
+    assert_eq!(GAMMA8[0], 0);
+    assert_eq!(GAMMA8[255], 255);
+    assert!(GAMMA8.windows(2).all(|pair| pair[0] <= pair[1]));
+    // Mid grey is much darker once linearised
+    assert!(GAMMA8[128] < 64);
+}
+
+#[test]
+fn test_correction_brightness_and_gamma() {
+    // Default is a no-op
+    let color = Rgb::new(10, 128, 250);
+    assert_eq!(ColorCorrection::default().apply(color), color);
+
+    let half = ColorCorrection::new(128, false);
+    assert_eq!(half.apply(Rgb::WHITE), Rgb::new(128, 128, 128));
+    assert_eq!(ColorCorrection::new(0, false).apply(Rgb::WHITE), Rgb::BLACK);
+
+    let gamma = ColorCorrection::new(255, true);
+    assert_eq!(gamma.apply(Rgb::new(128, 255, 0)), Rgb::new(GAMMA8[128], 255, 0));
+}
+
+#[test]
+fn test_matrix_applies_correction_only_on_output() {
+    let mut matrix = LedMatrix::new(MockChain::default(), Wiring::Progressive);
+    matrix.set_pixel(0, 0, Rgb::WHITE);
+    matrix.show().unwrap();
+    assert_eq!(matrix.chain().last[0], Rgb::WHITE);
+
+    // Changing the correction re-sends the same frame
+    matrix.set_correction(ColorCorrection::new(64, false));
+    matrix.show().unwrap();
+    assert_eq!(matrix.chain().last[0], Rgb::new(64, 64, 64));
+    assert_eq!(matrix.front()[0][0], Rgb::WHITE);
+}
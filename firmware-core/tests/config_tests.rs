@@ -0,0 +1,174 @@
+// tests/config_tests.rs
+
+// Host tests for the saved device config and the setup portal
+
+use core::net::Ipv4Addr;
+use firmware_core::config::{crc32, ConfigError, DeviceConfig, ServerAddress, ServerHost, CONFIG_RECORD_LEN};
+use firmware_core::portal::{
+    handle_request, parse_request, parse_setup_form, url_decode, write_form_response, write_saved_response,
+    Method, MAX_RESPONSE_LEN, ParsedRequest, PortalAction,
+};
+
+fn sample_config() -> DeviceConfig {
+    DeviceConfig::new("shortnet", "dictionary", "http://192.168.2.169:8080").unwrap()
+}
+
+#[test]
+fn test_crc32_known_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc32(b""), 0);
+}
+
+#[test]
+fn test_record_roundtrip() {
+    let config = sample_config();
+    let record = config.to_record();
+    assert_eq!(record.len(), CONFIG_RECORD_LEN);
+    assert_eq!(DeviceConfig::from_record(&record), Ok(config));
+
+    // Longest allowed values still fit
+    let long = DeviceConfig::new(&"s".repeat(32), &"p".repeat(64), "192.168.100.200:65535").unwrap();
+    assert_eq!(DeviceConfig::from_record(&long.to_record()), Ok(long));
+}
+
+#[test]
+fn test_record_errors() {
+    assert_eq!(DeviceConfig::from_record(&[0xFF; CONFIG_RECORD_LEN]), Err(ConfigError::Erased));
+    assert_eq!(DeviceConfig::from_record(&[0x00; CONFIG_RECORD_LEN]), Err(ConfigError::BadMagic));
+    assert_eq!(DeviceConfig::from_record(&[0xFF; 10]), Err(ConfigError::Corrupt));
+
+    let mut record = sample_config().to_record();
+    record[10] ^= 0x01;
+    assert_eq!(DeviceConfig::from_record(&record), Err(ConfigError::BadChecksum));
+
+    let mut record = sample_config().to_record();
+    record[4] = 99;
+    assert_eq!(DeviceConfig::from_record(&record), Err(ConfigError::UnsupportedVersion(99)));
+}
+
+#[test]
+fn test_config_validation() {
+    assert_eq!(DeviceConfig::new("", "dictionary", "10.0.0.1").unwrap_err(), ConfigError::MissingSsid);
+    assert_eq!(DeviceConfig::new(&"s".repeat(33), "", "10.0.0.1").unwrap_err(), ConfigError::SsidTooLong);
+    assert_eq!(DeviceConfig::new("net", "short", "10.0.0.1").unwrap_err(), ConfigError::BadPassword);
+    assert_eq!(DeviceConfig::new("net", "", "https://10.0.0.1").unwrap_err(), ConfigError::BadServer);
+
+    // Open networks have no password
+    assert!(DeviceConfig::new("cafe", "", "10.0.0.1").is_ok());
+
+    // No server means "find it on the LAN", and survives a trip through flash
+    let discover = DeviceConfig::new("cafe", "", "  ").unwrap();
+    assert_eq!(discover.server_address(), Ok(None));
+    assert_eq!(DeviceConfig::from_record(&discover.to_record()), Ok(discover));
+}
+
+#[test]
+fn test_server_address() {
+    let expected = ServerAddress { host: ServerHost::Ip(Ipv4Addr::new(192, 168, 2, 169)), port: 8080 };
+    assert_eq!(ServerAddress::parse("http://192.168.2.169:8080/"), Ok(expected.clone()));
+    assert_eq!(ServerAddress::parse("192.168.2.169:8080"), Ok(expected.clone()));
+    assert_eq!(ServerAddress::parse("192.168.2.169").unwrap().port, 80);
+    assert_eq!(expected.to_string(), "192.168.2.169:8080");
+
+    assert_eq!(ServerAddress::parse("192.168.2.169:0"), Err(ConfigError::BadServer));
+    assert_eq!(ServerAddress::parse("192.168.2:8080"), Err(ConfigError::BadServer));
+}
+
+#[test]
+fn test_server_hostname() {
+    let address = ServerAddress::parse("http://Canvas-Pi.local:8080/").unwrap();
+    assert_eq!(address.host, ServerHost::Name("Canvas-Pi.local".try_into().unwrap()));
+    assert!(address.host.is_mdns());
+    assert_eq!(address.to_string(), "Canvas-Pi.local:8080");
+
+    let address = ServerAddress::parse("canvas.example.com").unwrap();
+    assert_eq!(address.port, 80);
+    assert!(!address.host.is_mdns());
+    assert!(!ServerHost::Ip(Ipv4Addr::LOCALHOST).is_mdns());
+
+    for bad in ["under_score.lan", "-dash.lan", "double..dot", "space host", "https://canvas.lan"] {
+        assert_eq!(ServerAddress::parse(bad), Err(ConfigError::BadServer), "{}", bad);
+    }
+}
+
+#[test]
+fn test_url_decode() {
+    assert_eq!(url_decode::<32>("my+home%21%2Fwifi").unwrap().as_str(), "my home!/wifi");
+    assert_eq!(url_decode::<32>("caf%C3%A9").unwrap().as_str(), "café");
+    assert!(url_decode::<32>("bad%2").is_none());
+    assert!(url_decode::<4>("toolong").is_none());
+}
+
+#[test]
+fn test_setup_form() {
+    let config = parse_setup_form("ssid=my+net&password=secret%2Bpass&server=http%3A%2F%2F10.0.0.5%3A8080").unwrap();
+    assert_eq!(config.ssid.as_str(), "my net");
+    assert_eq!(config.password.as_str(), "secret+pass");
+    assert_eq!(config.server_address().unwrap().unwrap().to_string(), "10.0.0.5:8080");
+
+    assert_eq!(parse_setup_form("password=whatever1&server=10.0.0.5"), Err(ConfigError::MissingSsid));
+}
+
+#[test]
+fn test_parse_request_waits_for_body() {
+    let form = "ssid=net&password=&server=10.0.0.5";
+    let raw = format!(
+        "POST /save HTTP/1.1\r\nHost: 192.168.4.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{}",
+        form.len(),
+        form
+    );
+
+    // Headers only
+    let headers_end = raw.find("\r\n\r\n").unwrap() + 4;
+    assert_eq!(parse_request(&raw.as_bytes()[..headers_end - 2]), ParsedRequest::Incomplete);
+    assert_eq!(parse_request(&raw.as_bytes()[..headers_end + 3]), ParsedRequest::Incomplete);
+
+    let ParsedRequest::Complete(request) = parse_request(raw.as_bytes()) else {
+        panic!("request should be complete");
+    };
+    assert_eq!(request.method, Method::Post);
+    assert_eq!(request.path, "/save");
+    assert_eq!(request.body, form);
+
+    match handle_request(&request) {
+        PortalAction::Save(config) => assert_eq!(config.ssid.as_str(), "net"),
+        other => panic!("expected Save, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_every_other_request_shows_the_form() {
+    // Android's connectivity check
+    let ParsedRequest::Complete(request) = parse_request(b"GET /generate_204 HTTP/1.1\r\nHost: connectivitycheck.gstatic.com\r\n\r\n") else {
+        panic!("request should be complete");
+    };
+    assert_eq!(handle_request(&request), PortalAction::ShowForm(None));
+
+    // A rejected form shows the form again with the reason
+    let ParsedRequest::Complete(request) =
+        parse_request(b"POST /save HTTP/1.1\r\nContent-Length: 24\r\n\r\nssid=ab&server=ftp%3A%2F%2F")
+    else {
+        panic!("request should be complete");
+    };
+    assert_eq!(handle_request(&request), PortalAction::ShowForm(Some(ConfigError::BadServer)));
+
+    assert_eq!(parse_request(b"GET\r\n\r\n"), ParsedRequest::Invalid);
+}
+
+#[test]
+fn test_responses_have_correct_length() {
+    let mut form = String::new();
+    write_form_response(&mut form, Some(ConfigError::BadPassword)).unwrap();
+    let mut saved = String::new();
+    write_saved_response(&mut saved, &DeviceConfig::new("<i>&", "", "10.0.0.1").unwrap()).unwrap();
+
+    for response in [&form, &saved] {
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains(&format!("Content-Length: {}", body.len())));
+        assert!(response.len() <= MAX_RESPONSE_LEN);
+    }
+    assert!(form.contains(&ConfigError::BadPassword.to_string()));
+    // The SSID is escaped before it goes into the page
+    assert!(saved.contains("&lt;i&gt;&amp;"));
+}
@@ -0,0 +1,100 @@
+// tests/device_tests.rs
+
+// Host tests for the register/heartbeat reports the board sends the backend
+
+use firmware_core::device::{
+    device_id, parse_report_reply, write_report_request, DeviceReport, ReportKind, Reporter, HEARTBEAT_INTERVAL_SECS,
+    MAX_REPORT_REQUEST_LEN,
+};
+use heapless::String;
+use protocol::{DeviceReportResponse, TestPattern};
+
+// Test helper to build the longest report the board can send
+fn long_report() -> DeviceReport {
+    DeviceReport {
+        device_id: device_id([0xFF; 6]),
+        firmware_version: "v".repeat(32).as_str().try_into().unwrap(),
+        uptime_secs: u64::MAX,
+        rssi: Some(i32::MIN),
+        last_applied: u64::MAX,
+    }
+}
+
+#[test]
+fn test_device_id_from_mac() {
+    assert_eq!(device_id([0xA1, 0xB2, 0xC3, 0xD4, 0xE5, 0xF6]).as_str(), "rustycanvas-a1b2c3d4e5f6");
+}
+
+#[test]
+fn test_report_request_matches_backend_type() {
+    let report = DeviceReport {
+        device_id: device_id([0xA1, 0xB2, 0xC3, 0xD4, 0xE5, 0xF6]),
+        firmware_version: "0.1.0".try_into().unwrap(),
+        uptime_secs: 3600,
+        rssi: None,
+        last_applied: 1_700_000_000_000,
+    };
+    let mut request: String<MAX_REPORT_REQUEST_LEN> = String::new();
+    write_report_request(&mut request, "192.168.2.169:8080", ReportKind::Heartbeat, &report).unwrap();
+
+    let (head, body) = request.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("POST /devices/heartbeat HTTP/1.1\r\n"));
+    assert!(head.contains("Host: 192.168.2.169:8080\r\n"));
+    assert!(head.contains("Content-Type: application/json\r\n"));
+    assert!(head.contains(&format!("Content-Length: {}\r\n", body.len())));
+
+    // The backend reads it as its own (heap-backed) type
+    let parsed: protocol::DeviceReport = serde_json::from_str(body).unwrap();
+    assert_eq!(parsed.device_id, "rustycanvas-a1b2c3d4e5f6");
+    assert_eq!(parsed.uptime_secs, 3600);
+    assert_eq!(parsed.rssi, None);
+    assert_eq!(parsed.last_applied, 1_700_000_000_000);
+}
+
+#[test]
+fn test_longest_report_fits() {
+    let mut request: String<MAX_REPORT_REQUEST_LEN> = String::new();
+    // A host header as long as firmware discovery allows
+    write_report_request(&mut request, &"h".repeat(72), ReportKind::Register, &long_report()).unwrap();
+    assert!(request.starts_with("POST /devices/register "));
+}
+
+#[test]
+fn test_reporter_schedule() {
+    let interval = HEARTBEAT_INTERVAL_SECS * 1000;
+    let mut reporter = Reporter::new();
+
+    // Register as soon as we're online, retrying on the next step if the connection dropped
+    assert_eq!(reporter.due(5_000), Some(ReportKind::Register));
+    assert_eq!(reporter.due(6_000), Some(ReportKind::Register));
+    reporter.on_response(ReportKind::Register, 200, 6_000);
+    assert!(reporter.is_registered());
+
+    assert_eq!(reporter.due(6_000 + interval - 1), None);
+    assert_eq!(reporter.due(6_000 + interval), Some(ReportKind::Heartbeat));
+    reporter.on_response(ReportKind::Heartbeat, 200, 6_000 + interval);
+    assert_eq!(reporter.due(6_000 + interval + 1), None);
+
+    // The server forgot us
+    reporter.on_response(ReportKind::Heartbeat, 404, 100_000);
+    assert_eq!(reporter.due(100_000), Some(ReportKind::Register));
+
+    // A server without the device routes isn't asked again until the next interval
+    reporter.on_response(ReportKind::Register, 404, 100_000);
+    assert!(!reporter.is_registered());
+    assert_eq!(reporter.due(100_001), None);
+    assert_eq!(reporter.due(100_000 + interval), Some(ReportKind::Register));
+}
+
+#[test]
+fn test_report_reply_carries_test_pattern() {
+    let reply = DeviceReportResponse { success: true, error: None, test_pattern: Some(TestPattern::PixelWalk) };
+    assert_eq!(parse_report_reply(&serde_json::to_vec(&reply).unwrap()), Some(TestPattern::PixelWalk));
+
+    let reply = DeviceReportResponse { success: true, error: None, test_pattern: None };
+    assert_eq!(parse_report_reply(&serde_json::to_vec(&reply).unwrap()), None);
+
+    // Servers from before test patterns, and patterns this firmware doesn't know
+    assert_eq!(parse_report_reply(br#"{"success":true,"error":null}"#), None);
+    assert_eq!(parse_report_reply(br#"{"success":true,"error":null,"test_pattern":"rainbow"}"#), None);
+}
@@ -0,0 +1,167 @@
+// tests/discovery_tests.rs
+
+// Host tests for the DNS and mDNS lookups that find the backend
+
+use core::net::Ipv4Addr;
+use firmware_core::discovery::{
+    parse_a_answer, parse_service_answer, write_a_query, write_service_query, MAX_QUERY_LEN, MDNS_SERVICE_TYPE,
+};
+
+// Test helper to encode 'name' as DNS labels
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for label in name.split('.') {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    out
+}
+
+// Test helper to build a pointer to offset 'at'
+fn pointer(at: usize) -> Vec<u8> {
+    vec![0xC0 | (at >> 8) as u8, at as u8]
+}
+
+// Test helper to build one resource record
+fn record(name: &[u8], rtype: u16, class: u16, data: &[u8]) -> Vec<u8> {
+    let mut out = name.to_vec();
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&120u32.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+// Test helper to build a response header
+fn header(id: u16, questions: u16, answers: u16, additional: u16) -> Vec<u8> {
+    let mut out = id.to_be_bytes().to_vec();
+    out.extend_from_slice(&[0x84, 0x00]);
+    for count in [questions, answers, 0, additional] {
+        out.extend_from_slice(&count.to_be_bytes());
+    }
+    out
+}
+
+// Test helper to build what the backend's mDNS responder sends back for a service query,
+// with or without the host's A record
+fn service_response(id: u16, with_address: bool) -> Vec<u8> {
+    let mut packet = header(id, 1, 1, if with_address { 3 } else { 2 });
+    // Question, echoed back (one-shot queries get it repeated)
+    let service_at = packet.len();
+    packet.extend(encode_name(MDNS_SERVICE_TYPE));
+    packet.extend_from_slice(&[0, 12, 0, 1]);
+
+    // Answer: service PTR instance, where the instance name ends with a pointer to the service name
+    let mut instance = vec![11];
+    instance.extend_from_slice(b"RustyCanvas");
+    instance.extend(pointer(service_at));
+    let ptr = record(&pointer(service_at), 12, 1, &instance);
+    let instance_at = packet.len() + 2 + 10;
+    packet.extend(ptr);
+
+    // TXT record first, to check the SRV is searched for rather than assumed to come next
+    packet.extend(record(&pointer(instance_at), 16, 0x8001, &[0]));
+
+    // SRV: priority, weight, port, target "Laptop.local" (with "local" taken from the service name)
+    let mut srv = vec![0, 0, 0, 0];
+    srv.extend_from_slice(&8080u16.to_be_bytes());
+    srv.push(6);
+    srv.extend_from_slice(b"Laptop");
+    srv.extend(pointer(service_at + 1 + 12 + 1 + 4));
+    packet.extend(record(&pointer(instance_at), 33, 0x8001, &srv));
+
+    if with_address {
+        // The responder writes the name in lower case, names compare case-insensitively
+        let mut name = vec![6];
+        name.extend_from_slice(b"laptop");
+        name.extend(pointer(service_at + 1 + 12 + 1 + 4));
+        packet.extend(record(&name, 1, 0x8001, &[192, 168, 2, 169]));
+    }
+    packet
+}
+
+#[test]
+fn test_a_query_layout() {
+    let mut out = [0u8; MAX_QUERY_LEN];
+    let len = write_a_query(0xBEEF, "canvas.lan", true, &mut out).unwrap();
+
+    let mut expected = vec![0xBE, 0xEF, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    expected.extend(encode_name("canvas.lan"));
+    expected.extend_from_slice(&[0, 1, 0, 1]);
+    assert_eq!(&out[..len], &expected[..]);
+
+    // mDNS lookups don't ask for recursion, and a trailing dot is allowed
+    let len = write_a_query(7, "laptop.local.", false, &mut out).unwrap();
+    assert_eq!(out[2], 0);
+    assert_eq!(&out[12..len - 4], &encode_name("laptop.local")[..]);
+
+    assert!(write_a_query(1, "bad..name", true, &mut out).is_none());
+    assert!(write_a_query(1, "canvas.lan", true, &mut out[..20]).is_none());
+}
+
+#[test]
+fn test_service_query_layout() {
+    let mut out = [0u8; MAX_QUERY_LEN];
+    let len = write_service_query(0, &mut out).unwrap();
+    assert_eq!(&out[12..len - 4], &encode_name("_rustycanvas._tcp.local")[..]);
+    // PTR, class IN
+    assert_eq!(&out[len - 4..len], &[0, 12, 0, 1]);
+}
+
+#[test]
+fn test_parse_a_answer_follows_cname() {
+    let mut packet = header(0x1234, 1, 2, 0);
+    let question_at = packet.len();
+    packet.extend(encode_name("canvas.example.com"));
+    packet.extend_from_slice(&[0, 1, 0, 1]);
+    packet.extend(record(&pointer(question_at), 5, 1, &encode_name("host.example.net")));
+    packet.extend(record(&encode_name("host.example.net"), 1, 1, &[10, 0, 0, 7]));
+
+    assert_eq!(parse_a_answer(&packet, 0x1234), Some(Ipv4Addr::new(10, 0, 0, 7)));
+    // Somebody else's answer
+    assert_eq!(parse_a_answer(&packet, 0x1235), None);
+
+    // NXDOMAIN
+    packet[3] = 0x83;
+    assert_eq!(parse_a_answer(&packet, 0x1234), None);
+    // Truncated
+    assert_eq!(parse_a_answer(&packet[..packet.len() - 2], 0x1234), None);
+}
+
+#[test]
+fn test_parse_service_answer() {
+    let packet = service_response(42, true);
+    let answer = parse_service_answer(&packet, 42).unwrap();
+    assert_eq!(answer.host.as_str(), "Laptop.local");
+    assert_eq!(answer.port, 8080);
+    assert_eq!(answer.ip, Some(Ipv4Addr::new(192, 168, 2, 169)));
+
+    assert_eq!(parse_service_answer(&packet, 43), None);
+}
+
+#[test]
+fn test_parse_service_answer_without_address() {
+    // The board then asks for the host's address itself
+    let answer = parse_service_answer(&service_response(42, false), 42).unwrap();
+    assert_eq!(answer.host.as_str(), "Laptop.local");
+    assert_eq!(answer.ip, None);
+}
+
+#[test]
+fn test_other_services_are_ignored() {
+    let mut packet = header(9, 0, 1, 0);
+    let instance = encode_name("Printer._ipp._tcp.local");
+    packet.extend(record(&encode_name("_ipp._tcp.local"), 12, 1, &instance));
+    assert_eq!(parse_service_answer(&packet, 9), None);
+}
+
+#[test]
+fn test_pointer_loop_is_rejected() {
+    let mut packet = header(5, 0, 1, 0);
+    let answer_at = packet.len();
+    // The PTR's owner name points at itself
+    packet.extend(record(&pointer(answer_at), 12, 1, &pointer(answer_at)));
+    assert_eq!(parse_service_answer(&packet, 5), None);
+}
@@ -0,0 +1,139 @@
+// tests/display_tests.rs
+
+// Host tests for the LED matrix driver, using a mock SPI bus and a mock LED chain
+
+use core::convert::Infallible;
+use embedded_hal::spi::{ErrorType, SpiBus};
+use firmware_core::display::{
+    encode_ws2812_byte, LedChain, LedMatrix, Wiring, Ws2812Spi, LED_COUNT, WS2812_FRAME_BYTES,
+};
+use firmware_core::render::{render_frame, Panel};
+use protocol::{CanvasFrame, Rgb};
+
+// Records every SPI transfer
+#[derive(Default)]
+struct MockSpi {
+    writes: Vec<Vec<u8>>,
+}
+
+impl ErrorType for MockSpi {
+    type Error = Infallible;
+}
+
+impl SpiBus for MockSpi {
+    fn read(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.writes.push(words.to_vec());
+        Ok(())
+    }
+
+    fn transfer(&mut self, _read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.write(write)
+    }
+
+    fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Records every frame pushed down the chain
+#[derive(Default)]
+struct MockChain {
+    frames: Vec<Vec<Rgb>>,
+}
+
+impl LedChain for MockChain {
+    type Error = Infallible;
+
+    fn write(&mut self, leds: &[Rgb; LED_COUNT]) -> Result<(), Self::Error> {
+        self.frames.push(leds.to_vec());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_ws2812_bit_encoding() {
+    // 1 -> 110, 0 -> 100
+    assert_eq!(encode_ws2812_byte(0x00), [0b1001_0010, 0b0100_1001, 0b0010_0100]);
+    assert_eq!(encode_ws2812_byte(0xFF), [0b1101_1011, 0b0110_1101, 0b1011_0110]);
+    // 0x80 = 1000_0000
+    assert_eq!(encode_ws2812_byte(0x80), [0b1101_0010, 0b0100_1001, 0b0010_0100]);
+}
+
+#[test]
+fn test_ws2812_spi_sends_grb_then_latch_in_one_transfer() {
+    let mut chain = Ws2812Spi::new(MockSpi::default());
+    let mut leds = [Rgb::BLACK; LED_COUNT];
+    leds[0] = Rgb::new(0xFF, 0x00, 0x80);
+
+    chain.write(&leds).unwrap();
+
+    let encoded = chain.encoded();
+    assert_eq!(encoded.len(), WS2812_FRAME_BYTES);
+    assert_eq!(encoded[0..3], encode_ws2812_byte(0x00)); // green first
+    assert_eq!(encoded[3..6], encode_ws2812_byte(0xFF)); // then red
+    assert_eq!(encoded[6..9], encode_ws2812_byte(0x80)); // then blue
+    assert!(encoded[LED_COUNT * 9..].iter().all(|&b| b == 0), "latch must be all zero");
+}
+
+#[test]
+fn test_serpentine_wiring() {
+    assert_eq!(Wiring::Serpentine.chain_index(0, 0), 0);
+    assert_eq!(Wiring::Serpentine.chain_index(31, 0), 31);
+    // Second row runs right to left
+    assert_eq!(Wiring::Serpentine.chain_index(31, 1), 32);
+    assert_eq!(Wiring::Serpentine.chain_index(0, 1), 63);
+    assert_eq!(Wiring::Progressive.chain_index(0, 1), 32);
+}
+
+#[test]
+fn test_matrix_double_buffers() {
+    let mut matrix = LedMatrix::new(MockChain::default(), Wiring::Serpentine);
+
+    matrix.set_pixel(0, 1, Rgb::WHITE);
+    // Drawing only touches the back buffer
+    assert_eq!(matrix.front()[1][0], Rgb::BLACK);
+    assert!(matrix.chain().frames.is_empty());
+
+    matrix.show().unwrap();
+    assert_eq!(matrix.front()[1][0], Rgb::WHITE);
+    assert_eq!(matrix.chain().frames.len(), 1);
+    // (0, 1) is the end of the reversed second row
+    assert_eq!(matrix.chain().frames[0][63], Rgb::WHITE);
+}
+
+#[test]
+fn test_matrix_skips_unchanged_frames() {
+    let mut matrix = LedMatrix::new(MockChain::default(), Wiring::Progressive);
+    let mut frame = CanvasFrame::default();
+
+    // First show always transmits so the LEDs match the buffer after power-on
+    render_frame(&frame, &mut matrix).unwrap();
+    render_frame(&frame, &mut matrix).unwrap();
+    assert_eq!(matrix.chain().frames.len(), 1);
+
+    frame.set(5, 5, Rgb::new(1, 2, 3));
+    render_frame(&frame, &mut matrix).unwrap();
+    assert_eq!(matrix.chain().frames.len(), 2);
+    assert_eq!(matrix.chain().frames[1][5 * 32 + 5], Rgb::new(1, 2, 3));
+}
+
+#[test]
+fn test_matrix_over_spi_end_to_end() {
+    let mut matrix = LedMatrix::new(Ws2812Spi::new(MockSpi::default()), Wiring::Serpentine);
+    let mut frame = CanvasFrame::default();
+    frame.set(0, 0, Rgb::new(0, 0xFF, 0));
+
+    render_frame(&frame, &mut matrix).unwrap();
+
+    let encoded = matrix.chain().encoded();
+    assert_eq!(encoded[0..3], encode_ws2812_byte(0xFF));
+    assert_eq!(encoded[3..9], [encode_ws2812_byte(0), encode_ws2812_byte(0)].concat());
+}
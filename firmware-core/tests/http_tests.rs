@@ -0,0 +1,181 @@
+// tests/http_tests.rs
+
+// Host tests for the firmware's HTTP/1.1 client
+// The fixtures in tests/fixtures/ are raw responses captured from the backend with a plain TCP socket
+
+use firmware_core::canvas::{parse_canvas_response, CanvasStream};
+use firmware_core::http::{write_get_request, ResponseParser};
+use firmware_core::stream::StreamError;
+use firmware_core::updates::UpdatesStream;
+use protocol::{CanvasFrame, Rgb};
+
+const CANVAS_200: &[u8] = include_bytes!("fixtures/canvas_200.http");
+const UPDATES_200: &[u8] = include_bytes!("fixtures/updates_200.http");
+// Two responses read back to back from one kept-alive connection
+const UPDATES_KEEP_ALIVE: &[u8] = include_bytes!("fixtures/updates_keep_alive.http");
+const NOT_FOUND_404: &[u8] = include_bytes!("fixtures/not_found_404.http");
+
+// Test helper to feed a whole response in 'chunk_size' pieces, returning the body and the bytes used
+fn read_body(parser: &mut ResponseParser, raw: &[u8], chunk_size: usize) -> Result<(Vec<u8>, usize), StreamError> {
+    let mut body = Vec::new();
+    let mut used = 0;
+    for chunk in raw.chunks(chunk_size) {
+        used += parser.feed(chunk, |bytes| {
+            body.extend_from_slice(bytes);
+            Ok(())
+        })?;
+        if parser.is_complete() {
+            break;
+        }
+    }
+    Ok((body, used))
+}
+
+// Test helper to re-encode a body with chunked transfer encoding, 'size' bytes per chunk
+fn chunked_response(body: &[u8], size: usize) -> Vec<u8> {
+    let mut raw = b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    for (i, chunk) in body.chunks(size).enumerate() {
+        // Chunk extensions are legal and must be ignored
+        let ext = if i == 0 { ";name=value" } else { "" };
+        raw.extend_from_slice(format!("{:X}{}\r\n", chunk.len(), ext).as_bytes());
+        raw.extend_from_slice(chunk);
+        raw.extend_from_slice(b"\r\n");
+    }
+    raw.extend_from_slice(b"0\r\nx-trailer: yes\r\n\r\n");
+    raw
+}
+
+fn body_of(raw: &[u8]) -> &[u8] {
+    let start = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    &raw[start..]
+}
+
+#[test]
+fn test_write_get_request() {
+    let mut request = String::new();
+    write_get_request(&mut request, "192.168.2.169:8080", "/updates?since=5", true).unwrap();
+    assert_eq!(
+        request,
+        "GET /updates?since=5 HTTP/1.1\r\nHost: 192.168.2.169:8080\r\nAccept: application/json\r\nConnection: keep-alive\r\n\r\n"
+    );
+
+    let mut request = String::new();
+    write_get_request(&mut request, "h", "/canvas", false).unwrap();
+    assert!(request.contains("Connection: close\r\n"));
+}
+
+#[test]
+fn test_content_length_response() {
+    for chunk_size in [1, 2, 5, 64, 512, CANVAS_200.len()] {
+        let mut parser = ResponseParser::new();
+        let (body, used) = read_body(&mut parser, CANVAS_200, chunk_size).unwrap();
+
+        assert_eq!(parser.status(), Some(200));
+        assert_eq!(parser.content_length(), Some(body.len() as u64));
+        assert!(parser.is_complete());
+        // The capture asked for 'Connection: close'
+        assert!(!parser.keep_alive());
+        assert_eq!(used, CANVAS_200.len());
+        assert_eq!(body, body_of(CANVAS_200));
+    }
+}
+
+#[test]
+fn test_keep_alive_responses_share_a_connection() {
+    let mut first = ResponseParser::new();
+    let used = first.feed(UPDATES_KEEP_ALIVE, |_| Ok(())).unwrap();
+    assert!(first.is_complete());
+    assert!(first.keep_alive());
+
+    // Whatever the first parser didn't use is the start of the second response
+    let mut second = ResponseParser::new();
+    let (body, used_second) = read_body(&mut second, &UPDATES_KEEP_ALIVE[used..], 7).unwrap();
+    assert!(second.is_complete());
+    assert_eq!(used + used_second, UPDATES_KEEP_ALIVE.len());
+    assert!(body.starts_with(b"{\"updates\":"));
+}
+
+#[test]
+fn test_chunked_response() {
+    let expected = body_of(UPDATES_200);
+    let raw = chunked_response(expected, 50);
+
+    for chunk_size in 1..=raw.len() {
+        let mut parser = ResponseParser::new();
+        let (body, used) = read_body(&mut parser, &raw, chunk_size).unwrap();
+        assert!(parser.is_chunked());
+        assert!(parser.is_complete(), "chunk size {}", chunk_size);
+        assert_eq!(used, raw.len());
+        assert_eq!(body, expected);
+    }
+}
+
+#[test]
+fn test_bad_chunks() {
+    let mut parser = ResponseParser::new();
+    let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nZZ\r\n";
+    assert_eq!(parser.feed(raw, |_| Ok(())), Err(StreamError::BadChunk));
+
+    // Chunk data longer than announced
+    let mut parser = ResponseParser::new();
+    let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nabc\r\n";
+    assert_eq!(parser.feed(raw, |_| Ok(())), Err(StreamError::BadChunk));
+}
+
+#[test]
+fn test_close_delimited_response() {
+    let raw = b"HTTP/1.0 200 OK\r\ncontent-type: application/json\r\n\r\n{\"a\":1}";
+    let mut parser = ResponseParser::new();
+    let (body, _) = read_body(&mut parser, raw, 3).unwrap();
+
+    // Only the server closing the connection ends this body
+    assert!(!parser.is_complete());
+    assert!(!parser.keep_alive());
+    assert_eq!(parser.finish(), Ok(()));
+    assert!(parser.is_complete());
+    assert!(!parser.keep_alive());
+    assert_eq!(body, b"{\"a\":1}");
+}
+
+#[test]
+fn test_non_200_is_an_error() {
+    let mut parser = ResponseParser::new();
+    assert_eq!(parser.feed(NOT_FOUND_404, |_| Ok(())), Err(StreamError::HttpStatus(404)));
+    assert_eq!(parser.status(), Some(404));
+
+    let mut parser = ResponseParser::new();
+    assert_eq!(parser.feed(b"SPDY/3 200 OK\r\n", |_| Ok(())), Err(StreamError::BadStatusLine));
+
+    let mut parser = ResponseParser::new();
+    assert_eq!(parser.feed(b"HTTP/1.1 200 OK\r\nContent-Length: lots\r\n", |_| Ok(())), Err(StreamError::BadHeader));
+}
+
+#[test]
+fn test_truncated_response() {
+    let mut parser = ResponseParser::new();
+    parser.feed(&CANVAS_200[..CANVAS_200.len() - 10], |_| Ok(())).unwrap();
+    assert!(!parser.is_complete());
+    assert_eq!(parser.finish(), Err(StreamError::Incomplete));
+}
+
+#[test]
+fn test_streams_on_captured_responses() {
+    // Streaming a captured /canvas response gives the same frame as the buffered parser
+    let mut frame = CanvasFrame::default();
+    let mut stream = CanvasStream::new();
+    for chunk in CANVAS_200.chunks(512) {
+        stream.feed(chunk, &mut frame).unwrap();
+    }
+    assert!(stream.is_complete());
+    stream.finish().unwrap();
+    assert_eq!(frame, parse_canvas_response(CANVAS_200).unwrap());
+
+    // And a chunked /updates response is applied like a plain one
+    let mut frame = CanvasFrame::default();
+    let mut stream = UpdatesStream::new();
+    stream.feed(&chunked_response(body_of(UPDATES_200), 16), &mut frame).unwrap();
+    let summary = stream.finish().unwrap();
+    assert_eq!(summary.applied, 2);
+    assert_eq!(frame.get(3, 4), Some(Rgb::new(0xFF, 0x88, 0x00)));
+    assert_eq!(frame.get(31, 15), Some(Rgb::new(0x00, 0xFF, 0x00)));
+}
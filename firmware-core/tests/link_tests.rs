@@ -0,0 +1,118 @@
+// tests/link_tests.rs
+
+// Host tests for the firmware's reconnection state machine
+
+use firmware_core::link::{Backoff, Link, LinkEvent, LinkState, MAX_CONNECT_FAILURES};
+
+const POLL_MS: u64 = 1000;
+
+// Test helper to walk a fresh link all the way to Online
+fn online_link() -> Link {
+    let mut link = Link::new(POLL_MS);
+    for event in [LinkEvent::WifiJoined, LinkEvent::AddressAcquired, LinkEvent::ServerFound, LinkEvent::Connected] {
+        assert_eq!(link.on(event), 0);
+    }
+    assert_eq!(link.state(), LinkState::Online);
+    link
+}
+
+#[test]
+fn test_backoff_doubles_up_to_a_minute() {
+    let mut backoff = Backoff::new();
+    let delays: Vec<u64> = (0..10).map(|_| backoff.fail()).collect();
+    assert_eq!(delays, [1000, 2000, 4000, 8000, 16000, 32000, 60000, 60000, 60000, 60000]);
+
+    // Never overflows, however long the router is gone
+    for _ in 0..100 {
+        assert_eq!(backoff.fail(), 60000);
+    }
+    backoff.reset();
+    assert_eq!(backoff.fail(), 1000);
+}
+
+#[test]
+fn test_happy_path() {
+    let link = Link::new(POLL_MS);
+    assert_eq!(link.state(), LinkState::JoiningWifi);
+    assert!(link.status_color().is_some());
+
+    let mut link = online_link();
+    assert_eq!(link.status_color(), None);
+    assert_eq!(link.on(LinkEvent::PollSucceeded), POLL_MS);
+    assert_eq!(link.state(), LinkState::Online);
+}
+
+#[test]
+fn test_wifi_retries_back_off() {
+    let mut link = Link::new(POLL_MS);
+    assert_eq!(link.on(LinkEvent::WifiFailed), 1000);
+    assert_eq!(link.on(LinkEvent::WifiFailed), 2000);
+    assert_eq!(link.on(LinkEvent::WifiFailed), 4000);
+    assert_eq!(link.state(), LinkState::JoiningWifi);
+    // Shown in red once it keeps failing
+    assert_eq!(link.status_color(), Some(protocol::Rgb::new(255, 0, 0)));
+
+    // Success resets the backoff
+    link.on(LinkEvent::WifiJoined);
+    assert_eq!(link.failures(), 0);
+    assert_eq!(link.on(LinkEvent::AddressTimeout), 1000);
+    assert_eq!(link.state(), LinkState::JoiningWifi);
+}
+
+#[test]
+fn test_losing_wifi_starts_over() {
+    for state_event in [None, Some(LinkEvent::PollFailed)] {
+        let mut link = online_link();
+        if let Some(event) = state_event {
+            link.on(event);
+        }
+        assert_eq!(link.on(LinkEvent::WifiLost), 0);
+        assert_eq!(link.state(), LinkState::JoiningWifi);
+        assert_eq!(link.failures(), 0);
+    }
+}
+
+#[test]
+fn test_poll_failure_reconnects() {
+    let mut link = online_link();
+
+    // A dropped kept-alive connection is normal, reconnect at once
+    assert_eq!(link.on(LinkEvent::PollFailed), 0);
+    assert_eq!(link.state(), LinkState::Connecting);
+
+    // A server that accepts connections but fails every request is backed off from
+    assert_eq!(link.on(LinkEvent::Connected), 0);
+    assert_eq!(link.on(LinkEvent::PollFailed), 2000);
+    assert_eq!(link.on(LinkEvent::Connected), 0);
+    assert_eq!(link.on(LinkEvent::PollFailed), 4000);
+
+    assert_eq!(link.on(LinkEvent::Connected), 0);
+    assert_eq!(link.on(LinkEvent::PollSucceeded), POLL_MS);
+    assert_eq!(link.failures(), 0);
+}
+
+#[test]
+fn test_repeated_connect_failures_look_the_server_up_again() {
+    let mut link = online_link();
+    link.on(LinkEvent::PollFailed);
+
+    for _ in 1..MAX_CONNECT_FAILURES {
+        link.on(LinkEvent::ConnectFailed);
+        assert_eq!(link.state(), LinkState::Connecting);
+    }
+    link.on(LinkEvent::ConnectFailed);
+    assert_eq!(link.state(), LinkState::FindingServer);
+
+    // The count starts over for the (possibly new) address
+    link.on(LinkEvent::ServerFound);
+    link.on(LinkEvent::ConnectFailed);
+    assert_eq!(link.state(), LinkState::Connecting);
+}
+
+#[test]
+fn test_progress_events() {
+    assert!(LinkEvent::PollSucceeded.is_progress());
+    assert!(LinkEvent::WifiJoined.is_progress());
+    assert!(!LinkEvent::PollFailed.is_progress());
+    assert!(!LinkEvent::WifiLost.is_progress());
+}
@@ -0,0 +1,59 @@
+// tests/patterns_tests.rs
+
+// Host tests for the display test patterns
+
+use firmware_core::patterns::{draw_step, step_count, walk_position, FULL_WHITE_MS, PIXEL_WALK_STEP_MS};
+use protocol::{CanvasFrame, Rgb, TestPattern, PALETTE};
+
+// Test helper to count the pixels of 'frame' set to 'color'
+fn count(frame: &CanvasFrame, color: Rgb) -> usize {
+    frame.pixels.iter().flatten().filter(|pixel| **pixel == color).count()
+}
+
+#[test]
+fn test_pixel_walk_visits_every_pixel_once() {
+    let mut frame = CanvasFrame::default();
+    let steps = step_count(TestPattern::PixelWalk, &frame);
+    assert_eq!(steps, frame.width * frame.height);
+
+    let mut visited = vec![false; steps as usize];
+    for step in 0..steps {
+        assert_eq!(draw_step(TestPattern::PixelWalk, step, &mut frame), PIXEL_WALK_STEP_MS);
+        // Exactly one pixel lit, the previous one cleared
+        assert_eq!(count(&frame, Rgb::WHITE), 1);
+        let (x, y) = walk_position(step, &frame);
+        assert_eq!(frame.get(x, y), Some(Rgb::WHITE));
+        visited[(y * frame.width + x) as usize] = true;
+    }
+    assert!(visited.iter().all(|seen| *seen));
+    assert_eq!(walk_position(33, &frame), (1, 1));
+}
+
+#[test]
+fn test_color_bars_use_the_palette() {
+    let mut frame = CanvasFrame::default();
+    assert_eq!(step_count(TestPattern::ColorBars, &frame), 1);
+    draw_step(TestPattern::ColorBars, 0, &mut frame);
+
+    let bar_width = frame.width as usize / PALETTE.len();
+    for (i, hex) in PALETTE.iter().enumerate() {
+        let color = Rgb::parse_hex(hex).unwrap();
+        assert_eq!(count(&frame, color), bar_width * frame.height as usize, "{}", hex);
+        assert_eq!(frame.get((i * bar_width) as u32, 0), Some(color));
+    }
+}
+
+#[test]
+fn test_full_white_and_gradient() {
+    let mut frame = CanvasFrame::default();
+    assert_eq!(draw_step(TestPattern::FullWhite, 0, &mut frame), FULL_WHITE_MS);
+    assert_eq!(count(&frame, Rgb::WHITE), (frame.width * frame.height) as usize);
+
+    draw_step(TestPattern::Gradient, 0, &mut frame);
+    assert_eq!(frame.get(0, 0), Some(Rgb::new(0, 0, 0)));
+    assert_eq!(frame.get(frame.width - 1, 0), Some(Rgb::new(255, 0, 0)));
+    assert_eq!(frame.get(frame.width - 1, frame.height - 1), Some(Rgb::new(255, 255, 0)));
+    // Brightness only goes up across a row
+    let row = frame.pixels[0];
+    assert!(row.windows(2).all(|pair| pair[0].r <= pair[1].r));
+}
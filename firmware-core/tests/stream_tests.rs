@@ -0,0 +1,202 @@
+// tests/stream_tests.rs
+
+// Host tests for the streaming parsers, feeding responses in every chunk size down to single bytes
+
+use firmware_core::canvas::{parse_canvas_response, CanvasStream};
+use firmware_core::stream::{Event, JsonTokenizer, StreamError};
+use firmware_core::updates::{CanvasSync, SyncStep, UpdatesStream};
+use protocol::{BoardMode, CanvasFrame, CanvasResponse, PixelUpdate, Rgb, UpdatesResponse, CANVAS_HEIGHT, CANVAS_WIDTH};
+
+// Test helper to build a GET /canvas response with a different colour in every pixel
+fn canvas_response() -> Vec<u8> {
+    let pixels = (0..CANVAS_HEIGHT)
+        .map(|y| (0..CANVAS_WIDTH).map(|x| Rgb::new(x as u8 * 8, y as u8 * 16, 0x42).to_string()).collect())
+        .collect();
+    let body = serde_json::to_string(&CanvasResponse { width: CANVAS_WIDTH, height: CANVAS_HEIGHT, pixels }).unwrap();
+    http_response(&body)
+}
+
+// Test helper to wrap a body the way axum sends it
+fn http_response(body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\ndate: Sat, 18 Oct 2026 10:00:00 GMT\r\n\r\n{}",
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+fn updates_response(updates: Vec<PixelUpdate>, reset_required: bool, latest_timestamp: u64) -> Vec<u8> {
+    http_response(&serde_json::to_string(&UpdatesResponse { updates, reset_required, latest_timestamp, shutting_down: false, mode: BoardMode::Open }).unwrap())
+}
+
+fn update(x: u32, y: u32, color: &str, timestamp: u64) -> PixelUpdate {
+    PixelUpdate { x, y, color: color.to_string(), timestamp }
+}
+
+// Test helper to record every event as text
+fn events(json: &str, chunk_size: usize) -> Vec<String> {
+    let mut tokenizer = JsonTokenizer::new();
+    let mut seen = Vec::new();
+    for chunk in json.as_bytes().chunks(chunk_size) {
+        tokenizer
+            .feed(chunk, |event, depth| {
+                seen.push(format!("{}:{:?}", depth, event));
+                Ok(())
+            })
+            .unwrap();
+    }
+    assert!(tokenizer.is_complete());
+    seen
+}
+
+#[test]
+fn test_tokenizer_events_and_depths() {
+    let json = r#"{"a": [1, -2.5e3, "x\"y"], "b": {"c": true, "d": null}, "e": false}"#;
+    let expected = vec![
+        "1:BeginObject",
+        "1:Key(\"a\")",
+        "2:BeginArray",
+        "2:Number(\"1\")",
+        "2:Number(\"-2.5e3\")",
+        "2:Str(\"x\\\"y\")",
+        "2:EndArray",
+        "1:Key(\"b\")",
+        "2:BeginObject",
+        "2:Key(\"c\")",
+        "2:Bool(true)",
+        "2:Key(\"d\")",
+        "2:Null",
+        "2:EndObject",
+        "1:Key(\"e\")",
+        "1:Bool(false)",
+        "1:EndObject",
+    ];
+
+    // Every split point must give the same events
+    for chunk_size in 1..=json.len() {
+        assert_eq!(events(json, chunk_size), expected, "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn test_tokenizer_rejects_bad_json() {
+    let mut tokenizer = JsonTokenizer::new();
+    assert_eq!(tokenizer.feed(b"{\"a\": [1}", |_, _| Ok(())), Err(StreamError::MismatchedBracket));
+
+    let mut tokenizer = JsonTokenizer::new();
+    assert_eq!(tokenizer.feed(b"{\"a\": @}", |_, _| Ok(())), Err(StreamError::UnexpectedByte(b'@')));
+
+    let mut tokenizer = JsonTokenizer::new();
+    let long = format!("{{\"a\": \"{}\"}}", "x".repeat(100));
+    assert_eq!(tokenizer.feed(long.as_bytes(), |_, _| Ok(())), Err(StreamError::TokenTooLong));
+
+    let mut tokenizer = JsonTokenizer::new();
+    assert_eq!(tokenizer.feed(b"[[[[[[[[[", |_, _| Ok(())), Err(StreamError::TooDeep));
+
+    // Errors from the callback stop the tokenizer
+    let mut tokenizer = JsonTokenizer::new();
+    let result = tokenizer.feed(b"{\"a\": 1}", |event, _| match event {
+        Event::Number(_) => Err(StreamError::InvalidNumber),
+        _ => Ok(()),
+    });
+    assert_eq!(result, Err(StreamError::InvalidNumber));
+}
+
+#[test]
+fn test_canvas_stream_matches_buffered_parser() {
+    let raw = canvas_response();
+    let expected = parse_canvas_response(&raw).unwrap();
+
+    for chunk_size in [1, 2, 3, 7, 64, 511, 1536, raw.len()] {
+        let mut frame = CanvasFrame::default();
+        let mut stream = CanvasStream::new();
+        for chunk in raw.chunks(chunk_size) {
+            stream.feed(chunk, &mut frame).unwrap();
+        }
+        stream.finish().unwrap();
+        assert_eq!(frame, expected, "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn test_canvas_stream_truncated_and_invalid() {
+    let raw = canvas_response();
+    let mut frame = CanvasFrame::default();
+    let mut stream = CanvasStream::new();
+    stream.feed(&raw[..raw.len() / 2], &mut frame).unwrap();
+    assert!(!stream.is_complete());
+    assert_eq!(stream.finish(), Err(StreamError::Incomplete));
+
+    let raw = http_response(r##"{"width":32,"height":16,"pixels":[["#12345Z"]]}"##);
+    let mut stream = CanvasStream::new();
+    assert_eq!(stream.feed(&raw, &mut frame), Err(StreamError::InvalidColor));
+}
+
+#[test]
+fn test_updates_stream_applies_pixels() {
+    let raw = updates_response(vec![update(1, 2, "#FF0000", 10), update(40, 0, "#FFFFFF", 12), update(3, 4, "#00FF00", 11)], false, 12);
+
+    for chunk_size in 1..=raw.len() {
+        let mut frame = CanvasFrame::default();
+        let mut stream = UpdatesStream::new();
+        for chunk in raw.chunks(chunk_size) {
+            stream.feed(chunk, &mut frame).unwrap();
+        }
+        let summary = stream.finish().unwrap();
+
+        // The out-of-bounds update isn't counted but its timestamp is
+        assert_eq!(summary.applied, 2);
+        assert_eq!(summary.newest, 12);
+        assert!(!summary.reset_required);
+        assert_eq!(summary.latest_timestamp, 12);
+        assert_eq!(frame.get(1, 2), Some(Rgb::new(255, 0, 0)));
+        assert_eq!(frame.get(3, 4), Some(Rgb::new(0, 255, 0)));
+    }
+}
+
+#[test]
+fn test_updates_stream_missing_field() {
+    let raw = http_response(r##"{"updates":[{"x":1,"y":2,"timestamp":3}],"reset_required":false}"##);
+    let mut stream = UpdatesStream::new();
+    assert_eq!(stream.feed(&raw, &mut CanvasFrame::default()), Err(StreamError::MissingField));
+}
+
+#[test]
+fn test_sync_drives_the_right_stream() {
+    let mut sync = CanvasSync::new();
+    let mut frame = CanvasFrame::default();
+
+    // Where the server's history ends first
+    let mut stream = sync.start_response();
+    stream.feed(&updates_response(vec![], false, 50), &mut frame).unwrap();
+    assert_eq!(sync.finish_response(&mut stream), Ok(SyncStep::Positioned));
+    assert_eq!(sync.since(), 50);
+
+    // Then the full canvas
+    let mut stream = sync.start_response();
+    for chunk in canvas_response().chunks(100) {
+        stream.feed(chunk, &mut frame).unwrap();
+    }
+    assert_eq!(sync.finish_response(&mut stream), Ok(SyncStep::Loaded));
+    assert!(!sync.needs_full_canvas());
+
+    // Then deltas
+    let mut stream = sync.start_response();
+    stream.feed(&updates_response(vec![update(0, 0, "#ABCDEF", 77)], false, 77), &mut frame).unwrap();
+    assert_eq!(sync.finish_response(&mut stream), Ok(SyncStep::Applied(1)));
+    assert_eq!(sync.request_path().as_str(), "/updates?since=77");
+    assert_eq!(frame.get(0, 0), Some(Rgb::new(0xAB, 0xCD, 0xEF)));
+
+    // Reset required goes back to the canvas
+    let mut stream = sync.start_response();
+    stream.feed(&updates_response(vec![], true, 900), &mut frame).unwrap();
+    assert_eq!(sync.finish_response(&mut stream), Ok(SyncStep::Resync));
+    assert_eq!(sync.request_path().as_str(), "/canvas");
+
+    // A cut-off response leaves the cursor alone
+    let mut stream = sync.start_response();
+    stream.feed(b"HTTP/1.0 200 OK\r\n\r\n{\"width\":32", &mut frame).unwrap();
+    assert_eq!(sync.finish_response(&mut stream), Err(StreamError::Incomplete));
+    assert!(sync.needs_full_canvas());
+}
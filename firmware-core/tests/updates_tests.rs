@@ -0,0 +1,112 @@
+// tests/updates_tests.rs
+
+// Host tests for following GET /updates in the firmware
+
+use firmware_core::updates::{parse_updates_response, CanvasSync, SyncStep, MAX_UPDATES};
+use protocol::{BoardMode, CanvasFrame, PixelUpdate, Rgb, UpdatesResponse};
+
+// Test helper to build the exact HTTP response the backend sends for GET /updates
+fn http_updates(updates: Vec<PixelUpdate>, reset_required: bool, latest_timestamp: u64) -> Vec<u8> {
+    let body = serde_json::to_string(&UpdatesResponse { updates, reset_required, latest_timestamp, shutting_down: false, mode: BoardMode::Open }).unwrap();
+    format!("HTTP/1.0 200 OK\r\ncontent-type: application/json\r\n\r\n{}", body).into_bytes()
+}
+
+fn update(x: u32, y: u32, color: &str, timestamp: u64) -> PixelUpdate {
+    PixelUpdate { x, y, color: color.to_string(), timestamp }
+}
+
+#[test]
+fn test_parse_updates_response() {
+    let raw = http_updates(vec![update(1, 2, "#FF0000", 10), update(3, 4, "#00FF00", 11)], false, 11);
+    let parsed = parse_updates_response(&raw).unwrap();
+
+    assert_eq!(parsed.updates.len(), 2);
+    assert_eq!(parsed.updates[1].color, Rgb::new(0, 255, 0));
+    assert_eq!(parsed.updates[1].timestamp, 11);
+    assert!(!parsed.reset_required);
+    assert_eq!(parsed.latest_timestamp, 11);
+
+    // A full server history still fits
+    let full: Vec<PixelUpdate> = (0..MAX_UPDATES as u64).map(|i| update(0, 0, "#FFFFFF", 1_700_000_000_000 + i)).collect();
+    assert_eq!(parse_updates_response(&http_updates(full, false, 0)).unwrap().updates.len(), MAX_UPDATES);
+}
+
+#[test]
+fn test_request_path_follows_state() {
+    let mut sync = CanvasSync::new();
+    assert!(sync.needs_full_canvas());
+    // First only where the server's history ends
+    assert_eq!(sync.request_path().as_str(), "/updates?since=18446744073709551615");
+
+    let mut frame = CanvasFrame::default();
+    let step = sync.apply(&parse_updates_response(&http_updates(vec![], false, 0)).unwrap(), &mut frame);
+    assert_eq!(step, SyncStep::Positioned);
+    assert_eq!(sync.request_path().as_str(), "/canvas");
+
+    sync.full_canvas_loaded();
+    assert_eq!(sync.request_path().as_str(), "/updates?since=0");
+
+    let raw = http_updates(vec![update(0, 0, "#FFFFFF", u64::MAX)], false, u64::MAX);
+    sync.apply(&parse_updates_response(&raw).unwrap(), &mut frame);
+    assert_eq!(sync.request_path().as_str(), "/updates?since=18446744073709551615");
+}
+
+#[test]
+fn test_apply_updates_and_advance_cursor() {
+    let mut sync = CanvasSync::new();
+    sync.full_canvas_loaded();
+    let mut frame = CanvasFrame::default();
+
+    // The out-of-bounds update is skipped but still moves the cursor
+    let raw = http_updates(vec![update(5, 6, "#123456", 100), update(99, 0, "#FFFFFF", 105)], false, 105);
+    let step = sync.apply(&parse_updates_response(&raw).unwrap(), &mut frame);
+
+    assert_eq!(step, SyncStep::Applied(1));
+    assert_eq!(frame.get(5, 6), Some(Rgb::new(0x12, 0x34, 0x56)));
+    assert_eq!(sync.since(), 105);
+
+    // An empty poll keeps the cursor where it is
+    let step = sync.apply(&parse_updates_response(&http_updates(vec![], false, 105)).unwrap(), &mut frame);
+    assert_eq!(step, SyncStep::Applied(0));
+    assert_eq!(sync.since(), 105);
+}
+
+#[test]
+fn test_reset_required_triggers_full_reload_from_latest() {
+    let mut sync = CanvasSync::new();
+    sync.full_canvas_loaded();
+    let mut frame = CanvasFrame::default();
+
+    let step = sync.apply(&parse_updates_response(&http_updates(vec![], true, 5000)).unwrap(), &mut frame);
+
+    assert_eq!(step, SyncStep::Resync);
+    assert!(sync.needs_full_canvas());
+    assert_eq!(sync.request_path().as_str(), "/canvas");
+
+    // After the reload we continue from the server's newest update, not from 0
+    sync.full_canvas_loaded();
+    assert_eq!(sync.request_path().as_str(), "/updates?since=5000");
+}
+
+#[test]
+fn test_boot_starts_from_the_servers_latest_timestamp() {
+    let mut sync = CanvasSync::new();
+    let mut frame = CanvasFrame::default();
+
+    // A server that was reset since its last pixel: the canvas is blank and nothing before 7000 may be replayed
+    let step = sync.apply(&parse_updates_response(&http_updates(vec![], false, 7000)).unwrap(), &mut frame);
+    assert_eq!(step, SyncStep::Positioned);
+    assert!(sync.needs_full_canvas());
+    assert_eq!(sync.since(), 7000);
+
+    sync.full_canvas_loaded();
+    assert_eq!(sync.request_path().as_str(), "/updates?since=7000");
+}
+
+#[test]
+fn test_manual_resync() {
+    let mut sync = CanvasSync::default();
+    sync.full_canvas_loaded();
+    sync.resync();
+    assert!(sync.needs_full_canvas());
+}
@@ -0,0 +1,143 @@
+// src/discovery.rs
+
+// Turning the configured server (an IP address, a hostname, or nothing at all) into an address we can connect to
+
+// For your knowledge
+// The DNS packets are written and read by firmware-core/src/discovery.rs, this file only sends them over UDP
+// Hostnames go to the DNS server DHCP gave us, ".local" names and service discovery go to the mDNS group
+// Each call makes one attempt, retries are paced by the reconnection state machine (firmware-core/src/link.rs)
+
+use core::fmt::Write as _;
+use core::net::Ipv4Addr;
+
+use blocking_network_stack::{Stack, UdpSocket};
+use esp_hal::time::{self, Duration};
+use esp_println::println;
+use firmware_core::captive::DNS_PORT;
+use firmware_core::config::{ServerAddress, ServerHost};
+use firmware_core::discovery::{
+    parse_a_answer, parse_service_answer, write_a_query, write_service_query, MAX_QUERY_LEN, MDNS_ADDR, MDNS_PORT,
+    MDNS_SERVICE_TYPE,
+};
+use heapless::String;
+use smoltcp::{phy::Device, socket::udp::PacketMetadata, wire::IpAddress};
+
+// Our end of the lookups. Anything but 5353, so mDNS responders answer us directly
+const QUERY_PORT: u16 = 49152;
+
+const ANSWER_TIMEOUT_MS: u64 = 2000;
+
+// Room for the longest configured server plus ":65535"
+pub type HostHeader = String<72>;
+
+pub struct Server {
+    pub ip: Ipv4Addr,
+    pub port: u16,
+    // Sent as the Host header
+    pub host: HostHeader,
+}
+
+// Resolve 'configured', or find a backend on the LAN if no server was configured. None if nothing answered
+pub fn find_server<D: Device>(stack: &Stack<'_, D>, configured: Option<&ServerAddress>) -> Option<Server> {
+    // An IP address needs no lookup
+    if let Some(address @ ServerAddress { host: ServerHost::Ip(ip), port }) = configured {
+        return Some(Server { ip: *ip, port: *port, host: host_header(address) });
+    }
+
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0u8; 1536];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_buffer = [0u8; 512];
+    let mut udp = stack.get_udp_socket(&mut rx_meta, &mut rx_buffer, &mut tx_meta, &mut tx_buffer);
+    udp.bind(QUERY_PORT).unwrap();
+
+    // Answers to an earlier attempt may still arrive, a new id tells them apart
+    let id = (time::Instant::now().duration_since_epoch().as_millis() & 0xFFFF) as u16;
+    let server = match configured {
+        Some(address) => resolve(stack, &mut udp, &address.host, id)
+            .map(|ip| Server { ip, port: address.port, host: host_header(address) }),
+        None => discover(&mut udp, id),
+    };
+    if let Some(server) = &server {
+        println!("Using server {}:{}", server.ip, server.port);
+    }
+    server
+}
+
+// The configured name is what the server expects to be called
+fn host_header(address: &ServerAddress) -> HostHeader {
+    let mut host = HostHeader::new();
+    let _ = write!(host, "{}", address);
+    host
+}
+
+// Look up the address of a hostname
+fn resolve<D: Device>(stack: &Stack<'_, D>, udp: &mut UdpSocket<'_, '_, D>, host: &ServerHost, id: u16) -> Option<Ipv4Addr> {
+    let ServerHost::Name(name) = host else {
+        return None;
+    };
+
+    let (to, port) = if host.is_mdns() {
+        (MDNS_ADDR, MDNS_PORT)
+    } else {
+        // Home routers answer DNS themselves when DHCP doesn't name a server
+        let info = stack.get_ip_info().ok()?;
+        let dns = info.dns.unwrap_or(info.subnet.gateway);
+        (Ipv4Addr::from(dns.octets()), DNS_PORT)
+    };
+
+    let mut query = [0u8; MAX_QUERY_LEN];
+    let len = write_a_query(id, name, !host.is_mdns(), &mut query)?;
+    let ip = exchange(udp, to, port, &query[..len], |answer| parse_a_answer(answer, id));
+    if ip.is_none() {
+        println!("No address for {}", name);
+    }
+    ip
+}
+
+// Ask the LAN for a backend advertising MDNS_SERVICE_TYPE
+fn discover<D: Device>(udp: &mut UdpSocket<'_, '_, D>, id: u16) -> Option<Server> {
+    let mut query = [0u8; MAX_QUERY_LEN];
+    let len = write_service_query(id, &mut query)?;
+    let Some(answer) = exchange(udp, MDNS_ADDR, MDNS_PORT, &query[..len], |answer| parse_service_answer(answer, id))
+    else {
+        println!("No {} on the network", MDNS_SERVICE_TYPE);
+        return None;
+    };
+
+    // The responder usually sends the host's address along with the service, if not we ask for it
+    let ip = match answer.ip {
+        Some(ip) => ip,
+        None => {
+            let len = write_a_query(id, &answer.host, false, &mut query)?;
+            exchange(udp, MDNS_ADDR, MDNS_PORT, &query[..len], |packet| parse_a_answer(packet, id))?
+        }
+    };
+    println!("Found {} at {}:{}", answer.host, ip, answer.port);
+
+    let mut host = HostHeader::new();
+    let _ = write!(host, "{}:{}", ip, answer.port);
+    Some(Server { ip, port: answer.port, host })
+}
+
+// Send 'query' and wait for a packet 'parse' accepts (others, like answers to old queries, are dropped)
+fn exchange<D: Device, T>(
+    udp: &mut UdpSocket<'_, '_, D>,
+    to: Ipv4Addr,
+    port: u16,
+    query: &[u8],
+    parse: impl Fn(&[u8]) -> Option<T>,
+) -> Option<T> {
+    udp.send(IpAddress::Ipv4(to), port, query).ok()?;
+
+    let mut packet = [0u8; 1536];
+    let deadline = time::Instant::now() + Duration::from_millis(ANSWER_TIMEOUT_MS);
+    while time::Instant::now() < deadline {
+        if let Ok((len, _, _)) = udp.receive(&mut packet) {
+            if let Some(found) = parse(&packet[..len]) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
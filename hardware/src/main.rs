@@ -2,41 +2,67 @@
 #![no_main]
 
 extern crate alloc;
-use core::net::Ipv4Addr;
-use heapless::String;
 
-use blocking_network_stack::Stack;
+mod discovery;
+mod provisioning;
+
+use blocking_network_stack::{Socket, Stack};
 use embedded_io::*;
 use esp_alloc::{self as _, MemoryCapability};
 use esp_backtrace as _;
 use esp_hal::{
     clock::CpuClock,
+    gpio::{Input, InputConfig, Pull},
     interrupt::software::SoftwareInterruptControl,
     main,
     rng::Rng,
-    time::{self, Duration},
+    rtc_cntl::{Rtc, RwdtStage},
+    spi::{
+        master::{Config as SpiConfig, Spi},
+        Mode,
+    },
+    time::{self, Duration, Rate},
     timer::timg::TimerGroup,
 };
 use esp_println::println;
-use esp_radio::wifi::{ClientConfig, Config as WifiConfig, ModeConfig, ScanConfig};
-use serde::Deserialize;
+use discovery::Server;
+use esp_radio::wifi::{ClientConfig, Config as WifiConfig, ModeConfig, ScanConfig, WifiController};
+use esp_storage::FlashStorage;
+use firmware_core::color::ColorCorrection;
+use firmware_core::device::{self, DeviceReport, ReportKind, Reporter, MAX_REPORT_REQUEST_LEN};
+use firmware_core::display::{LedMatrix, Wiring, Ws2812Spi, WS2812_SPI_HZ};
+use firmware_core::http::{write_get_request, ResponseParser};
+use firmware_core::stream::StreamError;
+use firmware_core::link::{Link, LinkEvent, LinkState};
+use firmware_core::patterns;
+use firmware_core::render::{render_frame, render_frame_with_status, Panel};
+use firmware_core::updates::{CanvasSync, SyncStep};
+use heapless::{String, Vec};
+use protocol::{CanvasFrame, TestPattern};
 use smoltcp::{
     iface::{SocketSet, SocketStorage},
     wire::{DhcpOption, IpAddress},
 };
 
-const SSID: &str = "shortnet";
-const PASSWORD: &str = "dictionary";
+// Press the BOOT button (GPIO9) within this long of power-up to go back to the setup network
+// (holding it *while* resetting would put the chip in download mode instead)
+const SETUP_BUTTON_WINDOW_MS: u64 = 2000;
+// Keep it held this long to run the display test patterns instead (see firmware-core/src/patterns.rs)
+const DIAGNOSTICS_HOLD_MS: u64 = 3000;
 
-const COLOR_LEN: usize = 7; // "#RRGGBB"
-const RESP_BUF_LEN: usize = 8192;
+// How long each attempt at joining Wi-Fi and getting a DHCP lease may take before it counts as failed
+const WIFI_CONNECT_TIMEOUT_SECS: u64 = 30;
+const DHCP_TIMEOUT_SECS: u64 = 20;
 
-#[derive(Debug, Deserialize)]
-pub struct Canvas {
-    pub width: u8,
-    pub height: u8,
-    pub pixels: [[String<COLOR_LEN>; 32]; 16],
-}
+// Reboot if the link makes no progress for this long (a failing step backs off to one try a minute, so this is
+// a few failed cycles, or a single step that hangs)
+const WATCHDOG_TIMEOUT_SECS: u64 = 300;
+
+// How long to wait between polls of /updates (small responses, so this can be much faster than a full refetch)
+const POLL_INTERVAL_MS: u64 = 1000;
+
+// Global LED brightness (0-255). 512 LEDs at full white draw ~30 A, this keeps the panel on a small supply
+const PANEL_BRIGHTNESS: u8 = 40;
 
 #[main]
 fn main() -> ! {
@@ -58,15 +84,70 @@ fn main() -> ! {
     let sw_int = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
     esp_rtos::start(timg0.timer0, sw_int.software_interrupt0);
 
+    // LED matrix data line is driven from SPI2 MOSI on GPIO4 (the clock pin is not used)
+    let spi = Spi::new(
+        peripherals.SPI2,
+        SpiConfig::default()
+            .with_frequency(Rate::from_hz(WS2812_SPI_HZ))
+            .with_mode(Mode::_0),
+    )
+    .unwrap()
+    .with_mosi(peripherals.GPIO4);
+    let mut panel = LedMatrix::new(Ws2812Spi::new(spi), Wiring::Serpentine);
+    panel.set_correction(ColorCorrection::new(PANEL_BRIGHTNESS, true));
+
+    // Blank the panel so it doesn't show whatever the LEDs powered up with
+    if let Err(e) = panel.show() {
+        println!("Panel write error: {:?}", e);
+    }
+
+    // Wi-Fi and backend settings come from flash, entered once through the setup network (see provisioning.rs)
+    let mut flash = FlashStorage::new(peripherals.FLASH);
+    let boot_button = Input::new(peripherals.GPIO9, InputConfig::default().with_pull(Pull::Up));
+    println!("Press BOOT now to enter setup, or hold it to run the display test patterns");
+    let mut setup_requested = false;
+    let window_end = time::Instant::now() + Duration::from_millis(SETUP_BUTTON_WINDOW_MS);
+    while time::Instant::now() < window_end && !setup_requested {
+        setup_requested = boot_button.is_low();
+    }
+    if setup_requested {
+        let hold_end = time::Instant::now() + Duration::from_millis(DIAGNOSTICS_HOLD_MS);
+        while boot_button.is_low() && time::Instant::now() < hold_end {}
+        if boot_button.is_low() {
+            // Held: test the panel, then carry on booting normally
+            setup_requested = false;
+            run_test_patterns(&TestPattern::ALL, &mut panel);
+        }
+    }
+
+    let saved_config = match provisioning::load_config(&mut flash) {
+        Ok(_) if setup_requested => {
+            println!("BOOT pressed, entering setup");
+            None
+        }
+        Ok(saved) => Some(saved),
+        Err(e) => {
+            println!("No usable config ({}), entering setup", e);
+            None
+        }
+    };
+
     let radio = esp_radio::init().unwrap();
 
     let (mut controller, interfaces) =
         esp_radio::wifi::new(&radio, peripherals.WIFI, WifiConfig::default()).unwrap();
 
+    let Some(device_config) = saved_config else {
+        provisioning::run_portal(&mut controller, interfaces.ap, &mut flash);
+    };
+    // Checked when it was saved and again when it was loaded. None means look for a server on the LAN
+    let configured_server = device_config.server_address().unwrap();
+
     let mut device = interfaces.sta;
 
     let iface = create_interface(&mut device);
 
+    // DHCP, the UDP socket for finding the server and the TCP connection to it
     let mut socket_set_entries: [SocketStorage; 3] = Default::default();
     let mut socket_set = SocketSet::new(&mut socket_set_entries[..]);
     let mut dhcp_socket = smoltcp::socket::dhcpv4::Socket::new();
@@ -80,13 +161,13 @@ fn main() -> ! {
     let now = || time::Instant::now().duration_since_epoch().as_millis();
     let stack = Stack::new(iface, device, socket_set, now, rng.random());
 
-    controller
-        .set_power_saving(esp_radio::wifi::PowerSaveMode::None)
-        .unwrap();
+    if let Err(e) = controller.set_power_saving(esp_radio::wifi::PowerSaveMode::None) {
+        println!("set_power_saving failed: {:?}", e);
+    }
 
     let client_cfg = ClientConfig::default()
-        .with_ssid(SSID.into())
-        .with_password(PASSWORD.into());
+        .with_ssid(device_config.ssid.as_str().into())
+        .with_password(device_config.password.as_str().into());
 
     let mode_cfg = ModeConfig::Client(client_cfg);
 
@@ -94,12 +175,150 @@ fn main() -> ! {
 
     println!("wifi_set_configuration returned {:?}", res);
 
-    controller.start().unwrap();
+    // Without a running radio there's nothing to retry, a reboot is the best chance of it coming back
+    if let Err(e) = controller.start() {
+        println!("Wi-Fi failed to start: {:?}, rebooting", e);
+        esp_hal::system::software_reset();
+    }
     println!("is wifi started: {:?}", controller.is_started());
 
+    log_scan(&mut controller, &device_config.ssid);
+    println!("{:?}", controller.capabilities());
+
+    // From here on a hang anywhere (a socket that never answers, a driver stuck) reboots the board
+    // The watchdog is fed whenever the link makes progress, see firmware-core/src/link.rs
+    let mut rtc = Rtc::new(peripherals.LPWR);
+    rtc.rwdt.set_timeout(RwdtStage::Stage0, Duration::from_secs(WATCHDOG_TIMEOUT_SECS));
+    rtc.rwdt.enable();
+
+    let mut rx_buffer = [0u8; 1536];
+    let mut tx_buffer = [0u8; 1536];
+    let mut socket = stack.get_socket(&mut rx_buffer, &mut tx_buffer);
+
+    // Our copy of the board, kept in sync with /updates between full reloads (see firmware-core/src/updates.rs)
+    let mut frame = CanvasFrame::default();
+    let mut sync = CanvasSync::new();
+
+    // Every failure is retried from the right step with backoff, nothing here gives up (see firmware-core/src/link.rs)
+    let mut link = Link::new(POLL_INTERVAL_MS);
+    let mut server: Option<Server> = None;
+    // The connection is kept open between polls (HTTP/1.1 keep-alive) and only reopened when it drops
+    let mut connected = false;
+    // Registers with the server once we're online, then sends heartbeats between polls (see firmware-core/src/device.rs)
+    let mut reporter = Reporter::new();
+    // A test pattern an admin asked for, shown before the next step
+    let mut test_pattern: Option<TestPattern> = None;
+    let device_id = device::device_id(esp_radio::wifi::sta_mac());
+    println!("Device ID: {}", device_id);
+
+    loop {
+        let state = link.state();
+        let wifi_up = matches!(controller.is_connected(), Ok(true));
+
+        let event = if state != LinkState::JoiningWifi && !wifi_up {
+            println!("Wi-Fi connection lost");
+            LinkEvent::WifiLost
+        } else {
+            match state {
+                LinkState::JoiningWifi => {
+                    socket.disconnect();
+                    connected = false;
+                    let event = join_wifi(&mut controller);
+                    if event == LinkEvent::WifiJoined {
+                        // Whatever address we had may not be ours any more, ask DHCP again
+                        stack.reset();
+                    }
+                    event
+                }
+                LinkState::WaitingForAddress => wait_for_address(&stack),
+                LinkState::FindingServer => match discovery::find_server(&stack, configured_server.as_ref()) {
+                    Some(found) => {
+                        server = Some(found);
+                        LinkEvent::ServerFound
+                    }
+                    None => LinkEvent::ServerNotFound,
+                },
+                LinkState::Connecting => {
+                    socket.disconnect();
+                    connected = false;
+                    match &server {
+                        Some(server) => {
+                            println!("Connecting to {}...", server.host);
+                            match socket.open(IpAddress::Ipv4(server.ip), server.port) {
+                                Ok(_) => {
+                                    connected = true;
+                                    LinkEvent::Connected
+                                }
+                                Err(e) => {
+                                    println!("Failed to open socket: {:?}", e);
+                                    LinkEvent::ConnectFailed
+                                }
+                            }
+                        }
+                        None => LinkEvent::ServerNotFound,
+                    }
+                }
+                LinkState::Online => {
+                    let now_ms = time::Instant::now().duration_since_epoch().as_millis();
+                    let event = match (&server, reporter.due(now_ms)) {
+                        (Some(server), Some(kind)) => {
+                            let report = DeviceReport {
+                                device_id: device_id.clone(),
+                                firmware_version: env!("CARGO_PKG_VERSION").try_into().unwrap_or_default(),
+                                uptime_secs: now_ms / 1000,
+                                rssi: controller.rssi().ok(),
+                                last_applied: sync.since(),
+                            };
+                            send_report(&mut socket, &mut connected, server, kind, &report, &mut reporter, &mut test_pattern)
+                        }
+                        (Some(server), None) => poll(&mut socket, &mut connected, server, &mut sync, &mut frame),
+                        (None, _) => LinkEvent::ServerNotFound,
+                    };
+                    if event != LinkEvent::PollSucceeded {
+                        socket.disconnect();
+                        connected = false;
+                    }
+                    event
+                }
+            }
+        };
+
+        let wait_ms = link.on(event);
+        if event.is_progress() {
+            rtc.rwdt.feed();
+        }
+        if let Some(pattern) = test_pattern.take() {
+            // The longest pattern is well inside the watchdog timeout
+            run_test_patterns(&[pattern], &mut panel);
+            rtc.rwdt.feed();
+        }
+        if link.state() != state {
+            println!("{:?} -> {:?} (next step in {} ms)", state, link.state(), wait_ms);
+        }
+
+        // Only transmits to the LEDs if something changed. The status dot shows while we're not online
+        if let Err(e) = render_frame_with_status(&frame, &mut panel, link.status_color()) {
+            println!("Panel write error: {:?}", e);
+        }
+
+        let deadline = time::Instant::now() + Duration::from_millis(wait_ms);
+        while time::Instant::now() < deadline {
+            socket.work();
+        }
+    }
+}
+
+// Print what the scan finds, which makes a typo in the SSID easy to spot
+fn log_scan(controller: &mut WifiController<'_>, ssid: &str) {
     println!("Start Wifi Scan");
     let scan_config = ScanConfig::default().with_max(10);
-    let res = controller.scan_with_config(scan_config).unwrap();
+    let res = match controller.scan_with_config(scan_config) {
+        Ok(res) => res,
+        Err(e) => {
+            println!("Scan failed: {:?}", e);
+            return;
+        }
+    };
     let mut found_network = false;
     for ap in res {
         let ssid_str = ap.ssid.as_str();
@@ -107,153 +326,267 @@ fn main() -> ! {
             "Found AP: SSID={}, Channel={}, Signal={}",
             ssid_str, ap.channel, ap.signal_strength
         );
-        if ssid_str == SSID {
+        if ssid_str == ssid {
             found_network = true;
             println!("  -> This is our target network! Auth={:?}", ap.auth_method);
         }
     }
     if !found_network {
-        println!("WARNING: Target SSID '{}' not found in scan results!", SSID);
+        println!("WARNING: Target SSID '{}' not found in scan results!", ssid);
     }
+}
 
-    println!("{:?}", controller.capabilities());
+// One attempt at associating with the access point
+fn join_wifi(controller: &mut WifiController<'_>) -> LinkEvent {
+    // Drop any half-finished association first (fails harmlessly if there is none)
+    let _ = controller.disconnect();
     println!("wifi_connect {:?}", controller.connect());
 
-    // wait to get connected
     println!("Wait to get connected");
-    let connect_timeout = time::Instant::now() + Duration::from_secs(30);
+    let connect_timeout = time::Instant::now() + Duration::from_secs(WIFI_CONNECT_TIMEOUT_SECS);
     loop {
         match controller.is_connected() {
             Ok(true) => {
                 println!("Connected!");
-                break;
+                return LinkEvent::WifiJoined;
             }
             Ok(false) => {
                 if time::Instant::now() > connect_timeout {
-                    println!("ERROR: Connection timeout after 30 seconds");
-                    loop {}
+                    println!("Connection timeout after {} seconds", WIFI_CONNECT_TIMEOUT_SECS);
+                    return LinkEvent::WifiFailed;
                 }
             }
             Err(err) => {
                 println!("Connection error: {:?}", err);
-                loop {}
+                return LinkEvent::WifiFailed;
             }
         }
     }
-    println!("Connection status: {:?}", controller.is_connected());
+}
 
-    // wait for getting an ip address
+// Run DHCP until we have an address, or give up after a while
+fn wait_for_address<D: smoltcp::phy::Device>(stack: &Stack<'_, D>) -> LinkEvent {
     println!("Wait to get an ip address");
-    loop {
+    let deadline = time::Instant::now() + Duration::from_secs(DHCP_TIMEOUT_SECS);
+    while time::Instant::now() < deadline {
         stack.work();
 
         if stack.is_iface_up() {
             println!("got ip {:?}", stack.get_ip_info());
-            break;
+            return LinkEvent::AddressAcquired;
         }
     }
+    println!("No address from DHCP after {} seconds", DHCP_TIMEOUT_SECS);
+    LinkEvent::AddressTimeout
+}
 
-    println!("Start busy loop on main");
+// One GET of the canvas or /updates on the open connection, applied to 'frame'
+fn poll<D: smoltcp::phy::Device>(
+    socket: &mut Socket<'_, '_, D>,
+    connected: &mut bool,
+    server: &Server,
+    sync: &mut CanvasSync,
+    frame: &mut CanvasFrame,
+) -> LinkEvent {
+    // The server closed the last connection itself (no keep-alive), open a new one
+    if !*connected {
+        if let Err(e) = socket.open(IpAddress::Ipv4(server.ip), server.port) {
+            println!("Failed to open socket: {:?}", e);
+            return LinkEvent::PollFailed;
+        }
+        *connected = true;
+    }
 
-    let mut rx_buffer = [0u8; 1536];
-    let mut tx_buffer = [0u8; 1536];
-    let mut socket = stack.get_socket(&mut rx_buffer, &mut tx_buffer);
+    let path = sync.request_path();
+    let mut request: String<256> = String::new();
+    let _ = write_get_request(&mut request, &server.host, &path, true);
 
-    loop {
-        println!("Making HTTP request to 192.168.2.169:8080/canvas");
-        socket.work();
-
-        println!("Opening socket...");
-        match socket.open(IpAddress::Ipv4(Ipv4Addr::new(192, 168, 2, 169)), 8080) {
-            Ok(_) => println!("Socket opened"),
-            Err(e) => {
-                println!("Failed to open socket: {:?}", e);
-                continue;
-            }
-        }
+    if socket.write_all(request.as_bytes()).and_then(|_| socket.flush()).is_err() {
+        // Usually the server closing a kept-alive connection while we were idle
+        println!("Connection lost, reconnecting");
+        return LinkEvent::PollFailed;
+    }
 
-        println!("Sending HTTP request...");
-        socket
-            .write(b"GET /canvas HTTP/1.0\r\nHost: 192.168.2.169\r\n\r\n")
-            .unwrap();
-        socket.flush().unwrap();
-        println!("Request sent");
-
-        // Buffer for full HTTP response
-        let mut response_buf = [0u8; RESP_BUF_LEN];
-        let mut response_len = 0;
-
-        let deadline = time::Instant::now() + Duration::from_secs(20);
-        let mut buffer = [0u8; 512];
-
-        println!("Reading response...");
-        loop {
-            match socket.read(&mut buffer) {
-                Ok(len) => {
-                    if len > 0 {
-                        println!("Received {} bytes", len);
-                        // Copy incoming bytes into response_buf
-                        for &b in &buffer[..len] {
-                            if response_len < response_buf.len() {
-                                response_buf[response_len] = b;
-                                response_len += 1;
-                            }
-                        }
-                    }
+    // The response is parsed as it arrives and pixels go straight into 'frame', nothing is buffered
+    let mut stream = sync.start_response();
+    let mut received = 0;
+    let mut stream_error = None;
+
+    let deadline = time::Instant::now() + Duration::from_secs(20);
+    let mut buffer = [0u8; 512];
+
+    // Read until the response says it's complete, the server closes the connection, or we give up
+    while !stream.is_complete() {
+        match socket.read(&mut buffer) {
+            Ok(len) => {
+                received += len;
+                if let Err(e) = stream.feed(&buffer[..len], frame) {
+                    stream_error = Some(e);
+                    break;
                 }
-                Err(_) => break,
             }
-
-            if time::Instant::now() > deadline {
-                println!("Timeout after receiving {} bytes", response_len);
+            Err(_) => {
+                *connected = false;
                 break;
             }
         }
 
-        println!("Total received: {} bytes", response_len);
-        let full = &response_buf[..response_len];
-
-        match core::str::from_utf8(full) {
-            Ok(full_str) => {
-                println!("Response as string (first 200 chars):");
-                let preview = if full_str.len() > 200 {
-                    &full_str[..200]
-                } else {
-                    full_str
-                };
-                println!("{}", preview);
-
-                // Find beginning of JSON body
-                if let Some(json_start) = full_str.find("\r\n\r\n") {
-                    let json_str = &full_str[json_start + 4..];
-                    println!("Got JSON body ({} chars):", json_str.len());
-                    println!("{}", json_str);
-
-                    // Parse JSON into Canvas
-                    match serde_json_core::from_str::<Canvas>(json_str) {
-                        Ok((canvas, _)) => {
-                            println!(
-                                "Successfully parsed canvas: {}x{}",
-                                canvas.width, canvas.height
-                            );
-                            println!("Top-left pixel: {}", canvas.pixels[0][0].as_str());
-                        }
-                        Err(e) => println!("JSON parse error: {:?}", e),
-                    }
-                } else {
-                    println!("No HTTP body separator found");
+        if time::Instant::now() > deadline {
+            println!("Timeout after receiving {} bytes", received);
+            break;
+        }
+    }
+
+    // Parsing and syncing live in firmware-core so they can be tested on the host
+    let result = match stream_error {
+        Some(e) => Err(e),
+        None => sync.finish_response(&mut stream),
+    };
+    match result {
+        Ok(SyncStep::Positioned) => println!("Server history ends at {}, loading the canvas", sync.since()),
+        Ok(SyncStep::Loaded) => println!("Loaded canvas: {}x{}", frame.width, frame.height),
+        Ok(SyncStep::Applied(0)) => {}
+        Ok(SyncStep::Applied(n)) => println!("Applied {} updates (since={})", n, sync.since()),
+        Ok(SyncStep::Resync) => println!("Fell behind the server, reloading the canvas"),
+        Err(e) => {
+            // We can't tell what we missed (and the frame may be half written), so reload the full canvas
+            // After an error we can't tell where the next response would start either, so reconnect
+            println!("Response error: {:?}", e);
+            sync.resync();
+            return LinkEvent::PollFailed;
+        }
+    }
+
+    if *connected && !stream.keep_alive() {
+        socket.disconnect();
+        *connected = false;
+    }
+    LinkEvent::PollSucceeded
+}
+
+// POST a register or heartbeat report on the open connection, and tell 'reporter' how the server answered
+fn send_report<D: smoltcp::phy::Device>(
+    socket: &mut Socket<'_, '_, D>,
+    connected: &mut bool,
+    server: &Server,
+    kind: ReportKind,
+    report: &DeviceReport,
+    reporter: &mut Reporter,
+    test_pattern: &mut Option<TestPattern>,
+) -> LinkEvent {
+    if !*connected {
+        if let Err(e) = socket.open(IpAddress::Ipv4(server.ip), server.port) {
+            println!("Failed to open socket: {:?}", e);
+            return LinkEvent::PollFailed;
+        }
+        *connected = true;
+    }
+
+    let mut request: String<MAX_REPORT_REQUEST_LEN> = String::new();
+    if device::write_report_request(&mut request, &server.host, kind, report).is_err() {
+        // Can't happen with the sizes in device.rs, but don't try again until the next interval
+        reporter.on_response(kind, 0, time::Instant::now().duration_since_epoch().as_millis());
+        return LinkEvent::PollSucceeded;
+    }
+
+    if socket.write_all(request.as_bytes()).and_then(|_| socket.flush()).is_err() {
+        println!("Connection lost, reconnecting");
+        return LinkEvent::PollFailed;
+    }
+
+    // The body is only a few fields, kept whole to read the test pattern from
+    let mut parser = ResponseParser::new();
+    let mut body: Vec<u8, { device::MAX_REPLY_LEN }> = Vec::new();
+    let deadline = time::Instant::now() + Duration::from_secs(10);
+    let mut buffer = [0u8; 256];
+    let status = loop {
+        if parser.is_complete() {
+            break parser.status();
+        }
+        match socket.read(&mut buffer) {
+            Ok(len) => match parser.feed(&buffer[..len], |bytes| {
+                // A longer body is cut short and won't parse, which only loses the test pattern
+                let _ = body.extend_from_slice(bytes);
+                Ok(())
+            }) {
+                Ok(_) => {}
+                // The rest of the response is still on the connection, start a fresh one for the next request
+                Err(StreamError::HttpStatus(status)) => {
+                    socket.disconnect();
+                    *connected = false;
+                    break Some(status);
                 }
+                Err(e) => {
+                    println!("Report response error: {:?}", e);
+                    return LinkEvent::PollFailed;
+                }
+            },
+            Err(_) => {
+                *connected = false;
+                return LinkEvent::PollFailed;
             }
-            Err(e) => println!("UTF-8 decode error: {:?}", e),
         }
+        if time::Instant::now() > deadline {
+            println!("Timeout waiting for the {:?} response", kind);
+            return LinkEvent::PollFailed;
+        }
+    };
 
+    let Some(status) = status else {
+        return LinkEvent::PollFailed;
+    };
+    println!("{:?} answered {}", kind, status);
+    reporter.on_response(kind, status, time::Instant::now().duration_since_epoch().as_millis());
+    if status == 200 {
+        *test_pattern = device::parse_report_reply(&body);
+    }
+
+    if *connected && !parser.keep_alive() {
         socket.disconnect();
+        *connected = false;
+    }
+    LinkEvent::PollSucceeded
+}
 
-        let deadline = time::Instant::now() + Duration::from_secs(5);
-        while time::Instant::now() < deadline {
-            socket.work();
+// Show each pattern on the panel, logging what was shown and any write errors over serial
+fn run_test_patterns<P: Panel>(to_run: &[TestPattern], panel: &mut P)
+where
+    P::Error: core::fmt::Debug,
+{
+    let mut frame = CanvasFrame::default();
+    for &pattern in to_run {
+        let steps = patterns::step_count(pattern, &frame);
+        println!("Test pattern {}: {} step(s)", pattern, steps);
+        let started = time::Instant::now();
+        let mut errors = 0u32;
+
+        for step in 0..steps {
+            let hold_ms = patterns::draw_step(pattern, step, &mut frame);
+            if pattern == TestPattern::PixelWalk && step % frame.width == 0 {
+                let (_, y) = patterns::walk_position(step, &frame);
+                println!("  row {}", y);
+            }
+            if let Err(e) = render_frame(&frame, panel) {
+                if errors == 0 {
+                    println!("  panel write error at step {}: {:?}", step, e);
+                }
+                errors += 1;
+            }
+            let deadline = time::Instant::now() + Duration::from_millis(hold_ms);
+            while time::Instant::now() < deadline {}
         }
+
+        println!(
+            "Test pattern {} done: {} step(s) in {} ms, {} write error(s)",
+            pattern,
+            steps,
+            started.elapsed().as_millis(),
+            errors
+        );
     }
+
+    // Blank, until the canvas is drawn again
+    let _ = render_frame(&CanvasFrame::default(), panel);
 }
 
 fn timestamp() -> smoltcp::time::Instant {
@@ -0,0 +1,191 @@
+// src/provisioning.rs
+
+// Provisioning mode: the saved config in flash, and the setup network that fills it in
+
+// For your knowledge
+// The config record (firmware-core/src/config.rs) lives in its own flash sector at CONFIG_FLASH_OFFSET, which is the
+// "nvs" partition of the default partition table. We don't use ESP-IDF's NVS, so the sector is free for us
+// In setup mode the board is an open access point at SETUP_IP. It runs three servers on one smoltcp stack:
+// DHCP (so phones get an address), DNS (every name points at us) and HTTP on port 80 (the setup page)
+// Everything that can be tested on the host is in firmware-core (captive.rs, portal.rs), this file only moves bytes
+
+use core::net::Ipv4Addr;
+
+use blocking_network_stack::{ipv4, Stack};
+use embedded_io::*;
+use embedded_storage::{ReadStorage, Storage};
+use esp_hal::{
+    rng::Rng,
+    time::{self, Duration},
+};
+use esp_println::println;
+use esp_radio::wifi::{AccessPointConfig, ModeConfig, WifiController, WifiDevice};
+use esp_storage::{FlashStorage, FlashStorageError};
+use firmware_core::captive::{dns_reply, DhcpServer, DHCP_CLIENT_PORT, DHCP_REPLY_LEN, DHCP_SERVER_PORT, DNS_PORT};
+use firmware_core::config::{ConfigError, DeviceConfig, CONFIG_RECORD_LEN};
+use firmware_core::portal::{
+    handle_request, parse_request, write_form_response, write_saved_response, ParsedRequest, PortalAction,
+    MAX_REQUEST_LEN, MAX_RESPONSE_LEN, SETUP_SSID,
+};
+use heapless::String;
+use smoltcp::{
+    iface::{SocketSet, SocketStorage},
+    socket::udp::PacketMetadata,
+    wire::IpAddress,
+};
+
+// Start of the flash sector holding the config (sector aligned, 4 KiB)
+const CONFIG_FLASH_OFFSET: u32 = 0x9000;
+
+// The board's address on its own network (the same one phones see in the setup page's URL)
+const SETUP_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 4, 1);
+
+// Read the saved config. Err(ConfigError::Erased) means the board has never been set up
+pub fn load_config(flash: &mut FlashStorage<'_>) -> Result<DeviceConfig, ConfigError> {
+    let mut record = [0u8; CONFIG_RECORD_LEN];
+    if let Err(e) = flash.read(CONFIG_FLASH_OFFSET, &mut record) {
+        println!("Flash read error: {:?}", e);
+        return Err(ConfigError::Corrupt);
+    }
+    DeviceConfig::from_record(&record)
+}
+
+// Write the config (FlashStorage erases and rewrites the whole sector for us)
+pub fn save_config(flash: &mut FlashStorage<'_>, config: &DeviceConfig) -> Result<(), FlashStorageError> {
+    flash.write(CONFIG_FLASH_OFFSET, &config.to_record())
+}
+
+// Run the setup network until valid settings are posted, then save them and reboot into client mode
+pub fn run_portal(controller: &mut WifiController<'_>, mut device: WifiDevice<'_>, flash: &mut FlashStorage<'_>) -> ! {
+    let ap_config = AccessPointConfig::default().with_ssid(SETUP_SSID.into());
+    controller.set_config(&ModeConfig::AccessPoint(ap_config)).unwrap();
+    controller.start().unwrap();
+    println!("Setup network '{}' started, open http://{}/", SETUP_SSID, SETUP_IP);
+
+    let iface = crate::create_interface(&mut device);
+
+    // One TCP socket for the web server and one UDP socket each for DHCP and DNS
+    let mut socket_set_entries: [SocketStorage; 3] = Default::default();
+    let socket_set = SocketSet::new(&mut socket_set_entries[..]);
+    let now = || time::Instant::now().duration_since_epoch().as_millis();
+    let stack = Stack::new(iface, device, socket_set, now, Rng::new().random());
+
+    stack
+        .set_iface_configuration(&ipv4::Configuration::Client(ipv4::ClientConfiguration::Fixed(
+            ipv4::ClientSettings {
+                ip: ipv4::Ipv4Addr::from(SETUP_IP.octets()),
+                subnet: ipv4::Subnet {
+                    gateway: ipv4::Ipv4Addr::from(SETUP_IP.octets()),
+                    mask: ipv4::Mask(24),
+                },
+                dns: None,
+                secondary_dns: None,
+            },
+        )))
+        .unwrap();
+
+    let mut dhcp_rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut dhcp_rx_buffer = [0u8; 1536];
+    let mut dhcp_tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut dhcp_tx_buffer = [0u8; 1536];
+    let mut dhcp_socket =
+        stack.get_udp_socket(&mut dhcp_rx_meta, &mut dhcp_rx_buffer, &mut dhcp_tx_meta, &mut dhcp_tx_buffer);
+    dhcp_socket.bind(DHCP_SERVER_PORT).unwrap();
+
+    let mut dns_rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut dns_rx_buffer = [0u8; 1024];
+    let mut dns_tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut dns_tx_buffer = [0u8; 1024];
+    let mut dns_socket =
+        stack.get_udp_socket(&mut dns_rx_meta, &mut dns_rx_buffer, &mut dns_tx_meta, &mut dns_tx_buffer);
+    dns_socket.bind(DNS_PORT).unwrap();
+
+    let mut rx_buffer = [0u8; 1536];
+    let mut tx_buffer = [0u8; MAX_RESPONSE_LEN];
+    let mut socket = stack.get_socket(&mut rx_buffer, &mut tx_buffer);
+
+    let mut dhcp = DhcpServer::new(SETUP_IP);
+    let mut packet = [0u8; 576];
+    let mut dhcp_reply = [0u8; DHCP_REPLY_LEN];
+    let mut dns_answer = [0u8; 512];
+
+    loop {
+        socket.work();
+
+        if let Ok((len, _, _)) = dhcp_socket.receive(&mut packet) {
+            if let Some(reply_len) = dhcp.handle(&packet[..len], &mut dhcp_reply) {
+                // The client has no address yet, so the reply is broadcast
+                let _ = dhcp_socket.send(
+                    IpAddress::Ipv4(Ipv4Addr::BROADCAST),
+                    DHCP_CLIENT_PORT,
+                    &dhcp_reply[..reply_len],
+                );
+            }
+        }
+
+        if let Ok((len, from, port)) = dns_socket.receive(&mut packet) {
+            if let Some(reply_len) = dns_reply(&packet[..len], SETUP_IP, &mut dns_answer) {
+                let _ = dns_socket.send(from, port, &dns_answer[..reply_len]);
+            }
+        }
+
+        if !socket.is_open() {
+            socket.listen(80).unwrap();
+        }
+        if !socket.is_connected() {
+            continue;
+        }
+
+        // One request per connection (we answer with Connection: close)
+        let mut request = [0u8; MAX_REQUEST_LEN];
+        let mut received = 0;
+        let deadline = time::Instant::now() + Duration::from_secs(5);
+        let action = loop {
+            match socket.read(&mut request[received..]) {
+                Ok(len) => received += len,
+                Err(_) => break None,
+            }
+            match parse_request(&request[..received]) {
+                ParsedRequest::Complete(request) => break Some(handle_request(&request)),
+                ParsedRequest::Invalid => break None,
+                ParsedRequest::Incomplete if received == request.len() => break None,
+                ParsedRequest::Incomplete => {}
+            }
+            if time::Instant::now() > deadline {
+                break None;
+            }
+        };
+
+        let mut response: String<MAX_RESPONSE_LEN> = String::new();
+        let saved = match action {
+            Some(PortalAction::Save(config)) => {
+                let _ = write_saved_response(&mut response, &config);
+                Some(config)
+            }
+            Some(PortalAction::ShowForm(error)) => {
+                let _ = write_form_response(&mut response, error);
+                None
+            }
+            None => None,
+        };
+        if !response.is_empty() {
+            let _ = socket.write_all(response.as_bytes()).and_then(|_| socket.flush());
+        }
+        socket.close();
+
+        if let Some(config) = saved {
+            // Let the "saved" page reach the phone before the network goes away
+            let deadline = time::Instant::now() + Duration::from_secs(1);
+            while time::Instant::now() < deadline {
+                socket.work();
+            }
+            match save_config(flash, &config) {
+                Ok(()) => {
+                    println!("Saved config for '{}', rebooting", config.ssid);
+                    esp_hal::system::software_reset();
+                }
+                Err(e) => println!("Flash write error: {:?}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,344 @@
+// src/api.rs
+
+// Request and response bodies for every backend route
+// These need the heap (Vec/String), so they are only compiled with the 'alloc' feature
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::pattern::TestPattern;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// For GET /canvas
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CanvasResponse {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Vec<String>>,
+}
+
+// For POST /pixel (The Request Body)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PixelUpdateInput {
+    pub x: u32,
+    pub y: u32,
+    pub color: String,
+}
+
+// For POST /pixel (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PixelUpdateResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+// For POST /reset (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClearCanvasResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+// For GET /updates?since=123456789 (The Query String)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GetUpdatesInput {
+    pub since: u64, // Client sends the timestamp since they last synced
+}
+
+// Every failed request gets this body, whatever the route (4xx and 5xx statuses)
+// Routes that succeed answer their own response type instead
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ErrorResponse {
+    pub success: bool, // Always false, so clients that only look at 'success' keep working
+    pub error: String, // Stable code to match on, e.g. "out_of_bounds"
+    pub message: String, // For people, the wording may change
+}
+
+// Inner Object for Updates (Used inside UpdatesResponse)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PixelUpdate {
+    pub x: u32,
+    pub y: u32,
+    pub color: String,
+    pub timestamp: u64,
+}
+
+// For GET /updates (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UpdatesResponse {
+    pub updates: Vec<PixelUpdate>,
+    pub reset_required: bool, // Tell client if they are too far behind
+    // Newest timestamp in the server's history (0 if empty)
+    // After a reset_required, clients refetch the canvas and continue polling from here
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub latest_timestamp: u64,
+    // The server is stopping. Expect errors for a while and keep retrying rather than giving up
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shutting_down: bool,
+    // Whether pixels can be placed right now, so clients notice when an admin freezes the board
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mode: BoardMode,
+}
+
+// For POST /devices/register and POST /devices/heartbeat (The Request Body)
+// A board registers once after it boots, then sends the same report as a heartbeat every so often
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceReport {
+    pub device_id: String, // e.g. "rustycanvas-a1b2c3d4e5f6", from the board's MAC address
+    pub firmware_version: String,
+    pub uptime_secs: u64,
+    pub rssi: Option<i32>, // Wi-Fi signal strength in dBm, None if the board couldn't read it
+    pub last_applied: u64, // Newest /updates timestamp the board has drawn
+}
+
+// For POST /devices/register and POST /devices/heartbeat (The Response)
+// A heartbeat from a device the server doesn't know is answered with 404, the board then registers again
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceReportResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    // A test pattern an admin asked this board to show, handed over once
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub test_pattern: Option<TestPattern>,
+}
+
+// Inner Object for Devices (Used inside DevicesResponse)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceStatus {
+    pub device_id: String,
+    pub firmware_version: String,
+    pub uptime_secs: u64,
+    pub rssi: Option<i32>,
+    pub last_applied: u64,
+    pub registered_at: u64, // Server time of the last registration (i.e. the board's last boot), in ms
+    pub last_seen: u64,     // Server time of the last report, in ms
+    pub online: bool,       // Reported within the last DEVICE_OFFLINE_AFTER_SECS
+    // Waiting for the board's next report
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pending_test_pattern: Option<TestPattern>,
+}
+
+// For GET /devices (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DevicesResponse {
+    pub devices: Vec<DeviceStatus>,
+}
+
+// For POST /devices/{device_id}/test-pattern (The Request Body)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TestPatternInput {
+    pub pattern: TestPattern,
+}
+
+// For GET /backup (The Response) and POST /restore (The Request Body), also what a snapshot holds
+// A self-contained copy of the board that can be saved to a file and loaded into any server
+// 'format' and 'version' let a server refuse files it doesn't understand (see ARCHIVE_FORMAT/ARCHIVE_VERSION)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CanvasArchive {
+    pub format: String,
+    pub version: u32,
+    pub created_at: u64, // Server time when the archive was taken, in ms
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Vec<String>>, // Same layout as CanvasResponse
+    // The server's recent pixel history, oldest first
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub history: Vec<PixelUpdate>,
+}
+
+// For POST /snapshots (The Request Body)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SnapshotInput {
+    pub name: String,
+}
+
+// Inner Object for Snapshots (Used inside SnapshotsResponse and SnapshotResponse)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SnapshotInfo {
+    pub name: String,
+    pub created_at: u64,
+    pub width: u32,
+    pub height: u32,
+    pub history_len: usize,
+}
+
+// For GET /snapshots (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SnapshotsResponse {
+    pub snapshots: Vec<SnapshotInfo>,
+}
+
+// For POST /snapshots, POST /snapshots/{name}/restore, DELETE /snapshots/{name} and POST /restore (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SnapshotResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    // The snapshot that was saved, restored or deleted (None for a failure or a POST /restore)
+    pub snapshot: Option<SnapshotInfo>,
+}
+
+// Where a scheduled event is (see GET /event)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EventPhase {
+    // No event, the board is open as usual
+    Idle,
+    // Waiting for opens_at, the board is read-only
+    Scheduled,
+    Open,
+    // Past closes_at, the board is read-only until the event is cancelled or replaced
+    Closed,
+}
+
+// When the board opens and closes, and what happens when it closes
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EventSchedule {
+    pub opens_at: u64,  // Server time, in ms
+    pub closes_at: u64, // Server time, in ms
+    // Save the board under this snapshot name when it closes
+    pub snapshot_on_close: Option<String>,
+    // Clear the board when it closes (after the snapshot, if any)
+    pub reset_on_close: bool,
+}
+
+// For POST /event (The Request Body)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EventScheduleInput {
+    // Server time in ms, now if left out
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub opens_at: Option<u64>,
+    pub open_minutes: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub snapshot_on_close: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reset_on_close: bool,
+}
+
+// For GET /event (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EventStatus {
+    pub phase: EventPhase,
+    pub schedule: Option<EventSchedule>,
+    pub server_time: u64, // So clients can count down without trusting their own clock
+}
+
+// For POST /event and DELETE /event (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EventResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    pub event: Option<EventStatus>,
+}
+
+// Set by an admin to freeze the board without stopping the server (see GET /mode)
+// Reading the board and polling for updates work in every mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BoardMode {
+    #[default]
+    Open,
+    // Pixels are refused with "read_only"
+    ReadOnly,
+    // Pixels are refused with "maintenance", clients should try again later
+    Maintenance,
+}
+
+// For POST /mode (The Request Body)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ModeInput {
+    pub mode: BoardMode,
+}
+
+// For GET /mode (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ModeStatus {
+    pub mode: BoardMode,
+    pub changed_at: u64, // Server time in ms, 0 if the mode was never changed
+}
+
+// For POST /mode (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ModeResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    pub mode: Option<ModeStatus>,
+}
+
+// For GET /healthz (The Response)
+// Only says the process is up and answering, see GET /readyz for whether it can do its job
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HealthResponse {
+    pub status: String, // Always "ok"
+    pub uptime_secs: u64,
+}
+
+// Inner Object for Readiness (Used inside ReadinessResponse)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReadinessCheck {
+    pub name: String, // e.g. "store_write"
+    pub ok: bool,
+    pub detail: String,
+}
+
+// For GET /readyz (The Response), sent with 503 when 'ready' is false
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+// Inner Object for the board's fixed settings (Used inside VersionResponse)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CanvasConfig {
+    pub width: u32,
+    pub height: u32,
+    pub default_color: String,
+    pub palette: Vec<String>,
+    pub history_len: usize, // Updates GET /updates can hand out before clients must reload
+    pub flush_ms: u64,      // 0 when every update is written before it's answered
+    pub archive_version: u32,
+}
+
+// For GET /version (The Response)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VersionResponse {
+    pub name: String,
+    pub version: String,
+    pub commit: Option<String>, // Set when the server was built with GIT_COMMIT in the environment
+    pub profile: String,        // "release" or "debug"
+    pub canvas: CanvasConfig,
+}
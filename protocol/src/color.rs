@@ -0,0 +1,125 @@
+// src/color.rs
+
+// A single pixel colour, sent over the wire as a "#RRGGBB" hex string
+
+use core::fmt;
+use core::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+// Reasons a "#RRGGBB" string can be rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseColorError {
+    MissingHash,
+    WrongLength,
+    InvalidHexDigit,
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            ParseColorError::MissingHash => "colour must start with '#'",
+            ParseColorError::WrongLength => "colour must be exactly 7 characters (#RRGGBB)",
+            ParseColorError::InvalidHexDigit => "colour contains a non-hex digit",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb::new(0, 0, 0);
+    pub const WHITE: Rgb = Rgb::new(255, 255, 255);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb { r, g, b }
+    }
+
+    // Parse "#RRGGBB" (either case) into its three channels
+    pub fn parse_hex(s: &str) -> Result<Self, ParseColorError> {
+        let bytes = s.as_bytes();
+        if bytes.first() != Some(&b'#') {
+            return Err(ParseColorError::MissingHash);
+        }
+        if bytes.len() != 7 {
+            return Err(ParseColorError::WrongLength);
+        }
+
+        let channel = |i: usize| -> Result<u8, ParseColorError> {
+            let hi = hex_value(bytes[i])?;
+            let lo = hex_value(bytes[i + 1])?;
+            Ok((hi << 4) | lo)
+        };
+
+        Ok(Rgb::new(channel(1)?, channel(3)?, channel(5)?))
+    }
+
+    // Format as upper-case "#RRGGBB" without allocating
+    pub fn to_hex(self) -> [u8; 7] {
+        const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+        let mut out = [b'#'; 7];
+        for (i, channel) in [self.r, self.g, self.b].into_iter().enumerate() {
+            out[1 + i * 2] = DIGITS[(channel >> 4) as usize];
+            out[2 + i * 2] = DIGITS[(channel & 0x0F) as usize];
+        }
+        out
+    }
+}
+
+fn hex_value(digit: u8) -> Result<u8, ParseColorError> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(ParseColorError::InvalidHexDigit),
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rgb::parse_hex(s)
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = self.to_hex();
+        // to_hex only ever produces ASCII
+        f.write_str(core::str::from_utf8(&hex).unwrap_or("#000000"))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Rgb {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let hex = self.to_hex();
+        serializer.serialize_str(core::str::from_utf8(&hex).unwrap_or("#000000"))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Rgb {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RgbVisitor;
+
+        impl serde::de::Visitor<'_> for RgbVisitor {
+            type Value = Rgb;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a \"#RRGGBB\" colour string")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Rgb, E> {
+                Rgb::parse_hex(v).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(RgbVisitor)
+    }
+}
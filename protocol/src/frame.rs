@@ -0,0 +1,47 @@
+// src/frame.rs
+
+// Fixed-size version of the GET /canvas response
+// It needs no heap, so the firmware can deserialize the board straight into it with serde-json-core
+
+use crate::color::Rgb;
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+
+pub const FRAME_WIDTH: usize = CANVAS_WIDTH as usize;
+pub const FRAME_HEIGHT: usize = CANVAS_HEIGHT as usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CanvasFrame {
+    pub width: u32,
+    pub height: u32,
+    // Indexed as pixels[y][x], same as the JSON
+    pub pixels: [[Rgb; FRAME_WIDTH]; FRAME_HEIGHT],
+}
+
+impl Default for CanvasFrame {
+    fn default() -> Self {
+        CanvasFrame {
+            width: CANVAS_WIDTH,
+            height: CANVAS_HEIGHT,
+            pixels: [[Rgb::BLACK; FRAME_WIDTH]; FRAME_HEIGHT],
+        }
+    }
+}
+
+impl CanvasFrame {
+    // Returns None for coordinates outside the board
+    pub fn get(&self, x: u32, y: u32) -> Option<Rgb> {
+        self.pixels.get(y as usize)?.get(x as usize).copied()
+    }
+
+    // Returns false (and changes nothing) for coordinates outside the board
+    pub fn set(&mut self, x: u32, y: u32, color: Rgb) -> bool {
+        match self.pixels.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)) {
+            Some(pixel) => {
+                *pixel = color;
+                true
+            }
+            None => false,
+        }
+    }
+}
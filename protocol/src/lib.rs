@@ -0,0 +1,89 @@
+// src/lib.rs
+
+// Shared wire types for every RustyCanvas component (backend, frontend and firmware)
+// Keeping them in one crate means the JSON shape of each route is defined exactly once
+
+// For your knowledge
+// The crate is 'no_std' so the ESP32 firmware can depend on it
+// The heap-backed API types (Vec/String) are behind the 'alloc' feature, and all serde derives are behind the 'serde' feature
+// The firmware uses 'default-features = false, features = ["serde"]' and only gets the fixed-size types
+
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod color;
+pub mod frame;
+pub mod pattern;
+
+#[cfg(feature = "alloc")]
+pub mod api;
+
+pub use color::{ParseColorError, Rgb};
+pub use frame::CanvasFrame;
+pub use pattern::TestPattern;
+
+#[cfg(feature = "alloc")]
+pub use api::{
+    BoardMode,
+    CanvasArchive,
+    CanvasConfig,
+    CanvasResponse,
+    ClearCanvasResponse,
+    DeviceReport,
+    DeviceReportResponse,
+    DeviceStatus,
+    DevicesResponse,
+    ErrorResponse,
+    EventPhase,
+    EventResponse,
+    EventSchedule,
+    EventScheduleInput,
+    EventStatus,
+    GetUpdatesInput,
+    HealthResponse,
+    ModeInput,
+    ModeResponse,
+    ModeStatus,
+    PixelUpdate,
+    PixelUpdateInput,
+    PixelUpdateResponse,
+    ReadinessCheck,
+    ReadinessResponse,
+    SnapshotInfo,
+    SnapshotInput,
+    SnapshotResponse,
+    SnapshotsResponse,
+    TestPatternInput,
+    UpdatesResponse,
+    VersionResponse,
+};
+
+pub const CANVAS_WIDTH: u32 = 32;
+pub const CANVAS_HEIGHT: u32 = 16;
+pub const DEFAULT_COLOR: &str = "#000000";
+
+// DNS-SD service type the backend advertises over mDNS, so boards can find it on the LAN without an address
+pub const MDNS_SERVICE_TYPE: &str = "_rustycanvas._tcp.local";
+
+// Boards send a heartbeat every HEARTBEAT_INTERVAL_SECS, and are listed as offline after missing a few
+pub const HEARTBEAT_INTERVAL_SECS: u64 = 30;
+pub const DEVICE_OFFLINE_AFTER_SECS: u64 = 3 * HEARTBEAT_INTERVAL_SECS;
+
+// Identifies a CanvasArchive (GET /backup, snapshots). Bump the version when the archive changes incompatibly,
+// servers refuse archives newer than the version they know
+pub const ARCHIVE_FORMAT: &str = "rustycanvas-archive";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+// Allowable colours for the palette (shared by every client UI)
+pub const PALETTE: &[&str] = &[
+    "#000000", // Black
+    "#FFFFFF", // White
+    "#FF0000", // Red
+    "#00FF00", // Green
+    "#0000FF", // Blue
+    "#FFFF00", // Yellow
+    "#00FFFF", // Cyan
+    "#FF00FF", // Magenta
+];
@@ -0,0 +1,48 @@
+// src/pattern.rs
+
+// Display test patterns, asked for by an admin through the backend and drawn by the firmware
+
+// For your knowledge
+// On the wire a pattern is its snake_case name, e.g. "color_bars"
+// The pixels of each pattern are worked out on the board (firmware-core/src/patterns.rs), only the name travels
+
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TestPattern {
+    // One white pixel walking through every position, in canvas order
+    PixelWalk,
+    // Vertical bars in each palette colour
+    ColorBars,
+    // Every pixel white for a while (power supply and hot spots)
+    FullWhite,
+    // Red across, green down
+    Gradient,
+}
+
+impl TestPattern {
+    // Every pattern, in the order the boot-time diagnostics run them
+    pub const ALL: [TestPattern; 4] =
+        [TestPattern::PixelWalk, TestPattern::ColorBars, TestPattern::Gradient, TestPattern::FullWhite];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TestPattern::PixelWalk => "pixel_walk",
+            TestPattern::ColorBars => "color_bars",
+            TestPattern::FullWhite => "full_white",
+            TestPattern::Gradient => "gradient",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<TestPattern> {
+        TestPattern::ALL.into_iter().find(|pattern| pattern.as_str() == name)
+    }
+}
+
+impl fmt::Display for TestPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,231 @@
+// tests/roundtrip_tests.rs
+
+// Round-trip tests: every shared type must survive serialize -> deserialize unchanged,
+// and the fixed-size frame must parse the exact JSON the backend sends
+
+use protocol::{
+    BoardMode,
+    CanvasArchive,
+    CanvasConfig,
+    CanvasFrame,
+    CanvasResponse,
+    ClearCanvasResponse,
+    DeviceReport,
+    DeviceReportResponse,
+    DeviceStatus,
+    DevicesResponse,
+    ErrorResponse,
+    EventPhase,
+    EventResponse,
+    EventSchedule,
+    EventScheduleInput,
+    EventStatus,
+    GetUpdatesInput,
+    HealthResponse,
+    ModeInput,
+    ModeResponse,
+    ModeStatus,
+    ParseColorError,
+    PixelUpdate,
+    PixelUpdateInput,
+    PixelUpdateResponse,
+    ReadinessCheck,
+    ReadinessResponse,
+    Rgb,
+    SnapshotInfo,
+    SnapshotInput,
+    SnapshotResponse,
+    SnapshotsResponse,
+    TestPattern,
+    TestPatternInput,
+    UpdatesResponse,
+    VersionResponse,
+    CANVAS_HEIGHT,
+    CANVAS_WIDTH,
+    ARCHIVE_FORMAT,
+    ARCHIVE_VERSION,
+    DEFAULT_COLOR,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+// Test helper to push a value through JSON and back
+fn roundtrip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let json = serde_json::to_string(value).unwrap();
+    let back: T = serde_json::from_str(&json).unwrap();
+    assert_eq!(&back, value, "round trip changed the value (json: {})", json);
+}
+
+// Test helper to build the same body GET /canvas returns
+fn full_canvas_response(color: &str) -> CanvasResponse {
+    CanvasResponse {
+        width: CANVAS_WIDTH,
+        height: CANVAS_HEIGHT,
+        pixels: vec![vec![color.to_string(); CANVAS_WIDTH as usize]; CANVAS_HEIGHT as usize],
+    }
+}
+
+#[test]
+fn test_api_types_roundtrip() {
+    roundtrip(&full_canvas_response(DEFAULT_COLOR));
+    roundtrip(&PixelUpdateInput { x: 3, y: 4, color: "#ABCDEF".to_string() });
+    roundtrip(&PixelUpdateResponse { success: false, error: Some("out_of_bounds".to_string()) });
+    roundtrip(&PixelUpdateResponse { success: true, error: None });
+    roundtrip(&ErrorResponse { success: false, error: "out_of_bounds".to_string(), message: "pixel is off the board".to_string() });
+    roundtrip(&ClearCanvasResponse { success: true, message: "Canvas reset successfully".to_string() });
+    roundtrip(&GetUpdatesInput { since: 1_700_000_000_000 });
+    roundtrip(&UpdatesResponse {
+        updates: vec![PixelUpdate { x: 1, y: 2, color: "#FF0000".to_string(), timestamp: 42 }],
+        reset_required: false,
+        latest_timestamp: 42,
+        shutting_down: false,
+        mode: BoardMode::ReadOnly,
+    });
+    roundtrip(&DeviceReport {
+        device_id: "rustycanvas-a1b2c3d4e5f6".to_string(),
+        firmware_version: "0.1.0".to_string(),
+        uptime_secs: 3600,
+        rssi: Some(-61),
+        last_applied: 42,
+    });
+    roundtrip(&DeviceReportResponse { success: false, error: Some("unknown_device".to_string()), test_pattern: None });
+    roundtrip(&DeviceReportResponse { success: true, error: None, test_pattern: Some(TestPattern::ColorBars) });
+    roundtrip(&TestPatternInput { pattern: TestPattern::PixelWalk });
+    roundtrip(&DevicesResponse {
+        devices: vec![DeviceStatus {
+            device_id: "rustycanvas-a1b2c3d4e5f6".to_string(),
+            firmware_version: "0.1.0".to_string(),
+            uptime_secs: 3600,
+            rssi: None,
+            last_applied: 42,
+            registered_at: 1_700_000_000_000,
+            last_seen: 1_700_003_600_000,
+            online: true,
+            pending_test_pattern: Some(TestPattern::Gradient),
+        }],
+    });
+    roundtrip(&CanvasArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at: 1_700_000_000_000,
+        width: 2,
+        height: 1,
+        pixels: vec![vec!["#FF0000".to_string(), "#000000".to_string()]],
+        history: vec![PixelUpdate { x: 0, y: 0, color: "#FF0000".to_string(), timestamp: 42 }],
+    });
+    roundtrip(&SnapshotInput { name: "before-reset".to_string() });
+    let info = SnapshotInfo { name: "before-reset".to_string(), created_at: 1_700_000_000_000, width: 32, height: 16, history_len: 3 };
+    roundtrip(&SnapshotsResponse { snapshots: vec![info.clone()] });
+    roundtrip(&SnapshotResponse { success: true, error: None, snapshot: Some(info) });
+    roundtrip(&SnapshotResponse { success: false, error: Some("unknown_snapshot".to_string()), snapshot: None });
+    roundtrip(&EventScheduleInput { opens_at: None, open_minutes: 5, snapshot_on_close: Some("demo".to_string()), reset_on_close: true });
+    let schedule = EventSchedule { opens_at: 1_000, closes_at: 301_000, snapshot_on_close: None, reset_on_close: false };
+    let status = EventStatus { phase: EventPhase::Open, schedule: Some(schedule), server_time: 2_000 };
+    roundtrip(&EventResponse { success: true, error: None, event: Some(status) });
+    roundtrip(&EventResponse { success: false, error: Some("invalid_duration".to_string()), event: None });
+    roundtrip(&ModeInput { mode: BoardMode::Maintenance });
+    roundtrip(&ModeResponse { success: true, error: None, mode: Some(ModeStatus { mode: BoardMode::Open, changed_at: 42 }) });
+    roundtrip(&HealthResponse { status: "ok".to_string(), uptime_secs: 3600 });
+    roundtrip(&ReadinessResponse {
+        ready: false,
+        checks: vec![
+            ReadinessCheck { name: "store_read".to_string(), ok: true, detail: "store answered".to_string() },
+            ReadinessCheck { name: "store_write".to_string(), ok: false, detail: "could not write to the store".to_string() },
+        ],
+    });
+    let canvas = CanvasConfig {
+        width: CANVAS_WIDTH,
+        height: CANVAS_HEIGHT,
+        default_color: DEFAULT_COLOR.to_string(),
+        palette: vec!["#000000".to_string(), "#FFFFFF".to_string()],
+        history_len: 50,
+        flush_ms: 100,
+        archive_version: ARCHIVE_VERSION,
+    };
+    let version = VersionResponse {
+        name: "backend".to_string(),
+        version: "0.1.0".to_string(),
+        commit: Some("90c8602".to_string()),
+        profile: "release".to_string(),
+        canvas,
+    };
+    roundtrip(&version);
+    roundtrip(&VersionResponse { commit: None, profile: "debug".to_string(), ..version });
+}
+
+#[test]
+fn test_wire_field_names_are_stable() {
+    let body = serde_json::to_value(PixelUpdateResponse { success: true, error: None }).unwrap();
+    assert_eq!(body, serde_json::json!({ "success": true, "error": null }));
+
+    let body = serde_json::to_value(UpdatesResponse { updates: vec![], reset_required: true, latest_timestamp: 7, shutting_down: true, mode: BoardMode::ReadOnly }).unwrap();
+    assert_eq!(body, serde_json::json!({ "updates": [], "reset_required": true, "latest_timestamp": 7, "shutting_down": true, "mode": "read_only" }));
+
+    // Older servers don't send latest_timestamp, shutting_down or mode
+    let old: UpdatesResponse = serde_json::from_str(r#"{ "updates": [], "reset_required": false }"#).unwrap();
+    assert_eq!(old.latest_timestamp, 0);
+    assert!(!old.shutting_down);
+    assert_eq!(old.mode, BoardMode::Open);
+
+    // Only the duration is required to schedule an event
+    let input: EventScheduleInput = serde_json::from_str(r#"{ "open_minutes": 5 }"#).unwrap();
+    assert_eq!(input, EventScheduleInput { opens_at: None, open_minutes: 5, snapshot_on_close: None, reset_on_close: false });
+    assert_eq!(serde_json::to_value(EventPhase::Scheduled).unwrap(), serde_json::json!("scheduled"));
+
+    let body = serde_json::to_value(TestPatternInput { pattern: TestPattern::FullWhite }).unwrap();
+    assert_eq!(body, serde_json::json!({ "pattern": "full_white" }));
+}
+
+#[test]
+fn test_pattern_names() {
+    for pattern in TestPattern::ALL {
+        assert_eq!(TestPattern::parse(pattern.as_str()), Some(pattern));
+        // The wire name is the same one parse() takes
+        assert_eq!(serde_json::to_value(pattern).unwrap(), serde_json::json!(pattern.as_str()));
+    }
+    assert_eq!(TestPattern::parse("ColorBars"), None);
+}
+
+#[test]
+fn test_rgb_parse_and_format() {
+    assert_eq!(Rgb::parse_hex("#FF8000"), Ok(Rgb::new(255, 128, 0)));
+    assert_eq!(Rgb::parse_hex("#ff8000"), Ok(Rgb::new(255, 128, 0)));
+    assert_eq!(Rgb::new(1, 171, 255).to_string(), "#01ABFF");
+
+    assert_eq!(Rgb::parse_hex("FF8000"), Err(ParseColorError::MissingHash));
+    assert_eq!(Rgb::parse_hex("#FFF"), Err(ParseColorError::WrongLength));
+    assert_eq!(Rgb::parse_hex("#UNIQUE"), Err(ParseColorError::InvalidHexDigit));
+}
+
+#[test]
+fn test_rgb_roundtrip() {
+    roundtrip(&Rgb::new(0x12, 0x34, 0x56));
+    assert_eq!(serde_json::to_string(&Rgb::WHITE).unwrap(), "\"#FFFFFF\"");
+    assert!(serde_json::from_str::<Rgb>("\"#GG0000\"").is_err());
+}
+
+#[test]
+fn test_frame_parses_backend_json_with_serde_json_core() {
+    // Build the body through the alloc type, exactly as the backend would
+    let mut response = full_canvas_response(DEFAULT_COLOR);
+    response.pixels[5][10] = "#ABCDEF".to_string();
+    let json = serde_json::to_string(&response).unwrap();
+
+    // Parse it the way the firmware does (no heap)
+    let (frame, _) = serde_json_core::from_str::<CanvasFrame>(&json).unwrap();
+
+    assert_eq!(frame.width, CANVAS_WIDTH);
+    assert_eq!(frame.height, CANVAS_HEIGHT);
+    assert_eq!(frame.get(10, 5), Some(Rgb::new(0xAB, 0xCD, 0xEF)));
+    assert_eq!(frame.get(0, 0), Some(Rgb::BLACK));
+}
+
+#[test]
+fn test_frame_roundtrip_and_bounds() {
+    let mut frame = CanvasFrame::default();
+    assert!(frame.set(31, 15, Rgb::WHITE));
+    assert!(!frame.set(32, 0, Rgb::WHITE));
+    assert_eq!(frame.get(0, 16), None);
+
+    roundtrip(&frame);
+}
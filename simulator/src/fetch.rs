@@ -0,0 +1,28 @@
+// src/fetch.rs
+
+// Fetches GET /canvas the way the firmware does: the firmware's own HTTP/1.1 request over a plain TCP socket
+// (asking the server to close the connection afterwards, so we can simply read to the end)
+
+use firmware_core::http::write_get_request;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+// Same read deadline as the firmware
+const READ_TIMEOUT: Duration = Duration::from_secs(20);
+
+// 'server' is "host:port", e.g. "127.0.0.1:8080"
+pub fn fetch_canvas_response(server: &str) -> io::Result<Vec<u8>> {
+    let mut request = String::new();
+    // Writing into a String can't fail
+    let _ = write_get_request(&mut request, server, "/canvas", false);
+
+    let mut stream = TcpStream::connect(server)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    Ok(response)
+}
@@ -0,0 +1,8 @@
+// src/lib.rs
+
+// Desktop stand-in for the ESP32 + LED panel
+// It runs the firmware's own parsing and rendering code (firmware-core) against a simulated panel,
+// so display logic can be checked (and tested in CI) with no board attached
+
+pub mod fetch;
+pub mod panel;
@@ -0,0 +1,94 @@
+// src/main.rs
+
+// 'rustycanvas-sim': run the firmware's display pipeline on the desktop
+// Either fetch from a live server (like the board does) or read a saved response/JSON file,
+// then write the simulated panel to a PNG and/or print it to the terminal
+
+use clap::Parser;
+use firmware_core::canvas::{parse_canvas_json, parse_canvas_response};
+use firmware_core::render::render_frame;
+use protocol::CanvasFrame;
+use simulator::fetch::fetch_canvas_response;
+use simulator::panel::SimPanel;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "rustycanvas-sim", about = "Simulate the RustyCanvas LED panel on the host")]
+struct Args {
+    /// Server to fetch from, as host:port
+    #[arg(long, default_value = "127.0.0.1:8080", conflicts_with = "input")]
+    server: String,
+
+    /// Read a saved HTTP response or JSON body instead of contacting a server
+    #[arg(long)]
+    input: Option<PathBuf>,
+
+    /// Write the panel to this PNG file after every refresh
+    #[arg(long)]
+    png: Option<PathBuf>,
+
+    /// Size of one LED in the PNG, in pixels
+    #[arg(long, default_value_t = 16)]
+    scale: u32,
+
+    /// Print the panel to the terminal (default when --png is not given)
+    #[arg(long)]
+    terminal: bool,
+
+    /// Refetch every N seconds like the firmware does (0 = run once)
+    #[arg(long, default_value_t = 0)]
+    interval_secs: u64,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut panel = SimPanel::default();
+
+    loop {
+        if let Err(err) = refresh(&args, &mut panel) {
+            eprintln!("error: {}", err);
+            if args.interval_secs == 0 {
+                return ExitCode::FAILURE;
+            }
+        }
+
+        if args.interval_secs == 0 {
+            return ExitCode::SUCCESS;
+        }
+        std::thread::sleep(Duration::from_secs(args.interval_secs));
+    }
+}
+
+// One pass of the firmware loop: get the canvas, parse it, render it, output it
+fn refresh(args: &Args, panel: &mut SimPanel) -> Result<(), Box<dyn std::error::Error>> {
+    let frame = load_frame(args)?;
+    let Ok(()) = render_frame(&frame, panel); // SimPanel can't fail
+
+    if let Some(path) = &args.png {
+        panel.to_image(args.scale).save(path)?;
+        println!("frame {} written to {}", panel.frames, path.display());
+    }
+    if args.terminal || args.png.is_none() {
+        print!("{}", panel.to_ansi());
+    }
+    Ok(())
+}
+
+fn load_frame(args: &Args) -> Result<CanvasFrame, Box<dyn std::error::Error>> {
+    let frame = match &args.input {
+        // Saved files may be a full HTTP response or just the JSON body
+        Some(path) => {
+            let bytes = std::fs::read(path)?;
+            if bytes.starts_with(b"HTTP/") {
+                parse_canvas_response(&bytes)
+            } else {
+                parse_canvas_json(std::str::from_utf8(&bytes)?)
+            }
+        }
+        None => parse_canvas_response(&fetch_canvas_response(&args.server)?),
+    };
+
+    frame.map_err(|err| format!("could not parse canvas: {:?}", err).into())
+}
@@ -0,0 +1,107 @@
+// src/panel.rs
+
+// A simulated 32x16 LED panel that can be saved as a PNG or printed to the terminal
+
+use firmware_core::render::Panel;
+use image::{Rgb as ImageRgb, RgbImage};
+use protocol::{Rgb, CANVAS_HEIGHT, CANVAS_WIDTH};
+use std::convert::Infallible;
+
+// Colour of the gaps between LEDs in the PNG output
+const GAP_COLOR: ImageRgb<u8> = ImageRgb([16, 16, 16]);
+
+pub struct SimPanel {
+    width: u32,
+    height: u32,
+    // What set_pixel has written since the last show()
+    staged: Vec<Rgb>,
+    // What the "LEDs" are currently displaying
+    shown: Vec<Rgb>,
+    // How many times show() has been called
+    pub frames: u32,
+}
+
+impl Default for SimPanel {
+    fn default() -> Self {
+        SimPanel::new(CANVAS_WIDTH, CANVAS_HEIGHT)
+    }
+}
+
+impl SimPanel {
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = (width * height) as usize;
+        SimPanel {
+            width,
+            height,
+            staged: vec![Rgb::BLACK; len],
+            shown: vec![Rgb::BLACK; len],
+            frames: 0,
+        }
+    }
+
+    // The colour currently visible at (x, y)
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Rgb> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.shown[(y * self.width + x) as usize])
+    }
+
+    // Each LED becomes a 'scale' x 'scale' square, with a 1px dark gap when scale >= 4 so the grid is visible
+    pub fn to_image(&self, scale: u32) -> RgbImage {
+        let scale = scale.max(1);
+        let gap = u32::from(scale >= 4);
+
+        RgbImage::from_fn(self.width * scale, self.height * scale, |px, py| {
+            let (x, y) = (px / scale, py / scale);
+            if px % scale < gap || py % scale < gap {
+                return GAP_COLOR;
+            }
+            let color = self.shown[(y * self.width + x) as usize];
+            ImageRgb([color.r, color.g, color.b])
+        })
+    }
+
+    // Terminal view using upper half blocks: one character cell shows two panel rows
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+
+        for row in (0..self.height).step_by(2) {
+            for x in 0..self.width {
+                let top = self.shown[(row * self.width + x) as usize];
+                let bottom = if row + 1 < self.height {
+                    self.shown[((row + 1) * self.width + x) as usize]
+                } else {
+                    Rgb::BLACK
+                };
+                out.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                    top.r, top.g, top.b, bottom.r, bottom.g, bottom.b
+                ));
+            }
+            out.push_str("\x1b[0m\n");
+        }
+
+        out
+    }
+}
+
+impl Panel for SimPanel {
+    type Error = Infallible;
+
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.staged[(y * self.width + x) as usize] = color;
+        }
+    }
+
+    fn show(&mut self) -> Result<(), Self::Error> {
+        self.shown.copy_from_slice(&self.staged);
+        self.frames += 1;
+        Ok(())
+    }
+}
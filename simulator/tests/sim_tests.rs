@@ -0,0 +1,100 @@
+// tests/sim_tests.rs
+
+// End-to-end tests of the firmware display pipeline on the host:
+// backend -> raw HTTP/1.0 fetch -> firmware-core parsing -> rendering -> simulated panel
+
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use backend::server::handlers::{apply_pixel_update, PixelUpdateInput};
+use backend::server::routes::create_router;
+use backend::server::state::init_app_state;
+use backend::server::store::MemoryStore;
+use backend::server::cache::WritePolicy;
+use firmware_core::canvas::{parse_canvas_json, parse_canvas_response};
+use firmware_core::render::{render_frame, Panel};
+use protocol::{CanvasFrame, Rgb};
+use simulator::fetch::fetch_canvas_response;
+use simulator::panel::SimPanel;
+
+#[test]
+fn test_panel_shows_nothing_until_show() {
+    let mut frame = CanvasFrame::default();
+    frame.set(1, 1, Rgb::WHITE);
+    let mut panel = SimPanel::default();
+
+    panel.set_pixel(1, 1, Rgb::WHITE);
+    assert_eq!(panel.pixel(1, 1), Some(Rgb::BLACK));
+
+    render_frame(&frame, &mut panel).unwrap();
+    assert_eq!(panel.pixel(1, 1), Some(Rgb::WHITE));
+    assert_eq!(panel.frames, 1);
+}
+
+#[test]
+fn test_png_output_matches_panel() {
+    let mut frame = CanvasFrame::default();
+    frame.set(0, 0, Rgb::new(255, 0, 0));
+    frame.set(31, 15, Rgb::new(0, 0, 255));
+    let mut panel = SimPanel::default();
+    render_frame(&frame, &mut panel).unwrap();
+
+    let image = panel.to_image(8);
+
+    assert_eq!(image.dimensions(), (32 * 8, 16 * 8));
+    // Centre of the first LED is red, centre of the last is blue, the gap is dark
+    assert_eq!(image.get_pixel(4, 4).0, [255, 0, 0]);
+    assert_eq!(image.get_pixel(31 * 8 + 4, 15 * 8 + 4).0, [0, 0, 255]);
+    assert_eq!(image.get_pixel(8, 4).0, [16, 16, 16]);
+}
+
+#[test]
+fn test_terminal_output_has_one_line_per_two_rows() {
+    let mut frame = CanvasFrame::default();
+    frame.set(0, 0, Rgb::new(255, 0, 0));
+    frame.set(0, 1, Rgb::new(0, 255, 0));
+    let mut panel = SimPanel::default();
+    render_frame(&frame, &mut panel).unwrap();
+
+    let ansi = panel.to_ansi();
+
+    assert_eq!(ansi.lines().count(), 8);
+    assert!(ansi.starts_with("\x1b[38;2;255;0;0m\x1b[48;2;0;255;0m▀"));
+}
+
+#[test]
+fn test_json_fixture_renders() {
+    let row = format!("[{}]", vec!["\"#00FF00\""; 32].join(","));
+    let json = format!("{{\"width\":32,\"height\":16,\"pixels\":[{}]}}", vec![row; 16].join(","));
+
+    let frame = parse_canvas_json(&json).unwrap();
+    let mut panel = SimPanel::default();
+    render_frame(&frame, &mut panel).unwrap();
+
+    assert_eq!(panel.pixel(17, 9), Some(Rgb::new(0, 255, 0)));
+}
+
+// Fetch from the real backend with the firmware's HTTP/1.0 request
+#[tokio::test(flavor = "multi_thread")]
+async fn test_simulator_renders_live_backend() {
+    let app_state = init_app_state(Arc::new(MemoryStore::new()), WritePolicy::WriteThrough);
+    let input = PixelUpdateInput { x: 4, y: 2, color: "#ABCDEF".to_string() };
+    apply_pixel_update(&app_state.canvas, &input).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        axum::serve(listener, create_router(app_state)).await.unwrap();
+    });
+
+    let response = tokio::task::spawn_blocking(move || fetch_canvas_response(&server))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let frame = parse_canvas_response(&response).unwrap();
+    let mut panel = SimPanel::default();
+    render_frame(&frame, &mut panel).unwrap();
+
+    assert_eq!(panel.pixel(4, 2), Some(Rgb::new(0xAB, 0xCD, 0xEF)));
+    assert_eq!(panel.pixel(0, 0), Some(Rgb::BLACK));
+}
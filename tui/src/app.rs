@@ -0,0 +1,146 @@
+// src/app.rs
+
+// All of the TUI's state and the logic that changes it
+// Nothing in here touches the terminal or the network, so it can be unit tested directly
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use protocol::{BoardMode, CanvasResponse, PixelUpdate, CANVAS_HEIGHT, CANVAS_WIDTH, DEFAULT_COLOR, PALETTE};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionStatus {
+    Connecting,
+    Connected,
+    Disconnected(String),
+}
+
+// Results coming back from the background network tasks
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    // Full board (first load, or after falling too far behind)
+    Canvas(CanvasResponse),
+    // A successful poll of /updates (possibly empty)
+    Updates(Vec<PixelUpdate>),
+    // Our own POST /pixel finished
+    Placed { x: u32, y: u32, result: Result<(), String> },
+    // A poll failed
+    Error(String),
+    // An admin switched the board to open, read-only or maintenance
+    Mode(BoardMode),
+}
+
+// Work the event loop should start on behalf of a key press
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Place { x: u32, y: u32, color: String },
+    Refresh,
+}
+
+pub struct App {
+    pub canvas: CanvasResponse,
+    pub cursor: (u32, u32),
+    pub palette_index: usize,
+    pub status: ConnectionStatus,
+    // Last thing worth telling the user (shown in the status bar)
+    pub notice: Option<String>,
+    // While it isn't Open, placing pixels will be refused
+    pub mode: BoardMode,
+    pub should_quit: bool,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        App {
+            canvas: blank_canvas(),
+            cursor: (0, 0),
+            palette_index: 0,
+            status: ConnectionStatus::Connecting,
+            notice: None,
+            mode: BoardMode::Open,
+            should_quit: false,
+        }
+    }
+}
+
+impl App {
+    pub fn selected_color(&self) -> &'static str {
+        PALETTE[self.palette_index]
+    }
+
+    // Move the cursor, stopping at the board edges
+    pub fn move_cursor(&mut self, dx: i32, dy: i32) {
+        let max_x = self.canvas.width.saturating_sub(1) as i64;
+        let max_y = self.canvas.height.saturating_sub(1) as i64;
+        self.cursor.0 = (self.cursor.0 as i64 + dx as i64).clamp(0, max_x) as u32;
+        self.cursor.1 = (self.cursor.1 as i64 + dy as i64).clamp(0, max_y) as u32;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.should_quit = true,
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(1, 0),
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(0, -1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(0, 1),
+            KeyCode::Tab => self.palette_index = (self.palette_index + 1) % PALETTE.len(),
+            KeyCode::BackTab => self.palette_index = (self.palette_index + PALETTE.len() - 1) % PALETTE.len(),
+            // Number keys pick a palette colour directly
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index < PALETTE.len() {
+                    self.palette_index = index;
+                }
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                let (x, y) = self.cursor;
+                return Some(Action::Place { x, y, color: self.selected_color().to_string() });
+            }
+            KeyCode::Char('r') => return Some(Action::Refresh),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Canvas(canvas) => {
+                self.canvas = canvas;
+                self.move_cursor(0, 0); // re-clamp in case the board size changed
+                self.status = ConnectionStatus::Connected;
+            }
+            Message::Updates(updates) => {
+                for update in &updates {
+                    self.apply_update(update);
+                }
+                self.status = ConnectionStatus::Connected;
+            }
+            Message::Placed { x, y, result } => {
+                self.notice = Some(match result {
+                    Ok(()) => format!("placed at ({}, {})", x, y),
+                    Err(err) => format!("could not place at ({}, {}): {}", x, y, err),
+                });
+            }
+            Message::Error(err) => self.status = ConnectionStatus::Disconnected(err),
+            Message::Mode(mode) => self.mode = mode,
+        }
+    }
+
+    // Write one update into the local copy of the board (ignoring anything off the board)
+    pub fn apply_update(&mut self, update: &PixelUpdate) {
+        if let Some(pixel) = self.canvas.pixels
+            .get_mut(update.y as usize)
+            .and_then(|row| row.get_mut(update.x as usize))
+        {
+            *pixel = update.color.clone();
+        }
+    }
+}
+
+// Shown until the first GET /canvas succeeds
+pub fn blank_canvas() -> CanvasResponse {
+    CanvasResponse {
+        width: CANVAS_WIDTH,
+        height: CANVAS_HEIGHT,
+        pixels: vec![vec![DEFAULT_COLOR.to_string(); CANVAS_WIDTH as usize]; CANVAS_HEIGHT as usize],
+    }
+}
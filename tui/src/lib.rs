@@ -0,0 +1,7 @@
+// src/lib.rs
+
+// Library half of the terminal viewer, split out so /tests/ can drive the app logic and rendering
+
+pub mod app;
+pub mod net;
+pub mod ui;
@@ -0,0 +1,72 @@
+// src/main.rs
+
+// 'rustycanvas-tui': live terminal viewer for headless demo machines
+// Shows the board, follows /updates in real time and lets you place pixels from the keyboard
+
+use clap::Parser;
+use client::CanvasClient;
+use crossterm::event::{Event, EventStream, KeyEventKind};
+use futures_util::StreamExt;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tui::app::{Action, App};
+use tui::{net, ui};
+
+#[derive(Parser)]
+#[command(name = "rustycanvas-tui", about = "Live terminal viewer for a RustyCanvas server")]
+struct Args {
+    /// Server root URL
+    #[arg(long, env = "RUSTYCANVAS_SERVER", default_value = "http://127.0.0.1:8080")]
+    server: String,
+
+    /// How often to poll /updates, in milliseconds
+    #[arg(long, default_value_t = 250)]
+    interval_ms: u64,
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let client = CanvasClient::new(args.server);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(net::follow_updates(client.clone(), tx.clone(), Duration::from_millis(args.interval_ms)));
+
+    // ratatui::init switches to the alternate screen + raw mode and restores the terminal on panic
+    let mut terminal = ratatui::init();
+    let mut events = EventStream::new();
+    let mut app = App::default();
+
+    let result = loop {
+        if let Err(err) = terminal.draw(|frame| ui::draw(frame, &app)) {
+            break Err(err);
+        }
+
+        tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    match app.handle_key(key) {
+                        Some(Action::Place { x, y, color }) => {
+                            tokio::spawn(net::place_pixel(client.clone(), tx.clone(), x, y, color));
+                        }
+                        Some(Action::Refresh) => {
+                            tokio::spawn(net::refresh(client.clone(), tx.clone()));
+                        }
+                        None => {}
+                    }
+                }
+                Some(Ok(_)) => {} // resize, mouse, ... just redraw
+                Some(Err(err)) => break Err(err),
+                None => break Ok(()),
+            },
+            Some(message) = rx.recv() => app.handle_message(message),
+        }
+
+        if app.should_quit {
+            break Ok(());
+        }
+    };
+
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,88 @@
+// src/net.rs
+
+// Background network tasks, they report back to the event loop as app::Message values
+
+use crate::app::Message;
+use client::{BoardMode, CanvasClient};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedSender;
+
+// Load the board, then follow /updates forever (until the receiver is dropped)
+// Every poll reports back, even an empty one, so the UI always knows whether the server is reachable
+pub async fn follow_updates(client: CanvasClient, tx: UnboundedSender<Message>, poll_interval: Duration) {
+    // Take the timestamp before the first fetch so nothing placed in between is missed
+    let mut since = now_millis();
+    let mut need_canvas = true;
+    let mut mode = BoardMode::Open;
+
+    loop {
+        let message = if need_canvas {
+            match client.get_canvas().await {
+                Ok(canvas) => {
+                    need_canvas = false;
+                    Message::Canvas(canvas)
+                }
+                Err(err) => Message::Error(err.to_string()),
+            }
+        } else {
+            let response = client.get_updates(since).await;
+            // Tell the UI when an admin freezes or reopens the board
+            if let Ok(response) = &response
+                && response.mode != mode
+            {
+                mode = response.mode;
+                if tx.send(Message::Mode(mode)).is_err() {
+                    return;
+                }
+            }
+            match response {
+                Ok(response) if response.reset_required => {
+                    // Reload the board, then carry on from the server's newest update
+                    since = response.latest_timestamp;
+                    need_canvas = true;
+                    continue;
+                }
+                Ok(response) if response.shutting_down => {
+                    // Keep what it sent, but show the server as going away until it's back
+                    since = response.updates.iter().map(|u| u.timestamp).fold(since, u64::max);
+                    if !response.updates.is_empty() && tx.send(Message::Updates(response.updates)).is_err() {
+                        return;
+                    }
+                    Message::Error("server is shutting down".to_string())
+                }
+                Ok(response) => {
+                    since = response.updates.iter().map(|u| u.timestamp).fold(since, u64::max);
+                    Message::Updates(response.updates)
+                }
+                Err(err) => Message::Error(err.to_string()),
+            }
+        };
+
+        if tx.send(message).is_err() {
+            return; // UI has exited
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+// POST /pixel and report the outcome
+pub async fn place_pixel(client: CanvasClient, tx: UnboundedSender<Message>, x: u32, y: u32, color: String) {
+    let result = client.place_pixel(x, y, &color).await.map_err(|err| err.to_string());
+    let _ = tx.send(Message::Placed { x, y, result });
+}
+
+// One-off full reload (the 'r' key)
+pub async fn refresh(client: CanvasClient, tx: UnboundedSender<Message>) {
+    let message = match client.get_canvas().await {
+        Ok(canvas) => Message::Canvas(canvas),
+        Err(err) => Message::Error(err.to_string()),
+    };
+    let _ = tx.send(message);
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,115 @@
+// src/ui.rs
+
+// Draws the App into a ratatui frame
+
+// For your knowledge
+// A terminal cell is about twice as tall as it is wide, so each cell shows TWO canvas rows:
+// the upper half block '▀' is painted with the top pixel as foreground and the bottom pixel as background
+// The 32x16 board therefore takes 32 columns by 8 rows
+
+use crate::app::{App, ConnectionStatus};
+use protocol::{BoardMode, CanvasResponse, Rgb};
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+
+const UPPER_HALF_BLOCK: &str = "▀";
+
+// The board as a widget, with the pixel under the cursor drawn in its inverse colour
+pub struct CanvasView<'a> {
+    pub canvas: &'a CanvasResponse,
+    pub cursor: (u32, u32),
+}
+
+impl CanvasView<'_> {
+    fn pixel_color(&self, x: u32, y: u32) -> Color {
+        let rgb = self.canvas.pixels
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .and_then(|hex| Rgb::parse_hex(hex).ok())
+            .unwrap_or(Rgb::BLACK);
+
+        if (x, y) == self.cursor {
+            Color::Rgb(255 - rgb.r, 255 - rgb.g, 255 - rgb.b)
+        } else {
+            Color::Rgb(rgb.r, rgb.g, rgb.b)
+        }
+    }
+}
+
+impl Widget for CanvasView<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let rows = self.canvas.height.div_ceil(2);
+
+        for row in 0..rows.min(area.height as u32) {
+            for x in 0..self.canvas.width.min(area.width as u32) {
+                let top = self.pixel_color(x, row * 2);
+                let bottom = if row * 2 + 1 < self.canvas.height {
+                    self.pixel_color(x, row * 2 + 1)
+                } else {
+                    Color::Reset
+                };
+
+                if let Some(cell) = buf.cell_mut((area.x + x as u16, area.y + row as u16)) {
+                    cell.set_symbol(UPPER_HALF_BLOCK).set_fg(top).set_bg(bottom);
+                }
+            }
+        }
+    }
+}
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let canvas_height = app.canvas.height.div_ceil(2) as u16 + 2; // + top/bottom border
+    let canvas_width = app.canvas.width as u16 + 2; // + left/right border
+
+    let [board_area, status_area, help_area] = Layout::vertical([
+        Constraint::Length(canvas_height),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [board_area, _] = Layout::horizontal([Constraint::Length(canvas_width), Constraint::Min(0)]).areas(board_area);
+
+    let block = Block::bordered().title(" RustyCanvas ");
+    let inner = block.inner(board_area);
+    frame.render_widget(block, board_area);
+    frame.render_widget(CanvasView { canvas: &app.canvas, cursor: app.cursor }, inner);
+
+    frame.render_widget(Paragraph::new(status_line(app)), status_area);
+    frame.render_widget(
+        Paragraph::new("arrows/hjkl move · space place · tab/1-8 colour · r refresh · q quit").dark_gray(),
+        help_area,
+    );
+}
+
+fn status_line(app: &App) -> Line<'static> {
+    let status = match &app.status {
+        ConnectionStatus::Connecting => Span::raw("● connecting").yellow(),
+        ConnectionStatus::Connected => Span::raw("● connected").green(),
+        ConnectionStatus::Disconnected(err) => Span::raw(format!("● disconnected: {}", err)).red(),
+    };
+
+    let selected = Rgb::parse_hex(app.selected_color()).unwrap_or(Rgb::BLACK);
+    let mut spans = vec![
+        status,
+    ];
+    match app.mode {
+        BoardMode::Open => {}
+        BoardMode::ReadOnly => spans.push(Span::raw("  board is read-only").yellow()),
+        BoardMode::Maintenance => spans.push(Span::raw("  board is in maintenance").yellow()),
+    }
+    spans.extend([
+        Span::raw(format!("  ({:>2}, {:>2})  ", app.cursor.0, app.cursor.1)),
+        Span::styled("  ", Style::new().bg(Color::Rgb(selected.r, selected.g, selected.b))),
+        Span::raw(format!(" {}", app.selected_color())),
+    ]);
+    if let Some(notice) = &app.notice {
+        spans.push(Span::raw(format!("  {}", notice)).dark_gray());
+    }
+    Line::from(spans)
+}
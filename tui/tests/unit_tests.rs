@@ -0,0 +1,119 @@
+// tests/unit_tests.rs
+
+// Unit tests: key handling, update application and half-block rendering (no terminal or server needed)
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use protocol::{BoardMode, PixelUpdate, PALETTE};
+use ratatui::{Terminal, backend::TestBackend, buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+use tui::app::{Action, App, ConnectionStatus, Message, blank_canvas};
+use tui::ui::{CanvasView, draw};
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+#[test]
+fn test_cursor_moves_and_clamps_to_board() {
+    let mut app = App::default();
+
+    app.handle_key(key(KeyCode::Left));
+    app.handle_key(key(KeyCode::Up));
+    assert_eq!(app.cursor, (0, 0));
+
+    app.handle_key(key(KeyCode::Right));
+    app.handle_key(key(KeyCode::Char('j')));
+    assert_eq!(app.cursor, (1, 1));
+
+    for _ in 0..100 {
+        app.handle_key(key(KeyCode::Right));
+        app.handle_key(key(KeyCode::Down));
+    }
+    assert_eq!(app.cursor, (31, 15));
+}
+
+#[test]
+fn test_palette_selection_and_place_action() {
+    let mut app = App::default();
+
+    app.handle_key(key(KeyCode::Char('3')));
+    assert_eq!(app.selected_color(), PALETTE[2]);
+
+    app.handle_key(key(KeyCode::Tab));
+    assert_eq!(app.selected_color(), PALETTE[3]);
+
+    app.handle_key(key(KeyCode::BackTab));
+    app.handle_key(key(KeyCode::BackTab));
+    app.handle_key(key(KeyCode::BackTab));
+    assert_eq!(app.selected_color(), PALETTE[0]);
+
+    app.move_cursor(4, 2);
+    let action = app.handle_key(key(KeyCode::Char(' ')));
+    assert_eq!(action, Some(Action::Place { x: 4, y: 2, color: PALETTE[0].to_string() }));
+
+    assert_eq!(app.handle_key(key(KeyCode::Char('r'))), Some(Action::Refresh));
+
+    app.handle_key(key(KeyCode::Char('q')));
+    assert!(app.should_quit);
+}
+
+#[test]
+fn test_messages_update_board_and_status() {
+    let mut app = App::default();
+    assert_eq!(app.status, ConnectionStatus::Connecting);
+
+    app.handle_message(Message::Updates(vec![
+        PixelUpdate { x: 2, y: 3, color: "#FF0000".to_string(), timestamp: 1 },
+        PixelUpdate { x: 99, y: 99, color: "#FF0000".to_string(), timestamp: 2 },
+    ]));
+    assert_eq!(app.canvas.pixels[3][2], "#FF0000");
+    assert_eq!(app.status, ConnectionStatus::Connected);
+
+    app.handle_message(Message::Error("connection refused".to_string()));
+    assert_eq!(app.status, ConnectionStatus::Disconnected("connection refused".to_string()));
+
+    app.handle_message(Message::Canvas(blank_canvas()));
+    assert_eq!(app.canvas.pixels[3][2], "#000000");
+    assert_eq!(app.status, ConnectionStatus::Connected);
+}
+
+#[test]
+fn test_canvas_view_packs_two_rows_per_cell() {
+    let mut canvas = blank_canvas();
+    canvas.pixels[0][0] = "#FF0000".to_string();
+    canvas.pixels[1][0] = "#0000FF".to_string();
+
+    let area = Rect::new(0, 0, 32, 8);
+    let mut buf = Buffer::empty(area);
+    CanvasView { canvas: &canvas, cursor: (5, 5) }.render(area, &mut buf);
+
+    let cell = &buf[(0, 0)];
+    assert_eq!(cell.symbol(), "▀");
+    assert_eq!(cell.fg, Color::Rgb(255, 0, 0));
+    assert_eq!(cell.bg, Color::Rgb(0, 0, 255));
+
+    // Cursor at (5, 5) is the bottom half of cell row 2, drawn inverted (black -> white)
+    assert_eq!(buf[(5, 2)].bg, Color::Rgb(255, 255, 255));
+    assert_eq!(buf[(5, 2)].fg, Color::Rgb(0, 0, 0));
+}
+
+#[test]
+fn test_draw_shows_status_line() {
+    let mut app = App::default();
+    app.handle_message(Message::Canvas(blank_canvas()));
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+    terminal.draw(|frame| draw(frame, &app)).unwrap();
+
+    let buffer = terminal.backend().buffer();
+    let status_row: String = (0..80).map(|x| buffer[(x, 10)].symbol()).collect();
+    assert!(status_row.contains("connected"), "status row was {:?}", status_row);
+
+    assert!(!status_row.contains("read-only"), "status row was {:?}", status_row);
+
+    // A frozen board is called out
+    app.handle_message(Message::Mode(BoardMode::ReadOnly));
+    terminal.draw(|frame| draw(frame, &app)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let status_row: String = (0..80).map(|x| buffer[(x, 10)].symbol()).collect();
+    assert!(status_row.contains("board is read-only"), "status row was {:?}", status_row);
+}